@@ -0,0 +1,98 @@
+//! Compares the SWAR string and whitespace scanners against their scalar
+//! fallbacks on the test fixtures, then reports end-to-end tokenizer
+//! throughput.
+//!
+//! Run with `cargo bench --bench scan`.
+
+use rust_json::scan;
+use rust_json::token::parse_to_tokens;
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 2000;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+/// Offsets of the first content byte of every string in `bytes`.
+fn string_starts(bytes: &[u8]) -> Vec<usize> {
+    let mut starts = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'"' {
+            starts.push(index + 1);
+            index = scan::find_string_end(bytes, index + 1).unwrap();
+        }
+        index += 1;
+    }
+    starts
+}
+
+fn find_string_ends(bytes: &[u8], starts: &[usize], find: fn(&[u8], usize) -> Option<usize>) {
+    for &start in starts {
+        let mut index = start;
+        while let Some(at) = find(bytes, index) {
+            if bytes[at] == b'"' {
+                break;
+            }
+            index = at + 2;
+        }
+        black_box(index);
+    }
+}
+
+/// Skips every whitespace run in `bytes`, one token byte at a time.
+fn skip_all_whitespace(bytes: &[u8], skip: fn(&[u8], usize) -> usize) {
+    let mut index = 0;
+    while index < bytes.len() {
+        index = skip(bytes, index) + 1;
+    }
+    black_box(index);
+}
+
+fn throughput(len: usize, elapsed: Duration) -> f64 {
+    len as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0)
+}
+
+fn main() {
+    for i in 1..=3 {
+        let path = format!("tests/json{}.json", i);
+        let text = fs::read_to_string(&path).expect("fixture should be readable");
+        let bytes = text.as_bytes();
+        let starts = string_starts(bytes);
+
+        let scalar =
+            time(|| find_string_ends(bytes, &starts, scan::scalar::find_quote_or_backslash));
+        let swar = time(|| find_string_ends(bytes, &starts, scan::find_quote_or_backslash));
+        let scalar_whitespace = time(|| skip_all_whitespace(bytes, scan::scalar::skip_whitespace));
+        let swar_whitespace = time(|| skip_all_whitespace(bytes, scan::skip_whitespace));
+        let tokenize = time(|| {
+            black_box(parse_to_tokens(black_box(&text)).unwrap());
+        });
+
+        println!("{} ({} bytes)", path, bytes.len());
+        println!(
+            "  strings   scalar {:>10?}  swar {:>10?}  ({:.2}x)",
+            scalar,
+            swar,
+            scalar.as_secs_f64() / swar.as_secs_f64()
+        );
+        println!(
+            "  spaces    scalar {:>10?}  swar {:>10?}  ({:.2}x)",
+            scalar_whitespace,
+            swar_whitespace,
+            scalar_whitespace.as_secs_f64() / swar_whitespace.as_secs_f64()
+        );
+        println!(
+            "  tokenize  {:>10?}  {:.1} MiB/s",
+            tokenize,
+            throughput(bytes.len(), tokenize)
+        );
+    }
+}
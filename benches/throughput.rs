@@ -0,0 +1,42 @@
+//! Tokenize, parse and serialize throughput on the test fixtures.
+//!
+//! Run with `cargo bench --bench throughput`; Criterion reports each
+//! measurement in MiB/s. `rust_json bench <count>` gives a quicker reading
+//! without Criterion.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rust_json::parser::Parser;
+use rust_json::token::parse_to_tokens;
+use std::fs;
+
+fn fixtures() -> Vec<(String, String)> {
+    (1..=3)
+        .map(|i| {
+            let name = format!("json{}", i);
+            let text = fs::read_to_string(format!("tests/{}.json", name))
+                .expect("fixture should be readable");
+            (name, text)
+        })
+        .collect()
+}
+
+fn throughput(c: &mut Criterion) {
+    for (name, text) in fixtures() {
+        let tokens = parse_to_tokens(&text).unwrap();
+        let value = Parser::new(&tokens).parse().unwrap();
+
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function("tokenize", |b| {
+            b.iter(|| parse_to_tokens(black_box(&text)).unwrap())
+        });
+        group.bench_function("parse", |b| {
+            b.iter(|| Parser::new(black_box(&tokens)).parse().unwrap())
+        });
+        group.bench_function("serialize", |b| b.iter(|| black_box(&value).to_string()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
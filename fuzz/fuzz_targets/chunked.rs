@@ -0,0 +1,66 @@
+//! Runs arbitrary bytes through the readers that consume input piece by
+//! piece: the push parser and `from_reader` in chunks whose size comes from
+//! the first byte, `EventReader::skip_value`, JSON Lines and the stream
+//! deserializer. Errors are fine; panics are not.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_json::event::{EventReader, JsonEvent};
+use rust_json::lines::{JsonLines, JsonLinesReader};
+use rust_json::push::PushParser;
+use rust_json::read::from_reader;
+use rust_json::stream::StreamDeserializer;
+use std::io::Read;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, data)) = data.split_first() else {
+        return;
+    };
+    let size = usize::from(size.max(1));
+
+    let mut parser = PushParser::new();
+    let fed = data
+        .chunks(size)
+        .try_for_each(|chunk| parser.feed(chunk).map(drop));
+    if fed.is_ok() {
+        let _ = parser.finish();
+    }
+    let _ = from_reader(Chunks { data, size });
+    JsonLinesReader::new(data).for_each(drop);
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    JsonLines::new(text).for_each(drop);
+    let mut stream = StreamDeserializer::new(text);
+    while let Some(Ok(_)) = stream.next() {
+        assert!(stream.byte_offset() <= text.len());
+    }
+
+    // Skip every other value that starts inside an array.
+    let mut reader = EventReader::new(text);
+    let mut skip = false;
+    while let Ok(Some(event)) = reader.next_event() {
+        if event == JsonEvent::StartArray {
+            skip = !skip;
+            if skip && reader.skip_value().is_err() {
+                break;
+            }
+        }
+    }
+});
+
+/// Hands out `data` at most `size` bytes per read.
+struct Chunks<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Read for Chunks<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.data.len()).min(self.size);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
@@ -0,0 +1,41 @@
+//! Checks that `from_reader`, which goes through the push parser in chunks
+//! whose size comes from the first byte, accepts exactly what the strict
+//! tokenizer and `Parser` accept, and builds the same value.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_json::parser::Parser;
+use rust_json::read::from_reader;
+use rust_json::token::parse_bytes_to_tokens;
+use std::io::Read;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, data)) = data.split_first() else {
+        return;
+    };
+    let parsed = match parse_bytes_to_tokens(data) {
+        Ok(tokens) => Parser::new(&tokens)
+            .parse()
+            .ok()
+            .map(|value| value.into_owned()),
+        Err(_) => None,
+    };
+    let size = usize::from(size.max(1));
+    let read = from_reader(Chunks { data, size }).ok();
+    assert_eq!(read, parsed, "{:?}", String::from_utf8_lossy(data));
+});
+
+/// Hands out `data` at most `size` bytes per read.
+struct Chunks<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Read for Chunks<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.data.len()).min(self.size);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
@@ -0,0 +1,65 @@
+//! Wraps arbitrary text in up to 65535 levels of arrays and objects, chosen
+//! by the first bytes, and runs it through every parser. Past
+//! `parser::MAX_DEPTH` each must fail with `NestingTooDeep` rather than
+//! overflow the stack.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_json::cst::CstDocument;
+use rust_json::error::JsonParserError;
+use rust_json::event::EventReader;
+use rust_json::parser::{Parser, MAX_DEPTH};
+use rust_json::push::PushParser;
+use rust_json::read::from_reader;
+use rust_json::recover::Recovered;
+use rust_json::span::SpannedValue;
+use rust_json::token::parse_to_tokens;
+
+fuzz_target!(|data: &[u8]| {
+    let [high, low, kinds, rest @ ..] = data else {
+        return;
+    };
+    let Ok(inner) = std::str::from_utf8(rest) else {
+        return;
+    };
+    let depth = usize::from(u16::from_be_bytes([*high, *low]));
+    let mut text = String::new();
+    for level in 0..depth {
+        // Each bit of `kinds` picks an array or an object, cycling.
+        text.push_str(if kinds >> (level % 8) & 1 == 0 {
+            "["
+        } else {
+            "{\"\":"
+        });
+    }
+    text.push_str(inner);
+    for level in (0..depth).rev() {
+        text.push(if kinds >> (level % 8) & 1 == 0 {
+            ']'
+        } else {
+            '}'
+        });
+    }
+
+    let too_deep = depth > MAX_DEPTH;
+    if let Ok(tokens) = parse_to_tokens(&text) {
+        let result = Parser::new(&tokens).parse();
+        if too_deep {
+            assert_eq!(result.unwrap_err(), JsonParserError::NestingTooDeep);
+        }
+    }
+    let mut parser = PushParser::new();
+    if parser.feed(text.as_bytes()).is_ok() {
+        let _ = parser.finish();
+    }
+    let read = from_reader(text.as_bytes());
+    let spanned = SpannedValue::parse(&text);
+    let cst = CstDocument::parse(&text);
+    let recovered = Recovered::parse(&text);
+    let mut reader = EventReader::new(&text);
+    let events = reader.read_value();
+    if too_deep {
+        assert!(read.is_err() && spanned.is_err() && cst.is_err() && events.is_err());
+        assert!(!recovered.is_ok());
+    }
+});
@@ -0,0 +1,25 @@
+//! Runs arbitrary UTF-8 through every parser, including the error reporting
+//! paths. Errors are fine; panics are not.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_json::cst::CstDocument;
+use rust_json::parser::Parser;
+use rust_json::recover::Recovered;
+use rust_json::span::SpannedValue;
+use rust_json::token::{parse_json5_to_tokens, parse_to_tokens};
+
+fuzz_target!(|text: &str| {
+    let strict = parse_to_tokens(text).map(|tokens| Parser::new(&tokens).parse().is_ok());
+    if let Ok(tokens) = parse_json5_to_tokens(text) {
+        let _ = Parser::new_json5(&tokens).parse();
+    }
+    let _ = SpannedValue::parse(text);
+    let _ = CstDocument::parse(text);
+
+    let recovered = Recovered::parse(text);
+    for diagnostic in &recovered.diagnostics {
+        let _ = diagnostic.report(text).to_string();
+    }
+    assert_eq!(recovered.is_ok(), strict == Ok(true), "{:?}", text);
+});
@@ -0,0 +1,19 @@
+//! Feeds arbitrary bytes to every tokenizer. Errors are fine; panics are not.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_json::token::{
+    parse_bytes_to_tokens, parse_bytes_to_tokens_lossy, parse_json5_to_tokens, Tokenizer,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_bytes_to_tokens(data);
+    let _ = parse_bytes_to_tokens_lossy(data);
+    let mut tokenizer = Tokenizer::from_bytes(data);
+    while let Some(Ok((span, _))) = tokenizer.next_spanned() {
+        assert!(span.start <= span.end && span.end <= data.len());
+    }
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = parse_json5_to_tokens(text);
+    }
+});
@@ -0,0 +1,265 @@
+//! The `#[json(...)]` attributes.
+
+use syn::ext::IdentExt;
+use syn::{Attribute, Error, Field, Ident, LitStr, Path, Result};
+
+/// Attributes on the struct or enum itself.
+#[derive(Default)]
+pub struct Container {
+    pub rename_all: Option<RenameRule>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+}
+
+/// How an enum says which variant a value is.
+pub enum Tagging {
+    /// `"Unit"` or `{"Variant": content}`.
+    External,
+    /// `{"tag": "Variant", ...fields}`.
+    Internal { tag: String },
+    /// `{"tag": "Variant", "content": content}`.
+    Adjacent { tag: String, content: String },
+    /// Just the content; the first variant that fits wins.
+    Untagged,
+}
+
+impl Container {
+    pub fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container::default();
+        for attr in json_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    let rule: LitStr = meta.value()?.parse()?;
+                    container.rename_all = Some(RenameRule::parse(&rule)?);
+                } else if meta.path.is_ident("tag") {
+                    container.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("content") {
+                    container.content = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("untagged") {
+                    container.untagged = true;
+                } else {
+                    return Err(meta.error("unknown json container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(container)
+    }
+
+    pub fn tagging(&self, name: &Ident) -> Result<Tagging> {
+        match (self.untagged, &self.tag, &self.content) {
+            (false, None, None) => Ok(Tagging::External),
+            (false, Some(tag), None) => Ok(Tagging::Internal { tag: tag.clone() }),
+            (false, Some(tag), Some(content)) => Ok(Tagging::Adjacent {
+                tag: tag.clone(),
+                content: content.clone(),
+            }),
+            (true, None, None) => Ok(Tagging::Untagged),
+            (false, None, Some(_)) => Err(Error::new_spanned(
+                name,
+                "#[json(content = \"...\")] needs #[json(tag = \"...\")] as well",
+            )),
+            (true, _, _) => Err(Error::new_spanned(
+                name,
+                "#[json(untagged)] cannot be combined with tag or content",
+            )),
+        }
+    }
+
+    /// Rejects attributes that only make sense on enums.
+    pub fn check_struct(&self, name: &Ident) -> Result<()> {
+        if self.untagged || self.tag.is_some() || self.content.is_some() {
+            return Err(Error::new_spanned(
+                name,
+                "tag, content and untagged only apply to enums",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What to use for a field that is missing from the input.
+pub enum FieldDefault {
+    /// Fail, unless the type has its own meaning for absence (`Option`).
+    None,
+    /// `Default::default()`.
+    Default,
+    /// Call the given function.
+    Path(Path),
+}
+
+/// Attributes on a named field.
+pub struct FieldAttrs {
+    pub rename: Option<String>,
+    pub default: FieldDefault,
+    pub skip: bool,
+    pub flatten: bool,
+}
+
+impl FieldAttrs {
+    pub fn from_field(field: &Field) -> Result<Self> {
+        let mut attrs = FieldAttrs {
+            rename: None,
+            default: FieldDefault::None,
+            skip: false,
+            flatten: false,
+        };
+        for attr in json_attrs(&field.attrs) {
+            if field.ident.is_none() {
+                return Err(Error::new_spanned(
+                    attr,
+                    "json attributes are only supported on named fields",
+                ));
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    attrs.default = match meta.value() {
+                        Ok(value) => FieldDefault::Path(value.parse::<LitStr>()?.parse()?),
+                        Err(_) => FieldDefault::Default,
+                    };
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                } else {
+                    return Err(meta.error("unknown json field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        if attrs.flatten && (attrs.rename.is_some() || attrs.skip) {
+            return Err(Error::new_spanned(
+                field,
+                "#[json(flatten)] cannot be combined with rename or skip",
+            ));
+        }
+        Ok(attrs)
+    }
+}
+
+/// The name a variant is written as.
+pub fn variant_name(
+    attrs: &[Attribute],
+    ident: &Ident,
+    rule: Option<&RenameRule>,
+) -> Result<String> {
+    let mut rename = None;
+    for attr in json_attrs(attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown json variant attribute"))
+            }
+        })?;
+    }
+    let name = ident.unraw().to_string();
+    Ok(rename.unwrap_or_else(|| match rule {
+        Some(rule) => rule.apply_to_variant(&name),
+        None => name,
+    }))
+}
+
+/// The name a field is written as.
+pub fn field_name(attrs: &FieldAttrs, ident: &Ident, rule: Option<&RenameRule>) -> String {
+    let name = ident.unraw().to_string();
+    attrs.rename.clone().unwrap_or_else(|| match rule {
+        Some(rule) => rule.apply_to_field(&name),
+        None => name,
+    })
+}
+
+fn json_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("json"))
+}
+
+/// `#[json(rename_all = "...")]`: fields are assumed to be `snake_case` and
+/// variants `PascalCase`, as is usual in Rust.
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => {
+                return Err(Error::new_spanned(
+                    rule,
+                    "expected one of lowercase, UPPERCASE, PascalCase, camelCase, \
+                     snake_case, SCREAMING_SNAKE_CASE, kebab-case, SCREAMING-KEBAB-CASE",
+                ))
+            }
+        })
+    }
+
+    fn apply_to_field(self, name: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => name.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => name.to_ascii_uppercase(),
+            RenameRule::Pascal | RenameRule::Camel => {
+                let mut renamed = String::new();
+                let mut capitalize = matches!(self, RenameRule::Pascal);
+                for char in name.chars() {
+                    if char == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        renamed.push(char.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        renamed.push(char);
+                    }
+                }
+                renamed
+            }
+            RenameRule::Kebab => name.replace('_', "-"),
+            RenameRule::ScreamingKebab => name.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+
+    fn apply_to_variant(self, name: &str) -> String {
+        let snake = || {
+            let mut snake = String::new();
+            for (index, char) in name.char_indices() {
+                if char.is_uppercase() && index > 0 {
+                    snake.push('_');
+                }
+                snake.push(char.to_ascii_lowercase());
+            }
+            snake
+        };
+        match self {
+            RenameRule::Pascal => name.to_string(),
+            RenameRule::Lower => name.to_ascii_lowercase(),
+            RenameRule::Upper => name.to_ascii_uppercase(),
+            RenameRule::Camel => {
+                let mut chars = name.chars();
+                let first = chars.next().map(|char| char.to_ascii_lowercase());
+                first.into_iter().chain(chars).collect()
+            }
+            RenameRule::Snake => snake(),
+            RenameRule::ScreamingSnake => snake().to_ascii_uppercase(),
+            RenameRule::Kebab => snake().replace('_', "-"),
+            RenameRule::ScreamingKebab => snake().replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::attr::{self, Container, FieldDefault, Tagging};
+use crate::{fields, FieldInfo, Shape};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_quote, Data, DataEnum, DeriveInput, Error, GenericParam, Ident, LifetimeParam, Result,
+};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            container.check_struct(name)?;
+            let (shape, fields) = fields(&data.fields, container.rename_all.as_ref())?;
+            build(&quote!(Self), shape, &fields, &quote!(value))
+        }
+        Data::Enum(data) => {
+            let tagging = container.tagging(name)?;
+            enumeration(name, data, &tagging, container.rename_all.as_ref())?
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "FromJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    // `impl<'__json, 'a, T> FromJson<'__json> for Name<'a, T>
+    //  where '__json: 'a, T: FromJson<'__json>`
+    let mut generics = input.generics.clone();
+    let lifetimes: Vec<_> = generics
+        .lifetimes()
+        .map(|param| param.lifetime.clone())
+        .collect();
+    let mut json: LifetimeParam = parse_quote!('__json);
+    json.bounds.extend(lifetimes);
+    generics.params.insert(0, GenericParam::Lifetime(json));
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::rust_json::convert::FromJson<'__json>));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rust_json::convert::FromJson<'__json> for #name #ty_generics #where_clause {
+            fn from_json(
+                value: &'__json ::rust_json::value::JsonValue<'_>,
+            ) -> ::std::result::Result<Self, ::rust_json::convert::FromJsonError> {
+                #body
+            }
+        }
+    })
+}
+
+/// `Ok(value)` or `break '__build Err(error)`, mapping the error with `map`.
+fn try_from_json(value: TokenStream, map: TokenStream) -> TokenStream {
+    quote! {
+        match ::rust_json::convert::FromJson::from_json(#value) {
+            ::std::result::Result::Ok(__value) => __value,
+            ::std::result::Result::Err(__error) => {
+                break '__build ::std::result::Result::Err(__error #map)
+            }
+        }
+    }
+}
+
+fn default(default: &FieldDefault) -> TokenStream {
+    match default {
+        FieldDefault::None | FieldDefault::Default => quote!(::std::default::Default::default()),
+        FieldDefault::Path(path) => quote!(#path()),
+    }
+}
+
+/// Reads a named field out of `__object`, or the whole of `value` if it is
+/// flattened.
+fn field(field: &FieldInfo, value: &TokenStream) -> TokenStream {
+    if field.attrs.skip {
+        return default(&field.attrs.default);
+    }
+    if field.attrs.flatten {
+        return try_from_json(value.clone(), quote!());
+    }
+    let name = &field.name;
+    let ty = field.ty;
+    let present = try_from_json(quote!(__member), quote!(.at_key(#name)));
+    let missing = match &field.attrs.default {
+        FieldDefault::None => quote! {
+            match <#ty as ::rust_json::convert::FromJson<'_>>::from_missing() {
+                ::std::option::Option::Some(__value) => __value,
+                ::std::option::Option::None => break '__build ::std::result::Result::Err(
+                    ::rust_json::convert::FromJsonError::missing_field(#name),
+                ),
+            }
+        },
+        default => self::default(default),
+    };
+    quote! {
+        match __object.get(#name) {
+            ::std::option::Option::Some(__member) => #present,
+            ::std::option::Option::None => #missing,
+        }
+    }
+}
+
+/// An expression building `path` out of `value`, evaluating to a `Result`.
+fn build(
+    path: &TokenStream,
+    shape: Shape,
+    fields: &[FieldInfo],
+    value: &TokenStream,
+) -> TokenStream {
+    match shape {
+        Shape::Named => {
+            let members = fields.iter().map(|field| &field.member);
+            let values = fields.iter().map(|info| field(info, value));
+            quote! {
+                '__build: {
+                    let __object = match #value {
+                        ::rust_json::value::JsonValue::Object(__object) => __object,
+                        __other => break '__build ::std::result::Result::Err(
+                            ::rust_json::convert::FromJsonError::invalid_type("object", __other),
+                        ),
+                    };
+                    ::std::result::Result::Ok(#path { #(#members: #values,)* })
+                }
+            }
+        }
+        Shape::Tuple if fields.len() == 1 => quote! {
+            ::rust_json::convert::FromJson::from_json(#value).map(#path)
+        },
+        Shape::Tuple => {
+            let length = fields.len();
+            let values = (0..length)
+                .map(|index| try_from_json(quote!(&__array[#index]), quote!(.at_index(#index))));
+            quote! {
+                '__build: {
+                    let __array = match #value {
+                        ::rust_json::value::JsonValue::Array(__array) => &__array.array,
+                        __other => break '__build ::std::result::Result::Err(
+                            ::rust_json::convert::FromJsonError::invalid_type("array", __other),
+                        ),
+                    };
+                    if __array.len() != #length {
+                        break '__build ::std::result::Result::Err(
+                            ::rust_json::convert::FromJsonError::new(
+                                ::rust_json::convert::FromJsonErrorKind::InvalidLength {
+                                    expected: #length,
+                                    found: __array.len(),
+                                },
+                            ),
+                        );
+                    }
+                    ::std::result::Result::Ok(#path(#(#values),*))
+                }
+            }
+        }
+        Shape::Unit => quote! {
+            match #value {
+                ::rust_json::value::JsonValue::Null => ::std::result::Result::Ok(#path),
+                __other => ::std::result::Result::Err(
+                    ::rust_json::convert::FromJsonError::invalid_type("null", __other),
+                ),
+            }
+        },
+    }
+}
+
+struct VariantInfo<'a> {
+    ident: &'a Ident,
+    name: String,
+    shape: Shape,
+    fields: Vec<FieldInfo<'a>>,
+}
+
+impl VariantInfo<'_> {
+    fn build(&self, value: &TokenStream) -> TokenStream {
+        let ident = self.ident;
+        build(&quote!(Self::#ident), self.shape, &self.fields, value)
+    }
+}
+
+fn enumeration(
+    name: &Ident,
+    data: &DataEnum,
+    tagging: &Tagging,
+    rule: Option<&attr::RenameRule>,
+) -> Result<TokenStream> {
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let (shape, fields) = fields(&variant.fields, None)?;
+            if shape == Shape::Tuple
+                && fields.len() != 1
+                && matches!(tagging, Tagging::Internal { .. })
+            {
+                return Err(Error::new_spanned(
+                    variant,
+                    "internally tagged enums cannot have tuple variants",
+                ));
+            }
+            Ok(VariantInfo {
+                ident: &variant.ident,
+                name: attr::variant_name(&variant.attrs, &variant.ident, rule)?,
+                shape,
+                fields,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let names: Vec<_> = variants.iter().map(|variant| &variant.name).collect();
+    let unknown = quote! {
+        __other => ::std::result::Result::Err(
+            ::rust_json::convert::FromJsonError::unknown_variant(__other, VARIANTS),
+        ),
+    };
+
+    let body = match tagging {
+        Tagging::External => {
+            let units = variants
+                .iter()
+                .filter(|variant| variant.shape == Shape::Unit)
+                .map(|variant| {
+                    let name = &variant.name;
+                    let ident = variant.ident;
+                    quote!(#name => ::std::result::Result::Ok(Self::#ident),)
+                });
+            let content = quote!(&__member.value);
+            let arms = variants.iter().map(|variant| {
+                let name = &variant.name;
+                let build = variant.build(&content);
+                quote!(#name => (#build).map_err(|__error| __error.at_key(#name)),)
+            });
+            quote! {
+                match value {
+                    ::rust_json::value::JsonValue::String(__raw) => {
+                        match &*::rust_json::escape::unescape(__raw) {
+                            #(#units)*
+                            #unknown
+                        }
+                    }
+                    ::rust_json::value::JsonValue::Object(__object) => {
+                        match (__object.iter().next(), __object.len()) {
+                            (::std::option::Option::Some(__member), 1) => {
+                                match &*::rust_json::escape::unescape(&__member.key) {
+                                    #(#arms)*
+                                    #unknown
+                                }
+                            }
+                            _ => ::std::result::Result::Err(
+                                ::rust_json::convert::FromJsonError::invalid_type(
+                                    "string or object with one member",
+                                    value,
+                                ),
+                            ),
+                        }
+                    }
+                    __other => ::std::result::Result::Err(
+                        ::rust_json::convert::FromJsonError::invalid_type(
+                            "string or object with one member",
+                            __other,
+                        ),
+                    ),
+                }
+            }
+        }
+        Tagging::Internal { tag } => {
+            let arms = variants.iter().map(|variant| {
+                let name = &variant.name;
+                let ident = variant.ident;
+                let build = match variant.shape {
+                    // Objects are merged with the tag, anything else is kept
+                    // in a member named after the variant.
+                    Shape::Tuple => quote! {
+                        match ::rust_json::convert::FromJson::from_json(value) {
+                            ::std::result::Result::Ok(__value) => {
+                                ::std::result::Result::Ok(Self::#ident(__value))
+                            }
+                            ::std::result::Result::Err(__error) => {
+                                match __object.get(#name) {
+                                    ::std::option::Option::Some(__member) => {
+                                        ::rust_json::convert::FromJson::from_json(__member)
+                                            .map(Self::#ident)
+                                            .map_err(|__error| __error.at_key(#name))
+                                    }
+                                    ::std::option::Option::None => ::std::result::Result::Err(__error),
+                                }
+                            }
+                        }
+                    },
+                    Shape::Named => variant.build(&quote!(value)),
+                    Shape::Unit => quote!(::std::result::Result::Ok(Self::#ident)),
+                };
+                quote!(#name => #build,)
+            });
+            let read_tag = read_tag(tag);
+            quote! {
+                #read_tag
+                match &*__tag {
+                    #(#arms)*
+                    __other => ::std::result::Result::Err(
+                        ::rust_json::convert::FromJsonError::unknown_variant(__other, VARIANTS)
+                            .at_key(#tag),
+                    ),
+                }
+            }
+        }
+        Tagging::Adjacent { tag, content } => {
+            let arms = variants.iter().map(|variant| {
+                let name = &variant.name;
+                let build = if variant.shape == Shape::Unit {
+                    let ident = variant.ident;
+                    quote!(::std::result::Result::Ok(Self::#ident))
+                } else {
+                    let build = variant.build(&quote!(__content));
+                    quote! {
+                        match __object.get(#content) {
+                            ::std::option::Option::Some(__content) => {
+                                (#build).map_err(|__error| __error.at_key(#content))
+                            }
+                            ::std::option::Option::None => ::std::result::Result::Err(
+                                ::rust_json::convert::FromJsonError::missing_field(#content),
+                            ),
+                        }
+                    }
+                };
+                quote!(#name => #build,)
+            });
+            let read_tag = read_tag(tag);
+            quote! {
+                #read_tag
+                match &*__tag {
+                    #(#arms)*
+                    __other => ::std::result::Result::Err(
+                        ::rust_json::convert::FromJsonError::unknown_variant(__other, VARIANTS)
+                            .at_key(#tag),
+                    ),
+                }
+            }
+        }
+        Tagging::Untagged => {
+            let attempts = variants.iter().map(|variant| {
+                let build = variant.build(&quote!(value));
+                quote! {{
+                    let __attempt: ::std::result::Result<Self, _> = #build;
+                    if let ::std::result::Result::Ok(__value) = __attempt {
+                        return ::std::result::Result::Ok(__value);
+                    }
+                }}
+            });
+            let message = format!("data did not match any variant of untagged enum {name}");
+            quote! {
+                #(#attempts)*
+                ::std::result::Result::Err(::rust_json::convert::FromJsonError::custom(#message))
+            }
+        }
+    };
+    Ok(quote! {
+        const VARIANTS: &[&str] = &[#(#names),*];
+        #body
+    })
+}
+
+/// Binds `__object` and the unescaped tag `__tag`, returning early if either
+/// is missing.
+fn read_tag(tag: &str) -> TokenStream {
+    quote! {
+        let __object = match value {
+            ::rust_json::value::JsonValue::Object(__object) => __object,
+            __other => return ::std::result::Result::Err(
+                ::rust_json::convert::FromJsonError::invalid_type("object", __other),
+            ),
+        };
+        let __tag = match __object.get(#tag) {
+            ::std::option::Option::Some(::rust_json::value::JsonValue::String(__raw)) => {
+                ::rust_json::escape::unescape(__raw)
+            }
+            ::std::option::Option::Some(__other) => return ::std::result::Result::Err(
+                ::rust_json::convert::FromJsonError::invalid_type("string", __other).at_key(#tag),
+            ),
+            ::std::option::Option::None => return ::std::result::Result::Err(
+                ::rust_json::convert::FromJsonError::missing_field(#tag),
+            ),
+        };
+    }
+}
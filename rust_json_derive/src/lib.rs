@@ -0,0 +1,141 @@
+//! `#[derive(ToJson, FromJson)]` for the `rust_json::convert` traits.
+//!
+//! ```
+//! use rust_json::convert::{FromJson, ToJson};
+//!
+//! #[derive(Debug, PartialEq, ToJson, FromJson)]
+//! #[json(rename_all = "camelCase")]
+//! struct Server {
+//!     host_name: String,
+//!     #[json(default)]
+//!     port: u16,
+//!     #[json(skip)]
+//!     connections: usize,
+//! }
+//!
+//! let server = Server { host_name: "example.com".into(), port: 80, connections: 3 };
+//! let value = server.to_json();
+//! assert_eq!(value.to_string(), r#"{"hostName":"example.com","port":80}"#);
+//! assert_eq!(
+//!     Server::from_json(&value).unwrap(),
+//!     Server { connections: 0, ..server }
+//! );
+//! ```
+//!
+//! Structs with named fields are objects, newtype structs are their one
+//! field, other tuple structs are arrays and unit structs are `null`.
+//!
+//! Container attributes:
+//! - `rename_all = "..."`: `camelCase`, `PascalCase`, `snake_case`,
+//!   `SCREAMING_SNAKE_CASE`, `kebab-case`, `SCREAMING-KEBAB-CASE`,
+//!   `lowercase` or `UPPERCASE`. Renames the fields of a struct, or the
+//!   variants of an enum.
+//! - `tag = "type"`: internally tagged enum, `{"type": "Variant", ...}`.
+//!   Tuple variants are not allowed. A newtype variant holding something that
+//!   is not an object puts it in a member named after the variant.
+//! - `tag = "t", content = "c"`: adjacently tagged, `{"t": "Variant", "c": ...}`.
+//! - `untagged`: just the content; reading tries each variant in order.
+//!
+//! Without any of those, enums are externally tagged: unit variants are
+//! strings and the others `{"Variant": content}`.
+//!
+//! Field attributes:
+//! - `rename = "..."`
+//! - `default`, or `default = "path::to::function"`: used when the member is
+//!   missing. `Option` fields are `None` when missing even without it.
+//! - `skip`: neither written nor read; filled in with the default.
+//! - `flatten`: the field's own members are inlined into the parent object.
+//!
+//! Variants accept `rename = "..."`.
+//!
+//! Errors name the path to the member that did not fit, e.g.
+//! `servers[1].port: expected u16, found string`.
+
+mod attr;
+mod from_json;
+mod to_json;
+
+use attr::{FieldAttrs, RenameRule};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, Error, Fields, Ident, Member, Result, Type};
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_json::expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_json::expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A field of a struct or variant, as the generated code sees it.
+struct FieldInfo<'a> {
+    member: Member,
+    /// The local variable the field is bound to in patterns.
+    binding: Ident,
+    /// The member name in JSON; unused for tuple fields.
+    name: String,
+    attrs: FieldAttrs,
+    ty: &'a Type,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Named,
+    Tuple,
+    Unit,
+}
+
+fn fields<'a>(
+    fields: &'a Fields,
+    rule: Option<&RenameRule>,
+) -> Result<(Shape, Vec<FieldInfo<'a>>)> {
+    let shape = match fields {
+        Fields::Named(_) => Shape::Named,
+        Fields::Unnamed(_) => Shape::Tuple,
+        Fields::Unit => Shape::Unit,
+    };
+    let infos = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let attrs = FieldAttrs::from_field(field)?;
+            let (member, name) = match &field.ident {
+                Some(ident) => (
+                    Member::Named(ident.clone()),
+                    attr::field_name(&attrs, ident, rule),
+                ),
+                None => (Member::Unnamed(index.into()), String::new()),
+            };
+            Ok(FieldInfo {
+                member,
+                binding: format_ident!("__field{}", index),
+                name,
+                attrs,
+                ty: &field.ty,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((shape, infos))
+}
+
+/// A pattern binding the fields of `path` that are not skipped.
+fn pattern(path: &TokenStream2, shape: Shape, fields: &[FieldInfo]) -> TokenStream2 {
+    let bound = fields.iter().filter(|field| !field.attrs.skip);
+    let members = bound.clone().map(|field| &field.member);
+    let bindings = bound.map(|field| &field.binding);
+    match shape {
+        Shape::Named => quote!(#path { #(#members: #bindings,)* .. }),
+        Shape::Tuple => quote!(#path(#(#bindings),*)),
+        Shape::Unit => quote!(#path),
+    }
+}
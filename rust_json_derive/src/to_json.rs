@@ -0,0 +1,190 @@
+use crate::attr::{self, Container, Tagging};
+use crate::{fields, pattern, FieldInfo, Shape};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Error, Result, Variant};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::from_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let rule = container.rename_all.as_ref();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            container.check_struct(name)?;
+            let (shape, fields) = fields(&data.fields, rule)?;
+            let value = content(shape, &fields);
+            if shape == Shape::Unit {
+                value
+            } else {
+                let pattern = pattern(&quote!(Self), shape, &fields);
+                quote! {
+                    let #pattern = self;
+                    #value
+                }
+            }
+        }
+        Data::Enum(data) if data.variants.is_empty() => quote!(match *self {}),
+        Data::Enum(data) => {
+            let tagging = container.tagging(name)?;
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let (shape, fields) = fields(&variant.fields, None)?;
+                    let ident = &variant.ident;
+                    let pattern = pattern(&quote!(Self::#ident), shape, &fields);
+                    let value = tagged(&tagging, variant, rule, shape, &fields)?;
+                    Ok(quote!(#pattern => #value,))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "ToJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::rust_json::convert::ToJson));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rust_json::convert::ToJson for #name #ty_generics #where_clause {
+            fn to_json(&self) -> ::rust_json::value::JsonValue<'_> {
+                #body
+            }
+        }
+    })
+}
+
+fn string(text: &str) -> TokenStream {
+    quote! {
+        ::rust_json::value::JsonValue::String(
+            ::std::boxed::Box::new(::rust_json::escape::escape(#text)),
+        )
+    }
+}
+
+/// A statement adding a member to `__members`.
+fn push(key: &str, value: TokenStream) -> TokenStream {
+    quote! {
+        __members.push(::rust_json::keyvalue::KeyValue {
+            key: ::rust_json::escape::escape(#key),
+            value: #value,
+        });
+    }
+}
+
+/// Adds the members of `value` to `__members`, or `value` itself as `key`
+/// when it is not an object.
+fn extend(key: &str, value: TokenStream) -> TokenStream {
+    let fallback = push(key, quote!(__value));
+    quote! {
+        match #value {
+            ::rust_json::value::JsonValue::Object(__object) => __members.extend(*__object),
+            __value => { #fallback }
+        }
+    }
+}
+
+fn object(statements: TokenStream) -> TokenStream {
+    quote! {{
+        let mut __members = ::std::vec::Vec::new();
+        #statements
+        ::rust_json::value::JsonValue::Object(
+            ::std::boxed::Box::new(::std::iter::FromIterator::from_iter(__members)),
+        )
+    }}
+}
+
+fn to_json(field: &FieldInfo) -> TokenStream {
+    let binding = &field.binding;
+    quote!(::rust_json::convert::ToJson::to_json(#binding))
+}
+
+fn members(fields: &[FieldInfo]) -> TokenStream {
+    fields
+        .iter()
+        .filter(|field| !field.attrs.skip)
+        .map(|field| {
+            if field.attrs.flatten {
+                extend(&field.name, to_json(field))
+            } else {
+                push(&field.name, to_json(field))
+            }
+        })
+        .collect()
+}
+
+/// The value of a struct or variant, without any tag.
+fn content(shape: Shape, fields: &[FieldInfo]) -> TokenStream {
+    match shape {
+        Shape::Named => object(members(fields)),
+        Shape::Tuple if fields.len() == 1 => to_json(&fields[0]),
+        Shape::Tuple => {
+            let elements = fields.iter().map(to_json);
+            quote! {
+                ::rust_json::value::JsonValue::Array(::std::boxed::Box::new(
+                    ::rust_json::array::JsonArray {
+                        array: ::std::vec![#(#elements),*],
+                    },
+                ))
+            }
+        }
+        Shape::Unit => quote!(::rust_json::value::JsonValue::Null),
+    }
+}
+
+fn tagged(
+    tagging: &Tagging,
+    variant: &Variant,
+    rule: Option<&attr::RenameRule>,
+    shape: Shape,
+    fields: &[FieldInfo],
+) -> Result<TokenStream> {
+    let name = attr::variant_name(&variant.attrs, &variant.ident, rule)?;
+    Ok(match tagging {
+        Tagging::External if shape == Shape::Unit => string(&name),
+        Tagging::External => object(push(&name, content(shape, fields))),
+        Tagging::Internal { tag } => {
+            let tag = push(tag, string(&name));
+            match shape {
+                Shape::Unit => object(tag),
+                Shape::Named => {
+                    let members = members(fields);
+                    object(quote!(#tag #members))
+                }
+                Shape::Tuple if fields.len() == 1 => {
+                    let members = extend(&name, to_json(&fields[0]));
+                    object(quote!(#tag #members))
+                }
+                Shape::Tuple => {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "internally tagged enums cannot have tuple variants",
+                    ))
+                }
+            }
+        }
+        Tagging::Adjacent { tag, content: key } => {
+            let tag = push(tag, string(&name));
+            if shape == Shape::Unit {
+                object(tag)
+            } else {
+                let content = push(key, content(shape, fields));
+                object(quote!(#tag #content))
+            }
+        }
+        Tagging::Untagged => content(shape, fields),
+    })
+}
@@ -0,0 +1,434 @@
+use rust_json::convert::{FromJson, FromJsonError, ToJson};
+use rust_json::escape::{escape, unescape};
+use rust_json::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+fn read<T: for<'v> FromJson<'v>>(text: &str) -> Result<T, FromJsonError> {
+    T::from_json(&text.parse::<JsonValue>().unwrap())
+}
+
+fn error<T: for<'v> FromJson<'v> + Debug>(text: &str) -> String {
+    read::<T>(text).unwrap_err().to_string()
+}
+
+/// Writes `value`, checks the text, and reads it back.
+fn round_trip<T: ToJson + for<'v> FromJson<'v> + PartialEq + Debug>(value: T, expected: &str) {
+    assert_eq!(value.to_json().to_string(), expected);
+    assert_eq!(read::<T>(expected).unwrap(), value);
+}
+
+/// A small non-negative integer, implemented by hand as the leaf of the
+/// derived types below.
+#[derive(Debug, Default, PartialEq)]
+struct Int(u8);
+
+impl ToJson for Int {
+    fn to_json(&self) -> JsonValue<'_> {
+        JsonValue::Number(Box::new(self.0.into()))
+    }
+}
+
+impl<'v> FromJson<'v> for Int {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(n) if n.fract() == 0.0 && (0.0..256.0).contains(&**n) => {
+                Ok(Int(**n as u8))
+            }
+            JsonValue::Number(n) => Err(FromJsonError::invalid_value("u8", n)),
+            _ => Err(FromJsonError::invalid_type("u8", value)),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Text(String);
+
+impl ToJson for Text {
+    fn to_json(&self) -> JsonValue<'_> {
+        JsonValue::String(Box::new(escape(&self.0)))
+    }
+}
+
+impl<'v> FromJson<'v> for Text {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::String(raw) => Ok(Text(unescape(raw).into_owned())),
+            _ => Err(FromJsonError::invalid_type("string", value)),
+        }
+    }
+}
+
+fn text(text: &str) -> Text {
+    Text(text.to_string())
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Port(Int);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Server {
+    host: Text,
+    port: Port,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Config {
+    primary: Server,
+    backup: Server,
+}
+
+#[test]
+fn test_struct_round_trip() {
+    round_trip(
+        Config {
+            primary: Server {
+                host: text("a \"b\""),
+                port: Port(Int(80)),
+            },
+            backup: Server {
+                host: text("c"),
+                port: Port(Int(81)),
+            },
+        },
+        r#"{"primary":{"host":"a \"b\"","port":80},"backup":{"host":"c","port":81}}"#,
+    );
+}
+
+#[test]
+fn test_escaped_keys() {
+    let server: Server = read(r#"{"h\u006fst": "a", "\u0070ort": 80}"#).unwrap();
+    assert_eq!(server.port, Port(Int(80)));
+    let value: Internal = read(r#"{"\u0074ype": "Struct", "a": 1}"#).unwrap();
+    assert_eq!(value, Internal::Struct { a: Int(1) });
+    let value: Adjacent = read(r#"{"t": "Struct", "\u0063": {"a": 1}}"#).unwrap();
+    assert_eq!(value, Adjacent::Struct { a: Int(1) });
+}
+
+#[test]
+fn test_error_paths() {
+    let config = r#"{
+        "primary": {"host": "a", "port": 1},
+        "backup": {"host": "b", "port": "80"}
+    }"#;
+    assert_eq!(
+        error::<Config>(config),
+        "backup.port: expected u8, found string"
+    );
+    assert_eq!(
+        error::<Config>(r#"{"primary": {"host": "a"}}"#),
+        "primary: missing field `port`"
+    );
+    assert_eq!(error::<Config>("[]"), "expected object, found array");
+}
+
+fn eight() -> Int {
+    Int(8)
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(rename_all = "kebab-case")]
+struct Attributes {
+    #[json(rename = "ID")]
+    id: Int,
+    max_size: Int,
+    #[json(default)]
+    retries: Int,
+    #[json(default = "eight")]
+    threads: Int,
+    #[json(skip)]
+    cache: Text,
+}
+
+#[test]
+fn test_field_attributes() {
+    let attributes = Attributes {
+        id: Int(1),
+        max_size: Int(2),
+        retries: Int(3),
+        threads: Int(4),
+        cache: Text::default(),
+    };
+    round_trip(
+        attributes,
+        r#"{"ID":1,"max-size":2,"retries":3,"threads":4}"#,
+    );
+    assert_eq!(
+        read::<Attributes>(r#"{"ID": 1, "max-size": 2, "cache": "x"}"#).unwrap(),
+        Attributes {
+            id: Int(1),
+            max_size: Int(2),
+            retries: Int(0),
+            threads: Int(8),
+            cache: Text::default(),
+        }
+    );
+    assert_eq!(
+        error::<Attributes>(r#"{"id": 1, "max-size": 2}"#),
+        "missing field `ID`"
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Page {
+    number: Int,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Response {
+    title: Text,
+    #[json(flatten)]
+    page: Page,
+}
+
+#[test]
+fn test_flatten() {
+    round_trip(
+        Response {
+            title: text("a"),
+            page: Page { number: Int(2) },
+        },
+        r#"{"title":"a","number":2}"#,
+    );
+    assert_eq!(
+        error::<Response>(r#"{"title": "a", "number": true}"#),
+        "number: expected u8, found boolean"
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Pair(Int, Text);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Unit;
+
+#[test]
+fn test_tuple_and_unit_structs() {
+    round_trip(Pair(Int(1), text("a")), r#"[1,"a"]"#);
+    round_trip(Unit, "null");
+    assert_eq!(
+        error::<Pair>(r#"[1]"#),
+        "expected an array of 2 elements, found 1"
+    );
+    assert_eq!(
+        error::<Pair>(r#"[1, 2]"#),
+        "[1]: expected string, found number"
+    );
+    assert_eq!(error::<Port>("700"), "expected u8, found 700");
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Wrapper<T> {
+    name: Text,
+    value: T,
+}
+
+#[test]
+fn test_generics() {
+    round_trip(
+        Wrapper {
+            name: text("a"),
+            value: Pair(Int(1), text("b")),
+        },
+        r#"{"name":"a","value":[1,"b"]}"#,
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(rename_all = "snake_case")]
+enum External {
+    Unit,
+    NewType(Int),
+    Tuple(Int, Int),
+    Struct {
+        a: Int,
+    },
+    #[json(rename = "other")]
+    Renamed,
+}
+
+#[test]
+fn test_externally_tagged() {
+    round_trip(External::Unit, r#""unit""#);
+    round_trip(External::NewType(Int(1)), r#"{"new_type":1}"#);
+    round_trip(External::Tuple(Int(1), Int(2)), r#"{"tuple":[1,2]}"#);
+    round_trip(External::Struct { a: Int(1) }, r#"{"struct":{"a":1}}"#);
+    round_trip(External::Renamed, r#""other""#);
+    assert_eq!(
+        read::<External>(r#"{"unit": null}"#).unwrap(),
+        External::Unit
+    );
+    assert_eq!(
+        error::<External>(r#""Unit""#),
+        "unknown variant `Unit`, expected one of `unit`, `new_type`, `tuple`, `struct`, `other`"
+    );
+    assert_eq!(
+        error::<External>(r#"{"struct": {"a": -1}}"#),
+        "struct.a: expected u8, found -1"
+    );
+    assert_eq!(
+        error::<External>(r#"{"unit": null, "other": null}"#),
+        "expected string or object with one member, found object"
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(tag = "type")]
+enum Internal {
+    Unit,
+    Struct { a: Int },
+    Page(Page),
+    Number(Int),
+}
+
+#[test]
+fn test_internally_tagged() {
+    round_trip(Internal::Unit, r#"{"type":"Unit"}"#);
+    round_trip(Internal::Struct { a: Int(1) }, r#"{"type":"Struct","a":1}"#);
+    round_trip(
+        Internal::Page(Page { number: Int(1) }),
+        r#"{"type":"Page","number":1}"#,
+    );
+    round_trip(Internal::Number(Int(1)), r#"{"type":"Number","Number":1}"#);
+    assert_eq!(error::<Internal>(r#"{"a": 1}"#), "missing field `type`");
+    assert_eq!(
+        error::<Internal>(r#"{"type": 1}"#),
+        "type: expected string, found number"
+    );
+    assert_eq!(
+        error::<Internal>(r#"{"type": "Struct", "a": null}"#),
+        "a: expected u8, found null"
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(tag = "t", content = "c")]
+enum Adjacent {
+    Unit,
+    Tuple(Int, Text),
+    Struct { a: Int },
+}
+
+#[test]
+fn test_adjacently_tagged() {
+    round_trip(Adjacent::Unit, r#"{"t":"Unit"}"#);
+    round_trip(
+        Adjacent::Tuple(Int(1), text("x")),
+        r#"{"t":"Tuple","c":[1,"x"]}"#,
+    );
+    round_trip(
+        Adjacent::Struct { a: Int(1) },
+        r#"{"t":"Struct","c":{"a":1}}"#,
+    );
+    assert_eq!(error::<Adjacent>(r#"{"t": "Struct"}"#), "missing field `c`");
+    assert_eq!(
+        error::<Adjacent>(r#"{"t": "Tuple", "c": [1, 2]}"#),
+        "c[1]: expected string, found number"
+    );
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+#[json(untagged)]
+enum Untagged {
+    Number(Int),
+    Text(Text),
+    Point { x: Int, y: Int },
+    Nothing,
+}
+
+#[test]
+fn test_untagged() {
+    round_trip(Untagged::Number(Int(1)), "1");
+    round_trip(Untagged::Text(text("a")), r#""a""#);
+    round_trip(
+        Untagged::Point {
+            x: Int(1),
+            y: Int(2),
+        },
+        r#"{"x":1,"y":2}"#,
+    );
+    round_trip(Untagged::Nothing, "null");
+    assert_eq!(
+        error::<Untagged>("[]"),
+        "data did not match any variant of untagged enum Untagged"
+    );
+}
+
+// The types below use the `FromJson` and `ToJson` impls of std types from
+// `rust_json::convert` instead of hand-written leaves.
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Listen(u16);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Host {
+    host: String,
+    port: Listen,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Hosts {
+    servers: Vec<Host>,
+    comment: Option<String>,
+}
+
+#[test]
+fn test_std_fields_round_trip() {
+    round_trip(
+        Hosts {
+            servers: vec![Host {
+                host: "a \"b\"".to_string(),
+                port: Listen(80),
+                tags: vec!["x".to_string()],
+            }],
+            comment: Some("hi".to_string()),
+        },
+        r#"{"servers":[{"host":"a \"b\"","port":80,"tags":["x"]}],"comment":"hi"}"#,
+    );
+    round_trip(
+        Hosts {
+            servers: vec![],
+            comment: None,
+        },
+        r#"{"servers":[],"comment":null}"#,
+    );
+}
+
+#[test]
+fn test_missing_option_is_none() {
+    let hosts: Hosts = read(r#"{"servers": [], "unknown": 1}"#).unwrap();
+    assert_eq!(hosts.comment, None);
+}
+
+#[test]
+fn test_std_error_paths() {
+    let servers = r#"{"servers": [
+        {"host": "a", "port": 1, "tags": []},
+        {"host": "b", "port": "80", "tags": []}
+    ]}"#;
+    assert_eq!(
+        error::<Hosts>(servers),
+        "servers[1].port: expected u16, found string"
+    );
+    assert_eq!(
+        error::<Hosts>(r#"{"servers": [{"host": "a", "port": 1}]}"#),
+        "servers[0]: missing field `tags`"
+    );
+    assert_eq!(error::<Listen>("70000"), "expected u16, found 70000");
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Borrowed<'a, T> {
+    name: Cow<'a, str>,
+    value: T,
+}
+
+#[test]
+fn test_generics_and_lifetimes() {
+    let value: JsonValue = r#"{"name": "plain", "value": [true]}"#.parse().unwrap();
+    let borrowed = Borrowed::<Vec<bool>>::from_json(&value).unwrap();
+    assert!(matches!(borrowed.name, Cow::Borrowed("plain")));
+    assert_eq!(borrowed.value, vec![true]);
+    assert_eq!(borrowed.to_json(), value);
+}
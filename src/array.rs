@@ -2,7 +2,7 @@ use crate::value::JsonValue;
 use std::fmt::{Display, Formatter};
 use std::slice::Iter;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct JsonArray<'a> {
     pub array: Vec<JsonValue<'a>>,
 }
@@ -26,6 +26,12 @@ impl<'a> JsonArray<'a> {
         let iter = self.array.iter();
         JsonArrayIter { iter }
     }
+
+    pub fn into_owned(self) -> JsonArray<'static> {
+        JsonArray {
+            array: self.array.into_iter().map(JsonValue::into_owned).collect(),
+        }
+    }
 }
 
 pub struct JsonArrayIter<'a> {
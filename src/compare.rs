@@ -0,0 +1,480 @@
+//! Structural comparison of two values: what was added, removed or changed,
+//! and where, for reading when two documents that should match do not.
+//!
+//! ```
+//! use rust_json::compare::compare;
+//! use rust_json::parser::Parser;
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let tokens = parse_to_tokens(r#"{"name": "a", "ports": [80, 443]}"#).unwrap();
+//! let expected = Parser::new(&tokens).parse().unwrap();
+//! let tokens = parse_to_tokens(r#"{"name": "b", "ports": [80, 443, 8080]}"#).unwrap();
+//! let actual = Parser::new(&tokens).parse().unwrap();
+//!
+//! assert_eq!(
+//!     compare(&expected, &actual).to_string(),
+//!     "@@ $['name'] @@\n\
+//!      - \"a\"\n\
+//!      + \"b\"\n\
+//!      @@ $['ports'][2] @@\n\
+//!      + 8080\n"
+//! );
+//! ```
+
+use crate::convert::PathSegment;
+use crate::escape::unescape;
+use crate::jsonpath::NormalizedPath;
+use crate::object::JsonObject;
+use crate::patch::{align, Aligned};
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// What counts as a difference, see [`DiffOptions::compare`].
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    ignore_array_order: bool,
+    ignored_keys: Vec<String>,
+}
+
+/// How one value differs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change<'v> {
+    /// Only in the right value.
+    Added(&'v JsonValue<'v>),
+    /// Only in the left value.
+    Removed(&'v JsonValue<'v>),
+    Changed {
+        left: &'v JsonValue<'v>,
+        right: &'v JsonValue<'v>,
+    },
+}
+
+/// A value that differs, and where it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference<'v> {
+    pub path: NormalizedPath,
+    pub change: Change<'v>,
+}
+
+/// Every difference between two values, depth first. Within an object, the
+/// members only in the left value come first, in the left value's order,
+/// then the rest in the right value's order. Within an array compared by
+/// position, differences follow the elements; with
+/// [`ignore_array_order`](DiffOptions::ignore_array_order), removed
+/// elements come before added ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff<'v> {
+    pub differences: Vec<Difference<'v>>,
+}
+
+/// Compares with the default options: array order and every key matter.
+pub fn compare<'v>(left: &'v JsonValue<'v>, right: &'v JsonValue<'v>) -> Diff<'v> {
+    DiffOptions::default().compare(left, right)
+}
+
+impl DiffOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treats arrays as bags of elements: an element only differs when the
+    /// other array has no equal element left to match it with.
+    pub fn ignore_array_order(self, ignore: bool) -> Self {
+        Self {
+            ignore_array_order: ignore,
+            ..self
+        }
+    }
+
+    /// Skips object members named `key` (unescaped text) at any depth, such
+    /// as timestamps that change on every run.
+    pub fn ignore_key(mut self, key: impl Into<String>) -> Self {
+        self.ignored_keys.push(key.into());
+        self
+    }
+
+    /// Walks both values together. Members are matched by key, and array
+    /// elements by position or, when elements were inserted or removed, by
+    /// their longest common subsequence.
+    ///
+    /// Paths are into `right`, except that removed values have their path
+    /// in `left`.
+    pub fn compare<'v>(&self, left: &'v JsonValue<'v>, right: &'v JsonValue<'v>) -> Diff<'v> {
+        let mut differences = vec![];
+        self.walk(
+            left,
+            right,
+            &mut NormalizedPath::default(),
+            &mut differences,
+        );
+        Diff { differences }
+    }
+
+    fn ignored(&self, key: &str) -> bool {
+        self.ignored_keys.iter().any(|ignored| ignored == key)
+    }
+
+    /// The members that are compared, with their unescaped keys.
+    fn members<'a, 'v: 'a>(
+        &'a self,
+        object: &'v JsonObject<'v>,
+    ) -> impl Iterator<Item = (Cow<'v, str>, &'v JsonValue<'v>)> + 'a {
+        object
+            .children
+            .iter()
+            .map(|member| (unescape(&member.key), &member.value))
+            .filter(|(key, _)| !self.ignored(key))
+    }
+
+    /// Whether the two values have no differences.
+    fn same(&self, left: &JsonValue, right: &JsonValue) -> bool {
+        if self.ignored_keys.is_empty() && !self.ignore_array_order {
+            return left.equivalent(right);
+        }
+        match (left, right) {
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                let positions = b.positions();
+                self.members(a).count() == self.members(b).count()
+                    && self.members(a).all(|(key, left)| {
+                        positions
+                            .get(&key)
+                            .is_some_and(|&index| self.same(left, &b.children[index].value))
+                    })
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) if self.ignore_array_order => {
+                let (removed, added) = self.unmatched(&a.array, &b.array);
+                removed.is_empty() && added.is_empty()
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                a.array.len() == b.array.len()
+                    && a.array.iter().zip(&b.array).all(|(a, b)| self.same(a, b))
+            }
+            _ => left.equivalent(right),
+        }
+    }
+
+    /// The indices of elements of `left` and of `right` that have no equal
+    /// element in the other array, each element matching at most one.
+    fn unmatched(&self, left: &[JsonValue], right: &[JsonValue]) -> (Vec<usize>, Vec<usize>) {
+        let mut used = vec![false; right.len()];
+        let mut removed = vec![];
+        for (i, element) in left.iter().enumerate() {
+            let found = (0..right.len()).find(|&j| !used[j] && self.same(element, &right[j]));
+            match found {
+                Some(j) => used[j] = true,
+                None => removed.push(i),
+            }
+        }
+        let added = (0..right.len()).filter(|&j| !used[j]).collect();
+        (removed, added)
+    }
+
+    /// Records the differences between `left` and `right`. Each level only
+    /// looks at its own members and elements, so equal subtrees are not
+    /// walked again for every parent.
+    fn walk<'v>(
+        &self,
+        left: &'v JsonValue<'v>,
+        right: &'v JsonValue<'v>,
+        path: &mut NormalizedPath,
+        differences: &mut Vec<Difference<'v>>,
+    ) {
+        match (left, right) {
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                let (in_a, in_b) = (a.positions(), b.positions());
+                for (key, value) in self.members(a) {
+                    if !in_b.contains_key(&key) {
+                        push(
+                            differences,
+                            path,
+                            PathSegment::Key(key.into_owned()),
+                            Change::Removed(value),
+                        );
+                    }
+                }
+                for (key, value) in self.members(b) {
+                    match in_a.get(&key) {
+                        Some(&index) => {
+                            path.segments.push(PathSegment::Key(key.into_owned()));
+                            self.walk(&a.children[index].value, value, path, differences);
+                            path.segments.pop();
+                        }
+                        None => push(
+                            differences,
+                            path,
+                            PathSegment::Key(key.into_owned()),
+                            Change::Added(value),
+                        ),
+                    }
+                }
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) if self.ignore_array_order => {
+                let (removed, added) = self.unmatched(&a.array, &b.array);
+                for i in removed {
+                    push(
+                        differences,
+                        path,
+                        PathSegment::Index(i),
+                        Change::Removed(&a.array[i]),
+                    );
+                }
+                for j in added {
+                    push(
+                        differences,
+                        path,
+                        PathSegment::Index(j),
+                        Change::Added(&b.array[j]),
+                    );
+                }
+            }
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                let (a, b) = (&a.array, &b.array);
+                for aligned in align(a, b, |a, b| self.same(a, b)) {
+                    match aligned {
+                        Aligned::Kept(..) => {}
+                        Aligned::Changed(i, j) => {
+                            path.segments.push(PathSegment::Index(j));
+                            self.walk(&a[i], &b[j], path, differences);
+                            path.segments.pop();
+                        }
+                        Aligned::Removed(i) => push(
+                            differences,
+                            path,
+                            PathSegment::Index(i),
+                            Change::Removed(&a[i]),
+                        ),
+                        Aligned::Added(j) => push(
+                            differences,
+                            path,
+                            PathSegment::Index(j),
+                            Change::Added(&b[j]),
+                        ),
+                    }
+                }
+            }
+            _ if left.equivalent(right) => {}
+            _ => differences.push(Difference {
+                path: path.clone(),
+                change: Change::Changed { left, right },
+            }),
+        }
+    }
+}
+
+/// Records a difference at a child of `path`.
+fn push<'v>(
+    differences: &mut Vec<Difference<'v>>,
+    path: &NormalizedPath,
+    segment: PathSegment,
+    change: Change<'v>,
+) {
+    let mut path = path.clone();
+    path.segments.push(segment);
+    differences.push(Difference { path, change });
+}
+
+impl<'v> Diff<'v> {
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Renders the differences in the style of a unified diff: a `@@ path @@`
+    /// line for each, then the left value on `-` lines and the right value
+    /// on `+` lines, pretty-printed.
+    pub fn report(&self) -> DiffReport<'_, 'v> {
+        DiffReport {
+            diff: self,
+            color: false,
+        }
+    }
+}
+
+/// The plain [`DiffReport`].
+impl Display for Diff<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.report().fmt(f)
+    }
+}
+
+/// A [`Diff`] ready to be printed, see [`Diff::report`].
+#[derive(Debug, Clone, Copy)]
+pub struct DiffReport<'d, 'v> {
+    diff: &'d Diff<'v>,
+    color: bool,
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+impl<'d, 'v> DiffReport<'d, 'v> {
+    /// Colors the lines with ANSI escapes: paths cyan, removals red and
+    /// additions green.
+    pub fn color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    fn line(&self, f: &mut Formatter<'_>, color: &str, text: &str) -> std::fmt::Result {
+        if self.color {
+            writeln!(f, "{}{}{}", color, text, RESET)
+        } else {
+            writeln!(f, "{}", text)
+        }
+    }
+
+    fn value(
+        &self,
+        f: &mut Formatter<'_>,
+        sign: char,
+        color: &str,
+        value: &JsonValue,
+    ) -> std::fmt::Result {
+        for line in value.to_string_pretty(2).lines() {
+            self.line(f, color, &format!("{} {}", sign, line))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DiffReport<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for difference in &self.diff.differences {
+            self.line(f, CYAN, &format!("@@ {} @@", difference.path))?;
+            match difference.change {
+                Change::Added(value) => self.value(f, '+', GREEN, value)?,
+                Change::Removed(value) => self.value(f, '-', RED, value)?,
+                Change::Changed { left, right } => {
+                    self.value(f, '-', RED, left)?;
+                    self.value(f, '+', GREEN, right)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_compare {
+    use super::*;
+    use crate::value::parse;
+
+    /// Each difference as `path kind`.
+    fn summary(options: &DiffOptions, left: &str, right: &str) -> Vec<String> {
+        let (left, right) = (parse(left), parse(right));
+        options
+            .compare(&left, &right)
+            .differences
+            .iter()
+            .map(|difference| {
+                let kind = match difference.change {
+                    Change::Added(_) => "added",
+                    Change::Removed(_) => "removed",
+                    Change::Changed { .. } => "changed",
+                };
+                format!("{} {}", difference.path, kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_differences() {
+        let options = DiffOptions::new();
+        assert!(summary(
+            &options,
+            r#"{"a": [1, {"b": 2}]}"#,
+            r#"{"a": [1, {"b": 2}]}"#
+        )
+        .is_empty());
+        assert!(summary(&options, r#"{"a": 1, "b": 2}"#, r#"{"b": 2, "a": 1}"#).is_empty());
+        assert_eq!(
+            summary(
+                &options,
+                r#"{"a": 1, "b": {"c": true, "d": "x"}, "e": null}"#,
+                r#"{"a": 2, "b": {"c": true, "d": "y"}, "f": []}"#
+            ),
+            [
+                "$['e'] removed",
+                "$['a'] changed",
+                "$['b']['d'] changed",
+                "$['f'] added"
+            ]
+        );
+        assert_eq!(
+            summary(&options, "[1, 2, 3, 4]", "[0, 1, 3, 4, 5]"),
+            ["$[0] added", "$[1] removed", "$[4] added"]
+        );
+        assert_eq!(
+            summary(&options, r#"[{"a": 1}, 2]"#, r#"[{"a": 3}, 2]"#),
+            ["$[0]['a'] changed"]
+        );
+        assert_eq!(
+            summary(&options, r#"{"x\ty": [1]}"#, r#"{"x\ty": {}}"#),
+            [r"$['x\ty'] changed"]
+        );
+        assert_eq!(summary(&options, "1", "\"1\""), ["$ changed"]);
+    }
+
+    #[test]
+    fn test_options() {
+        let unordered = DiffOptions::new().ignore_array_order(true);
+        assert!(summary(&unordered, "[1, [2, 3], 1]", "[[3, 2], 1, 1]").is_empty());
+        assert_eq!(
+            summary(&unordered, "[1, 2, 2]", "[2, 3, 1]"),
+            ["$[2] removed", "$[1] added"]
+        );
+
+        let ignoring = DiffOptions::new().ignore_key("time").ignore_key("id");
+        assert!(summary(
+            &ignoring,
+            r#"{"time": 1, "items": [{"id": 1, "n": "a"}]}"#,
+            r#"{"items": [{"id": 2, "n": "a"}], "time": 2}"#
+        )
+        .is_empty());
+        assert_eq!(
+            summary(
+                &ignoring,
+                r#"[{"id": 1, "n": 1}]"#,
+                r#"[{"id": 1, "n": 2}]"#
+            ),
+            ["$[0]['n'] changed"]
+        );
+        assert_eq!(
+            summary(
+                &ignoring,
+                r#"{"\u006e": {"a": 1, "b": [1]}, "id": 1}"#,
+                r#"{"n": {"b": [1], "a": 2}, "o": 3}"#
+            ),
+            ["$['n']['a'] changed", "$['o'] added"]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let left = parse(r#"{"a": {"b": [1, 2]}, "c": 1}"#);
+        let right = parse(r#"{"a": {"b": 3}}"#);
+        let diff = compare(&left, &right);
+        assert_eq!(
+            diff.to_string(),
+            "@@ $['c'] @@\n\
+             - 1\n\
+             @@ $['a']['b'] @@\n\
+             - [\n\
+             -   1,\n\
+             -   2\n\
+             - ]\n\
+             + 3\n"
+        );
+        assert_eq!(
+            diff.report()
+                .color(true)
+                .to_string()
+                .lines()
+                .take(2)
+                .collect::<Vec<_>>(),
+            ["\x1b[36m@@ $['c'] @@\x1b[0m", "\x1b[31m- 1\x1b[0m"]
+        );
+        assert_eq!(compare(&left, &left).to_string(), "");
+    }
+}
@@ -0,0 +1,840 @@
+//! Converting Rust values to and from [`JsonValue`] without serde.
+//!
+//! [`ToJson`] builds a value, [`FromJson`] reads one back and says where it
+//! went wrong: errors carry the path of the offending member, e.g.
+//! `servers[1].port: expected u16, found string`. With the `derive` feature
+//! both traits can be derived, see `rust_json_derive`.
+//!
+//! Both are implemented for `bool`, the integer and float types, `String`,
+//! `str` (only borrowable from strings without escapes), `Cow<str>`,
+//! `Option`, `Box`, `Vec`, arrays, tuples of up to eight elements,
+//! `HashMap`/`BTreeMap<String, T>` and `JsonValue`. Single members can be
+//! pulled out with [`JsonValue::get`] and [`Lookup::parse`].
+//!
+//! ```
+//! use rust_json::convert::{FromJson, ToJson};
+//! use rust_json::parser::Parser;
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let tokens = parse_to_tokens(r#"[[1, 2], [3, 4.5]]"#).unwrap();
+//! let value = Parser::new(&tokens).parse().unwrap();
+//! let e = Vec::<Vec<u8>>::from_json(&value).unwrap_err();
+//! assert_eq!(e.to_string(), "[1][1]: expected u8, found 4.5");
+//!
+//! let numbers = vec![Some(1.5), None];
+//! assert_eq!(numbers.to_json().to_string(), "[1.5,null]");
+//!
+//! let tokens = parse_to_tokens(r#"{"server": {"port": 8080}}"#).unwrap();
+//! let value = Parser::new(&tokens).parse().unwrap();
+//! assert_eq!(value.get("server").get("port").parse::<u16>(), Ok(8080));
+//! ```
+
+use crate::array::JsonArray;
+use crate::escape::{escape, unescape};
+use crate::keyvalue::KeyValue;
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::hash::BuildHasher;
+
+#[cfg(feature = "derive")]
+pub use rust_json_derive::{FromJson, ToJson};
+
+/// Types that can be written as JSON.
+pub trait ToJson {
+    /// Builds the value, borrowing strings from `self` where they need no
+    /// escaping.
+    fn to_json(&self) -> JsonValue<'_>;
+}
+
+/// Types that can be read from JSON. `'v` is how long the value is borrowed
+/// for, which lets types such as `&'v str` point into it.
+pub trait FromJson<'v>: Sized {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError>;
+
+    /// What an object member of this type means when it is absent, if
+    /// anything. `Option` is `None`; for everything else a missing member is
+    /// an error.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// One step on the way from the root to the value an error is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// What went wrong, see [`FromJsonError`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FromJsonErrorKind {
+    /// The value is of the wrong kind, e.g. a string where a number belongs.
+    InvalidType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The value has the right kind but does not fit, e.g. `300` for a `u8`.
+    InvalidValue {
+        expected: &'static str,
+        found: String,
+    },
+    /// An array has the wrong number of elements for a tuple or array type.
+    InvalidLength {
+        expected: usize,
+        found: usize,
+    },
+    MissingField(String),
+    UnknownVariant {
+        found: String,
+        expected: &'static [&'static str],
+    },
+    Custom(String),
+}
+
+/// Why [`FromJson::from_json`] failed, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromJsonError {
+    kind: FromJsonErrorKind,
+    /// Innermost segment first, since the path is built while unwinding.
+    path: Vec<PathSegment>,
+}
+
+impl FromJsonError {
+    pub fn new(kind: FromJsonErrorKind) -> Self {
+        FromJsonError { kind, path: vec![] }
+    }
+
+    pub fn invalid_type(expected: &'static str, found: &JsonValue) -> Self {
+        Self::new(FromJsonErrorKind::InvalidType {
+            expected,
+            found: type_name(found),
+        })
+    }
+
+    pub fn invalid_value(expected: &'static str, found: impl Display) -> Self {
+        Self::new(FromJsonErrorKind::InvalidValue {
+            expected,
+            found: found.to_string(),
+        })
+    }
+
+    pub fn missing_field(name: &str) -> Self {
+        Self::new(FromJsonErrorKind::MissingField(name.to_string()))
+    }
+
+    pub fn unknown_variant(found: &str, expected: &'static [&'static str]) -> Self {
+        Self::new(FromJsonErrorKind::UnknownVariant {
+            found: found.to_string(),
+            expected,
+        })
+    }
+
+    pub fn custom(message: impl Display) -> Self {
+        Self::new(FromJsonErrorKind::Custom(message.to_string()))
+    }
+
+    /// Records that the error happened inside the member `key`.
+    pub fn at_key(mut self, key: &str) -> Self {
+        self.path.push(PathSegment::Key(key.to_string()));
+        self
+    }
+
+    /// Records that the error happened inside element `index`.
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.path.push(PathSegment::Index(index));
+        self
+    }
+
+    pub fn kind(&self) -> &FromJsonErrorKind {
+        &self.kind
+    }
+
+    /// From the root to the value the error is about.
+    pub fn path(&self) -> impl Iterator<Item = &PathSegment> {
+        self.path.iter().rev()
+    }
+}
+
+impl Display for FromJsonErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FromJsonErrorKind::InvalidType { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            FromJsonErrorKind::InvalidValue { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            FromJsonErrorKind::InvalidLength { expected, found } => write!(
+                f,
+                "expected an array of {} elements, found {}",
+                expected, found
+            ),
+            FromJsonErrorKind::MissingField(name) => write!(f, "missing field `{}`", name),
+            FromJsonErrorKind::UnknownVariant { found, expected } => {
+                write!(f, "unknown variant `{}`, expected one of ", found)?;
+                for (index, variant) in expected.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { ", " };
+                    write!(f, "{}`{}`", separator, variant)?;
+                }
+                Ok(())
+            }
+            FromJsonErrorKind::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Display for FromJsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, segment) in self.path().enumerate() {
+            match segment {
+                PathSegment::Key(key) if index == 0 => write!(f, "{}", key)?,
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for FromJsonError {}
+
+/// How errors refer to the kind of `value`.
+pub(crate) fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Object(_) => "object",
+        JsonValue::Array(_) => "array",
+        JsonValue::True | JsonValue::False => "boolean",
+        JsonValue::Null => "null",
+        JsonValue::Empty => "nothing",
+    }
+}
+
+/// `null` for NaN and the infinities, which JSON has no way to write.
+fn number(number: f64) -> JsonValue<'static> {
+    if number.is_finite() {
+        JsonValue::Number(Box::new(number))
+    } else {
+        JsonValue::Null
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue<'_> {
+        if *self {
+            JsonValue::True
+        } else {
+            JsonValue::False
+        }
+    }
+}
+
+impl<'v> FromJson<'v> for bool {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::True => Ok(true),
+            JsonValue::False => Ok(false),
+            other => Err(FromJsonError::invalid_type("boolean", other)),
+        }
+    }
+}
+
+macro_rules! integers {
+    ($($type:ident)*) => {
+        $(impl ToJson for $type {
+            /// Integers beyond 2^53 lose precision, as numbers are `f64`.
+            fn to_json(&self) -> JsonValue<'_> {
+                number(*self as f64)
+            }
+        }
+
+        impl<'v> FromJson<'v> for $type {
+            fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+                // Exclusive, as `MAX as f64` rounds up to 2^63 or 2^64 for
+                // the 64-bit types.
+                let end = if $type::MIN == 0 {
+                    (1u128 << $type::BITS) as f64
+                } else {
+                    -($type::MIN as f64)
+                };
+                match value {
+                    JsonValue::Number(n)
+                        if n.fract() == 0.0 && **n >= $type::MIN as f64 && **n < end =>
+                    {
+                        Ok(**n as $type)
+                    }
+                    JsonValue::Number(n) => Err(FromJsonError::invalid_value(stringify!($type), n)),
+                    other => Err(FromJsonError::invalid_type(stringify!($type), other)),
+                }
+            }
+        })*
+    };
+}
+
+integers!(i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
+macro_rules! floats {
+    ($($type:ident)*) => {
+        $(impl ToJson for $type {
+            /// NaN and the infinities are written as `null`.
+            fn to_json(&self) -> JsonValue<'_> {
+                number(*self as f64)
+            }
+        }
+
+        impl<'v> FromJson<'v> for $type {
+            /// A finite number too large for the type is an error rather
+            /// than an infinity.
+            fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+                match value {
+                    JsonValue::Number(n) if n.is_finite() && (**n as $type).is_infinite() => {
+                        Err(FromJsonError::invalid_value(stringify!($type), n))
+                    }
+                    JsonValue::Number(n) => Ok(**n as $type),
+                    other => Err(FromJsonError::invalid_type(stringify!($type), other)),
+                }
+            }
+        })*
+    };
+}
+
+floats!(f32 f64);
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue<'_> {
+        JsonValue::String(Box::new(escape(self)))
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue<'_> {
+        self.as_str().to_json()
+    }
+}
+
+impl<'v> FromJson<'v> for String {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::String(raw) => Ok(unescape(raw).into_owned()),
+            other => Err(FromJsonError::invalid_type("string", other)),
+        }
+    }
+}
+
+impl<'v> FromJson<'v> for &'v str {
+    /// Strings with escapes cannot be borrowed and are an error; read those
+    /// as `String` or `Cow<str>`.
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::String(raw) => match unescape(raw) {
+                Cow::Borrowed(text) => Ok(text),
+                Cow::Owned(_) => Err(FromJsonError::invalid_value(
+                    "string without escapes",
+                    format!("\"{}\"", raw),
+                )),
+            },
+            other => Err(FromJsonError::invalid_type("string", other)),
+        }
+    }
+}
+
+impl ToJson for Cow<'_, str> {
+    fn to_json(&self) -> JsonValue<'_> {
+        (**self).to_json()
+    }
+}
+
+impl<'v> FromJson<'v> for Cow<'v, str> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::String(raw) => Ok(unescape(raw)),
+            other => Err(FromJsonError::invalid_type("string", other)),
+        }
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue<'_> {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue<'_> {
+        (**self).to_json()
+    }
+}
+
+impl<'v, T: FromJson<'v>> FromJson<'v> for Box<T> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue<'_> {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl<'v, T: FromJson<'v>> FromJson<'v> for Option<T> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue<'_> {
+        let array = self.iter().map(ToJson::to_json).collect();
+        JsonValue::Array(Box::new(JsonArray { array }))
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue<'_> {
+        self.as_slice().to_json()
+    }
+}
+
+impl<'v, T: FromJson<'v>> FromJson<'v> for Vec<T> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Array(array) => array
+                .array
+                .iter()
+                .enumerate()
+                .map(|(index, value)| T::from_json(value).map_err(|e| e.at_index(index)))
+                .collect(),
+            other => Err(FromJsonError::invalid_type("array", other)),
+        }
+    }
+}
+
+/// The elements of `value`, which must be an array of `len` of them.
+fn elements<'v, 'a>(
+    value: &'v JsonValue<'a>,
+    len: usize,
+) -> Result<&'v [JsonValue<'a>], FromJsonError> {
+    match value {
+        JsonValue::Array(array) if array.array.len() == len => Ok(&array.array),
+        JsonValue::Array(array) => Err(FromJsonError::new(FromJsonErrorKind::InvalidLength {
+            expected: len,
+            found: array.array.len(),
+        })),
+        other => Err(FromJsonError::invalid_type("array", other)),
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> JsonValue<'_> {
+        self.as_slice().to_json()
+    }
+}
+
+impl<'v, T: FromJson<'v>, const N: usize> FromJson<'v> for [T; N] {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        let elements = elements(value, N)?
+            .iter()
+            .enumerate()
+            .map(|(index, value)| T::from_json(value).map_err(|e| e.at_index(index)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(elements
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("the length was checked")))
+    }
+}
+
+macro_rules! tuples {
+    ($($len:literal => ($($index:tt $name:ident)+))+) => {
+        $(impl<$($name: ToJson),+> ToJson for ($($name,)+) {
+            fn to_json(&self) -> JsonValue<'_> {
+                let array = vec![$(self.$index.to_json()),+];
+                JsonValue::Array(Box::new(JsonArray { array }))
+            }
+        }
+
+        impl<'v, $($name: FromJson<'v>),+> FromJson<'v> for ($($name,)+) {
+            fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+                let elements = elements(value, $len)?;
+                Ok(($($name::from_json(&elements[$index]).map_err(|e| e.at_index($index))?,)+))
+            }
+        })+
+    };
+}
+
+tuples! {
+    1 => (0 A)
+    2 => (0 A 1 B)
+    3 => (0 A 1 B 2 C)
+    4 => (0 A 1 B 2 C 3 D)
+    5 => (0 A 1 B 2 C 3 D 4 E)
+    6 => (0 A 1 B 2 C 3 D 4 E 5 F)
+    7 => (0 A 1 B 2 C 3 D 4 E 5 F 6 G)
+    8 => (0 A 1 B 2 C 3 D 4 E 5 F 6 G 7 H)
+}
+
+fn to_object<'a, T: ToJson + 'a>(
+    members: impl Iterator<Item = (&'a String, &'a T)>,
+) -> JsonValue<'a> {
+    let object = members
+        .map(|(key, value)| KeyValue {
+            key: escape(key),
+            value: value.to_json(),
+        })
+        .collect();
+    JsonValue::Object(Box::new(object))
+}
+
+/// Reads every member of an object; with duplicate keys the last one wins.
+fn from_object<'v, T: FromJson<'v>, C: FromIterator<(String, T)>>(
+    value: &'v JsonValue<'_>,
+) -> Result<C, FromJsonError> {
+    match value {
+        JsonValue::Object(object) => object
+            .iter()
+            .map(|member| {
+                let key = unescape(&member.key);
+                match T::from_json(&member.value) {
+                    Ok(value) => Ok((key.into_owned(), value)),
+                    Err(e) => Err(e.at_key(&key)),
+                }
+            })
+            .collect(),
+        other => Err(FromJsonError::invalid_type("object", other)),
+    }
+}
+
+/// Members come out in the map's iteration order.
+impl<T: ToJson, S> ToJson for HashMap<String, T, S> {
+    fn to_json(&self) -> JsonValue<'_> {
+        to_object(self.iter())
+    }
+}
+
+impl<'v, T: FromJson<'v>, S: BuildHasher + Default> FromJson<'v> for HashMap<String, T, S> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        from_object(value)
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> JsonValue<'_> {
+        to_object(self.iter())
+    }
+}
+
+impl<'v, T: FromJson<'v>> FromJson<'v> for BTreeMap<String, T> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        from_object(value)
+    }
+}
+
+impl<'a> ToJson for JsonValue<'a> {
+    fn to_json(&self) -> JsonValue<'_> {
+        self.clone()
+    }
+}
+
+impl<'v> FromJson<'v> for JsonValue<'v> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        Ok(value.clone())
+    }
+}
+
+/// A member reached with [`JsonValue::get`]: read it with [`Lookup::parse`]
+/// or go further down with [`Lookup::get`].
+///
+/// Nothing fails before `parse`, whose error has the whole path, so
+/// `value.get("server").get("port").parse::<u16>()` reports
+/// ``server.port: expected u16, found string`` or
+/// ``server: missing field `port` ``.
+#[derive(Debug, Clone)]
+pub struct Lookup<'v, 'a> {
+    /// The keys leading to the value reached, root first.
+    keys: Vec<String>,
+    state: State<'v, 'a>,
+}
+
+#[derive(Debug, Clone)]
+enum State<'v, 'a> {
+    Found(&'v JsonValue<'a>),
+    /// The parent object has no member with this key.
+    Missing(String),
+    Failed(FromJsonError),
+}
+
+impl<'v, 'a> Lookup<'v, 'a> {
+    pub(crate) fn new(value: &'v JsonValue<'a>) -> Self {
+        Lookup {
+            keys: vec![],
+            state: State::Found(value),
+        }
+    }
+
+    /// The member `key` (unescaped text) of the value reached so far.
+    pub fn get(mut self, key: &str) -> Self {
+        self.state = match self.state {
+            State::Found(JsonValue::Object(object)) => match object.get(key) {
+                Some(value) => {
+                    self.keys.push(key.to_string());
+                    State::Found(value)
+                }
+                None => State::Missing(key.to_string()),
+            },
+            State::Found(other) => State::Failed(located(
+                FromJsonError::invalid_type("object", other),
+                &self.keys,
+            )),
+            State::Missing(key) => {
+                State::Failed(located(FromJsonError::missing_field(&key), &self.keys))
+            }
+            State::Failed(e) => State::Failed(e),
+        };
+        self
+    }
+
+    /// The value reached, if every key along the way was there.
+    pub fn value(&self) -> Option<&'v JsonValue<'a>> {
+        match self.state {
+            State::Found(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts the value reached, as [`FromJson::from_json`] would. A
+    /// missing member is only an error if `T` has no meaning for absence,
+    /// so `Option<T>` gives `None`.
+    pub fn parse<T: FromJson<'v>>(self) -> Result<T, FromJsonError> {
+        match self.state {
+            State::Found(value) => T::from_json(value).map_err(|e| located(e, &self.keys)),
+            State::Missing(key) => T::from_missing()
+                .ok_or_else(|| located(FromJsonError::missing_field(&key), &self.keys)),
+            State::Failed(e) => Err(e),
+        }
+    }
+}
+
+/// Places an error about the value at `keys` in the document.
+fn located(mut error: FromJsonError, keys: &[String]) -> FromJsonError {
+    for key in keys.iter().rev() {
+        error = error.at_key(key);
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests_convert {
+    use super::*;
+    use crate::value::parse;
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(u16::from_json(&parse("8080")), Ok(8080));
+        assert_eq!(i8::from_json(&parse("-128")), Ok(-128));
+        assert_eq!(f32::from_json(&parse("0.5")), Ok(0.5));
+        assert_eq!(
+            f32::from_json(&parse("-1e39")).unwrap_err().to_string(),
+            "expected f32, found -1000000000000000000000000000000000000000"
+        );
+        assert_eq!(f64::from_json(&parse("1e300")), Ok(1e300));
+        assert_eq!(bool::from_json(&parse("true")), Ok(true));
+        assert_eq!(
+            String::from_json(&parse(r#""a\"bé""#)),
+            Ok("a\"bé".to_string())
+        );
+        assert_eq!(
+            u8::from_json(&parse("256")).unwrap_err().to_string(),
+            "expected u8, found 256"
+        );
+        assert_eq!(
+            u8::from_json(&parse("-1.5")).unwrap_err().to_string(),
+            "expected u8, found -1.5"
+        );
+        assert_eq!(i64::from_json(&parse("-9223372036854775808")), Ok(i64::MIN));
+        assert_eq!(
+            i64::from_json(&parse("9223372036854775808"))
+                .unwrap_err()
+                .to_string(),
+            "expected i64, found 9223372036854776000"
+        );
+        assert!(u64::from_json(&parse("18446744073709551616")).is_err());
+        assert_eq!(u32::from_json(&parse("4294967295")), Ok(u32::MAX));
+        assert!(u32::from_json(&parse("4294967296")).is_err());
+        assert_eq!(
+            bool::from_json(&parse("null")).unwrap_err().to_string(),
+            "expected boolean, found null"
+        );
+    }
+
+    #[test]
+    fn test_containers() {
+        let value = parse(r#"[[1, null], [], [3, 4]]"#);
+        assert_eq!(
+            Vec::<Vec<Option<u8>>>::from_json(&value),
+            Ok(vec![vec![Some(1), None], vec![], vec![Some(3), Some(4)]])
+        );
+        let e = Vec::<Vec<u8>>::from_json(&value).unwrap_err();
+        assert_eq!(e.to_string(), "[0][1]: expected u8, found null");
+        assert_eq!(
+            e.path().collect::<Vec<_>>(),
+            [&PathSegment::Index(0), &PathSegment::Index(1)]
+        );
+        assert_eq!(Option::<u8>::from_missing(), Some(None));
+        assert_eq!(u8::from_missing(), None);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let strings = vec![Some("tab\t"), None];
+        assert_eq!(strings.to_json().to_string(), r#"["tab\t",null]"#);
+        assert_eq!((-3i64).to_json(), parse("-3"));
+        assert_eq!(Box::new(true).to_json(), JsonValue::True);
+        let floats = [f64::NAN, f64::INFINITY, 1.5];
+        assert_eq!(floats.to_json().to_string(), "[null,null,1.5]");
+        let value = parse(r#"{"a": [1]}"#);
+        assert_eq!(value.to_json(), value);
+    }
+
+    #[test]
+    fn test_borrowed_str() {
+        let value = parse(r#"["plain", "tab\t"]"#);
+        assert_eq!(
+            <[&str; 1]>::from_json(&parse(r#"["plain"]"#)),
+            Ok(["plain"])
+        );
+        assert_eq!(
+            <(&str, &str)>::from_json(&value).unwrap_err().to_string(),
+            r#"[1]: expected string without escapes, found "tab\t""#
+        );
+        assert_eq!(
+            <(&str, Cow<str>)>::from_json(&value),
+            Ok(("plain", Cow::Owned("tab\t".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_tuples_and_arrays() {
+        let value = parse(r#"[1, "a", [true, false]]"#);
+        let tuple = <(u8, String, [bool; 2])>::from_json(&value).unwrap();
+        assert_eq!(tuple, (1, "a".to_string(), [true, false]));
+        assert_eq!(tuple.to_json(), value);
+        assert_eq!(
+            <(u8, String)>::from_json(&value).unwrap_err().to_string(),
+            "expected an array of 2 elements, found 3"
+        );
+        assert_eq!(
+            <(u8, u8, [bool; 2])>::from_json(&value)
+                .unwrap_err()
+                .to_string(),
+            "[1]: expected u8, found string"
+        );
+        assert_eq!(
+            <[u8; 2]>::from_json(&parse("{}")).unwrap_err().to_string(),
+            "expected array, found object"
+        );
+    }
+
+    #[test]
+    fn test_maps() {
+        let value = parse(r#"{"b": 2, "a\n": 1}"#);
+        let map = BTreeMap::<String, u8>::from_json(&value).unwrap();
+        assert_eq!(
+            map,
+            BTreeMap::from([("a\n".to_string(), 1), ("b".to_string(), 2)])
+        );
+        assert_eq!(map.to_json().to_string(), r#"{"a\n":1,"b":2}"#);
+        let map = HashMap::<String, Vec<u8>>::from_json(&parse(r#"{"x": [1]}"#)).unwrap();
+        assert_eq!(map.to_json().to_string(), r#"{"x":[1]}"#);
+        assert_eq!(
+            HashMap::<String, u8>::from_json(&parse(r#"{"a\tb": -1}"#))
+                .unwrap_err()
+                .to_string(),
+            "a\tb: expected u8, found -1"
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let value = parse(r#"{"server": {"port": "80", "host": "a"}, "debug": true}"#);
+        assert_eq!(value.get("debug").parse::<bool>(), Ok(true));
+        assert_eq!(value.get("server").get("host").parse::<&str>(), Ok("a"));
+        let escaped = parse(r#"{"\u0070ort": 80}"#);
+        assert_eq!(escaped.get("port").parse::<u16>(), Ok(80));
+        assert_eq!(
+            value.parse::<HashMap<String, JsonValue>>().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            value
+                .get("server")
+                .get("port")
+                .parse::<u16>()
+                .unwrap_err()
+                .to_string(),
+            "server.port: expected u16, found string"
+        );
+        assert_eq!(
+            value
+                .get("server")
+                .get("tls")
+                .parse::<bool>()
+                .unwrap_err()
+                .to_string(),
+            "server: missing field `tls`"
+        );
+        assert_eq!(
+            value.get("server").get("tls").parse::<Option<bool>>(),
+            Ok(None)
+        );
+        assert_eq!(
+            value
+                .get("tls")
+                .get("cert")
+                .parse::<Option<String>>()
+                .unwrap_err()
+                .to_string(),
+            "missing field `tls`"
+        );
+        assert_eq!(
+            value
+                .get("debug")
+                .get("level")
+                .parse::<u8>()
+                .unwrap_err()
+                .to_string(),
+            "debug: expected object, found boolean"
+        );
+        assert_eq!(value.get("server").get("tls").value(), None);
+        assert_eq!(value.get("debug").value(), Some(&JsonValue::True));
+    }
+
+    #[test]
+    fn test_error_paths() {
+        let e = FromJsonError::missing_field("port")
+            .at_index(1)
+            .at_key("servers");
+        assert_eq!(e.to_string(), "servers[1]: missing field `port`");
+        let e = FromJsonError::unknown_variant("Oval", &["Circle", "Rect"]).at_key("shape");
+        assert_eq!(
+            e.to_string(),
+            "shape: unknown variant `Oval`, expected one of `Circle`, `Rect`"
+        );
+    }
+}
@@ -0,0 +1,651 @@
+//! A lossless concrete syntax tree for editing JSONC configuration files.
+//!
+//! [`CstDocument::parse`] keeps every byte of its input: whitespace and
+//! `//` / `/* */` comments are stored as trivia next to the keys, values and
+//! commas they surround, so printing an unmodified document reproduces it
+//! exactly. Replacing one value only changes that value's text.
+//!
+//! ```
+//! use rust_json::cst::{CstDocument, CstValue};
+//!
+//! let text = "{\n  // the port to bind\n  \"port\": 8080, /* keep */\n  \"debug\": false\n}\n";
+//! let mut doc = CstDocument::parse(text).unwrap();
+//! let root = doc.value_mut().as_object_mut().unwrap();
+//! *root.get_mut("port").unwrap() = CstValue::parse("9090").unwrap();
+//! assert_eq!(
+//!     doc.to_string(),
+//!     "{\n  // the port to bind\n  \"port\": 9090, /* keep */\n  \"debug\": false\n}\n"
+//! );
+//! ```
+
+use crate::array::JsonArray;
+use crate::error::JsonParserError::{NestingTooDeep, UnexpectedEndOfTokens};
+use crate::error::{
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_key_or_right_brace, expect_value,
+    JsonError,
+};
+use crate::escape::{escape, unescape};
+use crate::keyvalue::KeyValue;
+use crate::object::JsonObject;
+use crate::parser::MAX_DEPTH;
+use crate::span::Span;
+use crate::token::JsonToken::{
+    Colon, Comma, False, LeftBrace, LeftBracket, Null, Number, RightBrace, RightBracket, True,
+};
+use crate::token::{JsonToken, Tokenizer};
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// A whole file: the root value and the trivia around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstDocument {
+    leading: String,
+    value: CstValue,
+    trailing: String,
+}
+
+/// A value exactly as it was written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstValue {
+    Scalar(CstScalar),
+    Object(CstObject),
+    Array(CstArray),
+}
+
+/// A string, number or literal, including the quotes of a string. Only
+/// parsing makes one, so it always holds a single valid token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstScalar {
+    text: String,
+}
+
+impl CstScalar {
+    /// The token as written.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstObject {
+    members: Vec<CstMember>,
+    /// Trivia between the last member (or `{`) and `}`.
+    trailing: String,
+}
+
+/// `leading "key" before_colon : after_colon value after_value ,`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstMember {
+    leading: String,
+    key: String,
+    before_colon: String,
+    after_colon: String,
+    value: CstValue,
+    after_value: String,
+    comma: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstArray {
+    elements: Vec<CstElement>,
+    /// Trivia between the last element (or `[`) and `]`.
+    trailing: String,
+}
+
+/// `leading value after_value ,`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstElement {
+    leading: String,
+    value: CstValue,
+    after_value: String,
+    comma: bool,
+}
+
+impl CstDocument {
+    /// Parses JSON with comments. Trailing commas are kept as written.
+    pub fn parse(origin: &str) -> Result<CstDocument, JsonError<'_>> {
+        let mut builder = CstBuilder::new(origin);
+        let (leading, first) = builder.expect_token()?;
+        let value = builder.value(first)?;
+        if let Some((_, (_, token))) = builder.token()? {
+            Err(expect_end_of_input(&token))?;
+        }
+        Ok(CstDocument {
+            leading,
+            value,
+            trailing: origin[builder.end..].to_string(),
+        })
+    }
+
+    pub fn value(&self) -> &CstValue {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut CstValue {
+        &mut self.value
+    }
+}
+
+impl CstValue {
+    /// Parses a single value written without surrounding trivia, e.g. to
+    /// replace an existing one.
+    pub fn parse(origin: &str) -> Result<CstValue, JsonError<'_>> {
+        CstDocument::parse(origin).map(|doc| doc.value)
+    }
+
+    /// The compact text of `value`, as a new node. Fails for
+    /// [`JsonValue::Empty`], which has no text.
+    pub fn from_value(value: &JsonValue) -> Result<CstValue, JsonError<'static>> {
+        CstValue::parse(&value.to_string()).map_err(JsonError::into_owned)
+    }
+
+    pub fn as_object(&self) -> Option<&CstObject> {
+        match self {
+            CstValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut CstObject> {
+        match self {
+            CstValue::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&CstArray> {
+        match self {
+            CstValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut CstArray> {
+        match self {
+            CstValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// Reads the node as a plain [`JsonValue`], dropping all trivia.
+    pub fn to_value(&self) -> JsonValue<'_> {
+        match self {
+            CstValue::Scalar(scalar) => match Tokenizer::new(&scalar.text).next() {
+                Some(Ok(JsonToken::String(str))) => JsonValue::String(Box::new(str)),
+                Some(Ok(Number(num))) => JsonValue::Number(Box::new(num)),
+                Some(Ok(True)) => JsonValue::True,
+                Some(Ok(False)) => JsonValue::False,
+                _ => JsonValue::Null,
+            },
+            CstValue::Object(obj) => JsonValue::Object(Box::new(JsonObject {
+                children: obj
+                    .members
+                    .iter()
+                    .map(|member| KeyValue {
+                        key: Cow::Borrowed(&member.key),
+                        value: member.value.to_value(),
+                    })
+                    .collect(),
+            })),
+            CstValue::Array(arr) => JsonValue::Array(Box::new(JsonArray {
+                array: arr
+                    .elements
+                    .iter()
+                    .map(|element| element.value.to_value())
+                    .collect(),
+            })),
+        }
+    }
+}
+
+impl CstObject {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// The keys as written in the document, with their escapes.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|member| member.key.as_str())
+    }
+
+    /// Index of the member whose key, once unescaped, is `key`.
+    fn position(&self, key: &str) -> Option<usize> {
+        self.members
+            .iter()
+            .position(|member| unescape(&member.key) == key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&CstValue> {
+        let index = self.position(key)?;
+        Some(&self.members[index].value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut CstValue> {
+        let index = self.position(key)?;
+        Some(&mut self.members[index].value)
+    }
+
+    /// Replaces the value of `key`, keeping the trivia around it, or appends
+    /// a new member laid out like the current last one.
+    pub fn insert(&mut self, key: &str, value: CstValue) {
+        if let Some(slot) = self.get_mut(key) {
+            *slot = value;
+            return;
+        }
+        let member = match self.members.last_mut() {
+            Some(last) => {
+                let trailing_comma = last.comma;
+                if !last.comma {
+                    // The comma goes right after the value, whatever used to
+                    // follow it now follows the new member.
+                    self.trailing
+                        .insert_str(0, &std::mem::take(&mut last.after_value));
+                    last.comma = true;
+                }
+                CstMember {
+                    leading: indentation(&last.leading),
+                    key: escape(key).into_owned(),
+                    before_colon: last.before_colon.clone(),
+                    after_colon: last.after_colon.clone(),
+                    value,
+                    after_value: String::new(),
+                    comma: trailing_comma,
+                }
+            }
+            None => CstMember {
+                leading: String::new(),
+                key: escape(key).into_owned(),
+                before_colon: String::new(),
+                after_colon: " ".to_string(),
+                value,
+                after_value: String::new(),
+                comma: false,
+            },
+        };
+        self.members.push(member);
+    }
+
+    /// Removes `key` together with the trivia in front of it.
+    pub fn remove(&mut self, key: &str) -> Option<CstValue> {
+        let index = self.position(key)?;
+        let member = self.members.remove(index);
+        if index == self.members.len() {
+            if let Some(last) = self.members.last_mut() {
+                last.comma = member.comma;
+                last.after_value.push_str(&member.after_value);
+            }
+        }
+        Some(member.value)
+    }
+}
+
+impl CstArray {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&CstValue> {
+        self.elements.get(index).map(|element| &element.value)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut CstValue> {
+        self.elements
+            .get_mut(index)
+            .map(|element| &mut element.value)
+    }
+
+    /// Appends an element laid out like the current last one.
+    pub fn push(&mut self, value: CstValue) {
+        let element = match self.elements.last_mut() {
+            Some(last) => {
+                let trailing_comma = last.comma;
+                if !last.comma {
+                    self.trailing
+                        .insert_str(0, &std::mem::take(&mut last.after_value));
+                    last.comma = true;
+                }
+                CstElement {
+                    leading: indentation(&last.leading),
+                    value,
+                    after_value: String::new(),
+                    comma: trailing_comma,
+                }
+            }
+            None => CstElement {
+                leading: String::new(),
+                value,
+                after_value: String::new(),
+                comma: false,
+            },
+        };
+        self.elements.push(element);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<CstValue> {
+        if index >= self.elements.len() {
+            return None;
+        }
+        let element = self.elements.remove(index);
+        if index == self.elements.len() {
+            if let Some(last) = self.elements.last_mut() {
+                last.comma = element.comma;
+                last.after_value.push_str(&element.after_value);
+            }
+        }
+        Some(element.value)
+    }
+}
+
+/// The line break and indentation a sibling's leading trivia ends with,
+/// without any comments in it. Siblings on one line are separated by a space.
+fn indentation(leading: &str) -> String {
+    match leading.rfind('\n') {
+        Some(at) => leading[at..]
+            .chars()
+            .take_while(|char| char.is_whitespace())
+            .collect(),
+        None => " ".to_string(),
+    }
+}
+
+impl Display for CstDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.value, self.trailing)
+    }
+}
+
+impl Display for CstValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CstValue::Scalar(scalar) => write!(f, "{}", scalar.text),
+            CstValue::Object(obj) => write!(f, "{}", obj),
+            CstValue::Array(arr) => write!(f, "{}", arr),
+        }
+    }
+}
+
+impl Display for CstObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for member in &self.members {
+            write!(
+                f,
+                "{}\"{}\"{}:{}{}{}",
+                member.leading,
+                member.key,
+                member.before_colon,
+                member.after_colon,
+                member.value,
+                member.after_value
+            )?;
+            if member.comma {
+                write!(f, ",")?;
+            }
+        }
+        write!(f, "{}}}", self.trailing)
+    }
+}
+
+impl Display for CstArray {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for element in &self.elements {
+            write!(
+                f,
+                "{}{}{}",
+                element.leading, element.value, element.after_value
+            )?;
+            if element.comma {
+                write!(f, ",")?;
+            }
+        }
+        write!(f, "{}]", self.trailing)
+    }
+}
+
+/// A token and the byte range it was read from.
+type Spanned<'a> = (Span, JsonToken<'a>);
+
+/// A token together with the trivia in front of it.
+type Trivia<'a> = (String, Spanned<'a>);
+
+/// Reads tokens with their spans and turns the gaps between them into
+/// trivia.
+struct CstBuilder<'a> {
+    origin: &'a str,
+    tokens: Tokenizer<'a>,
+    /// End of the last token read.
+    end: usize,
+    /// How many containers enclose the current position.
+    depth: usize,
+}
+
+impl<'a> CstBuilder<'a> {
+    fn new(origin: &'a str) -> Self {
+        Self {
+            origin,
+            tokens: Tokenizer::jsonc(origin),
+            end: 0,
+            depth: 0,
+        }
+    }
+
+    /// The next token and the trivia in front of it.
+    fn token(&mut self) -> Result<Option<Trivia<'a>>, JsonError<'a>> {
+        match self.tokens.next_spanned().transpose()? {
+            Some((span, token)) => {
+                let trivia = self.origin[self.end..span.start].to_string();
+                self.end = span.end;
+                Ok(Some((trivia, (span, token))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn expect_token(&mut self) -> Result<Trivia<'a>, JsonError<'a>> {
+        match self.token()? {
+            Some(token) => Ok(token),
+            None => Err(UnexpectedEndOfTokens)?,
+        }
+    }
+
+    fn value(&mut self, (span, token): Spanned<'a>) -> Result<CstValue, JsonError<'a>> {
+        match token {
+            LeftBrace | LeftBracket if self.depth == MAX_DEPTH => Err(NestingTooDeep)?,
+            LeftBrace | LeftBracket => {
+                self.depth += 1;
+                let value = match token {
+                    LeftBrace => self.object().map(CstValue::Object),
+                    _ => self.array().map(CstValue::Array),
+                };
+                self.depth -= 1;
+                value
+            }
+            JsonToken::String(_) | Number(_) | True | False | Null => {
+                Ok(CstValue::Scalar(CstScalar {
+                    text: self.origin[span.range()].to_string(),
+                }))
+            }
+            token => Err(expect_value(&token))?,
+        }
+    }
+
+    fn object(&mut self) -> Result<CstObject, JsonError<'a>> {
+        let mut members: Vec<CstMember> = vec![];
+        loop {
+            let (leading, (_, token)) = self.expect_token()?;
+            let key = match token {
+                RightBrace if members.last().map(|member| member.comma).unwrap_or(true) => {
+                    return Ok(CstObject {
+                        members,
+                        trailing: leading,
+                    })
+                }
+                JsonToken::String(key) => key.into_owned(),
+                token => Err(expect_key_or_right_brace(&token))?,
+            };
+            let before_colon = match self.expect_token()? {
+                (trivia, (_, Colon)) => trivia,
+                (_, (_, token)) => Err(expect_colon_after_key(&token))?,
+            };
+            let (after_colon, first) = self.expect_token()?;
+            let value = self.value(first)?;
+            let (after_value, (_, token)) = self.expect_token()?;
+            let comma = match token {
+                Comma => true,
+                RightBrace => false,
+                token => Err(expect_a_comma_or_right_brace_after_value(&token))?,
+            };
+            members.push(CstMember {
+                leading,
+                key,
+                before_colon,
+                after_colon,
+                value,
+                after_value,
+                comma,
+            });
+            if !comma {
+                return Ok(CstObject {
+                    members,
+                    trailing: String::new(),
+                });
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<CstArray, JsonError<'a>> {
+        let mut elements: Vec<CstElement> = vec![];
+        loop {
+            let (leading, first) = self.expect_token()?;
+            if first.1 == RightBracket
+                && elements.last().map(|element| element.comma).unwrap_or(true)
+            {
+                return Ok(CstArray {
+                    elements,
+                    trailing: leading,
+                });
+            }
+            let value = self.value(first)?;
+            let (after_value, (_, token)) = self.expect_token()?;
+            let comma = match token {
+                Comma => true,
+                RightBracket => false,
+                token => Err(expect_a_comma_or_right_bracket_after_value(&token))?,
+            };
+            elements.push(CstElement {
+                leading,
+                value,
+                after_value,
+                comma,
+            });
+            if !comma {
+                return Ok(CstArray {
+                    elements,
+                    trailing: String::new(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_cst {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::token::parse_to_tokens;
+
+    const CONFIG: &str = r#"// editor settings
+{
+    "font": {"size": 12, "family": "mono"}, // inline
+    /* list */ "plugins": [ "a" ,"b", ],
+    "tabs"  :  4
+}
+"#;
+
+    #[test]
+    fn test_lossless() {
+        assert_eq!(CstDocument::parse(CONFIG).unwrap().to_string(), CONFIG);
+        for i in 1..=3 {
+            let text = std::fs::read_to_string(format!("tests/json{}.json", i)).unwrap();
+            let doc = CstDocument::parse(&text).unwrap();
+            assert_eq!(doc.to_string(), text);
+
+            let tokens = parse_to_tokens(&text).unwrap();
+            assert_eq!(
+                doc.value().to_value(),
+                Parser::new(&tokens).parse().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_edit_changes_only_that_value() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let root = doc.value_mut().as_object_mut().unwrap();
+        let font = root.get_mut("font").unwrap().as_object_mut().unwrap();
+        *font.get_mut("size").unwrap() = CstValue::parse("14").unwrap();
+        assert_eq!(
+            doc.to_string(),
+            CONFIG.replace(r#""size": 12"#, r#""size": 14"#)
+        );
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let text = "{\n  \"a\": 1,\n  // b\n  \"b\": [1]\n}";
+        let mut doc = CstDocument::parse(text).unwrap();
+        let root = doc.value_mut().as_object_mut().unwrap();
+        root.get_mut("b")
+            .unwrap()
+            .as_array_mut()
+            .unwrap()
+            .push(CstValue::parse("2").unwrap());
+        root.insert("c", CstValue::from_value(&JsonValue::True).unwrap());
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"a\": 1,\n  // b\n  \"b\": [1, 2],\n  \"c\": true\n}"
+        );
+
+        let root = doc.value_mut().as_object_mut().unwrap();
+        assert_eq!(root.remove("c"), Some(CstValue::parse("true").unwrap()));
+        assert_eq!(root.remove("missing"), None);
+        assert!(CstValue::from_value(&JsonValue::Empty).is_err());
+        let CstValue::Scalar(scalar) = CstValue::parse(r#""a\n""#).unwrap() else {
+            panic!("not a scalar");
+        };
+        assert_eq!(scalar.as_str(), r#""a\n""#);
+        assert_eq!(
+            doc.to_string(),
+            "{\n  \"a\": 1,\n  // b\n  \"b\": [1, 2]\n}"
+        );
+    }
+
+    #[test]
+    fn test_keys_are_escaped() {
+        let mut doc = CstDocument::parse(r#"{"\u0041": 1}"#).unwrap();
+        let root = doc.value_mut().as_object_mut().unwrap();
+        assert!(root.get("A").is_some());
+        root.insert("a\"b", CstValue::from_value(&JsonValue::Null).unwrap());
+        assert!(root.get("a\"b").is_some());
+        let text = doc.to_string();
+        assert_eq!(text, r#"{"\u0041": 1, "a\"b": null}"#);
+        let tokens = parse_to_tokens(&text).unwrap();
+        assert!(Parser::new(&tokens).parse().is_ok());
+
+        let root = doc.value_mut().as_object_mut().unwrap();
+        assert!(root.remove("a\"b").is_some());
+        assert!(root.remove("A").is_some());
+        assert!(root.is_empty());
+    }
+}
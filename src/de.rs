@@ -0,0 +1,751 @@
+//! Deserializing Rust types with serde, behind the `serde` feature.
+//!
+//! [`from_str`] walks the tokens directly instead of building a
+//! [`JsonValue`] first. Strings without escapes are handed to the visitor
+//! borrowed from the input, so `&str` and `#[serde(borrow)] Cow<str>` fields
+//! do not allocate.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Server<'a> {
+//!     host: &'a str,
+//!     port: u16,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let text = r#"{"host": "example.com", "port": 8080, "tags": ["a\tb"]}"#;
+//! let server: Server = rust_json::de::from_str(text).unwrap();
+//! assert_eq!((server.host, server.port), ("example.com", 8080));
+//! assert_eq!(server.tags, ["a\tb"]);
+//! ```
+//!
+//! Numbers are read as `f64`, so integers beyond 2^53 lose precision.
+
+use crate::array::JsonArray;
+use crate::error::JsonParserError::{NestingTooDeep, UnexpectedEndOfTokens};
+use crate::error::{
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_key_or_right_brace, expect_value,
+    JsonError, JsonParserError,
+};
+use crate::escape::{escape, unescape};
+use crate::keyvalue::KeyValue;
+use crate::object::JsonObject;
+use crate::parser::MAX_DEPTH;
+use crate::span::{Location, Span};
+use crate::token::JsonToken::{self, *};
+use crate::token::Tokenizer;
+use crate::value::JsonValue;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use serde::{forward_to_deserialize_any, Deserialize};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// Why [`from_str`] failed.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The input is not JSON.
+    Syntax {
+        error: JsonError<'static>,
+        location: Location,
+    },
+    /// The input is JSON, but does not fit the type, e.g. a missing field or
+    /// a string where a number was expected.
+    Data {
+        message: std::string::String,
+        location: Option<Location>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (message, location): (&dyn Display, _) = match self {
+            Error::Syntax { error, location } => (error, Some(location)),
+            Error::Data { message, location } => (message, location.as_ref()),
+        };
+        match location {
+            Some(location) => write!(
+                f,
+                "{} at line {} column {}",
+                message, location.line, location.column
+            ),
+            None => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Data {
+            message: msg.to_string(),
+            location: None,
+        }
+    }
+}
+
+/// Deserializes a `T` from `source`, borrowing strings from it where the
+/// type allows.
+pub fn from_str<'de, T: Deserialize<'de>>(source: &'de str) -> Result<T, Error> {
+    let mut deserializer = Deserializer::new(source)?;
+    let value = T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A serde [`Deserializer`](serde::Deserializer) over one JSON document.
+pub struct Deserializer<'de> {
+    source: &'de str,
+    tokens: Vec<(Span, JsonToken<'de>)>,
+    index: usize,
+    /// The token looked at most recently; errors raised by visitors point
+    /// here.
+    last: usize,
+    /// How many containers enclose the current token.
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Tokenizes `source`, failing on the first token that is not valid.
+    pub fn new(source: &'de str) -> Result<Self, Error> {
+        let mut tokenizer = Tokenizer::new(source);
+        let mut tokens = vec![];
+        while let Some(token) = tokenizer.next_recovering() {
+            match token {
+                Ok(token) => tokens.push(token),
+                Err((span, error)) => {
+                    return Err(Error::Syntax {
+                        error: error.into(),
+                        location: span.location(source),
+                    })
+                }
+            }
+        }
+        Ok(Self {
+            source,
+            tokens,
+            index: 0,
+            last: 0,
+            depth: 0,
+        })
+    }
+
+    /// Checks that nothing follows the value that was deserialized.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.tokens.get(self.index) {
+            Some((_, token)) => {
+                self.last = self.index;
+                Err(self.syntax(expect_end_of_input(token)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn span(&self, index: usize) -> Span {
+        match self.tokens.get(index) {
+            Some((span, _)) => *span,
+            None => Span::new(self.source.len(), self.source.len()),
+        }
+    }
+
+    fn syntax(&self, error: JsonParserError) -> Error {
+        Error::Syntax {
+            error: JsonError::Parse(error.into_owned()),
+            location: self.span(self.last).location(self.source),
+        }
+    }
+
+    /// Fills in where a visitor's error happened.
+    fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::Data {
+                message,
+                location: None,
+            } => Error::Data {
+                message,
+                location: Some(self.span(self.last).location(self.source)),
+            },
+            error => error,
+        }
+    }
+
+    fn peek(&mut self) -> Result<JsonToken<'de>, Error> {
+        self.last = self.index;
+        match self.tokens.get(self.index) {
+            Some((_, token)) => Ok(token.clone()),
+            None => Err(self.syntax(UnexpectedEndOfTokens)),
+        }
+    }
+
+    fn next(&mut self) -> Result<JsonToken<'de>, Error> {
+        let token = self.peek()?;
+        self.index += 1;
+        Ok(token)
+    }
+
+    /// Runs `f` on the contents of the container just opened, failing if it
+    /// is nested too deeply.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.syntax(NestingTooDeep));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn end_array(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            RightBracket => Ok(()),
+            Comma => Err(de::Error::custom(
+                "the array has more elements than expected",
+            )),
+            token => Err(self.syntax(expect_a_comma_or_right_bracket_after_value(&token))),
+        }
+    }
+
+    fn end_object(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            RightBrace => Ok(()),
+            Comma => Err(de::Error::custom(
+                "the object has more members than expected",
+            )),
+            token => Err(self.syntax(expect_a_comma_or_right_brace_after_value(&token))),
+        }
+    }
+}
+
+/// Decodes a string token, staying borrowed when there are no escapes.
+fn text(raw: Cow<'_, str>) -> Cow<'_, str> {
+    match raw {
+        Cow::Borrowed(raw) => unescape(raw),
+        Cow::Owned(raw) => Cow::Owned(unescape(&raw).into_owned()),
+    }
+}
+
+fn visit_text<'de, V: Visitor<'de>>(raw: Cow<'de, str>, visitor: V) -> Result<V::Value, Error> {
+    match text(raw) {
+        Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+        Cow::Owned(text) => visitor.visit_string(text),
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.next()? {
+            Null => visitor.visit_unit(),
+            True => visitor.visit_bool(true),
+            False => visitor.visit_bool(false),
+            // Hand integers over as integers so that `u16` and friends
+            // accept them.
+            Number(number) if number.fract() == 0.0 && number >= i64::MIN as f64 => {
+                if number < 0.0 {
+                    visitor.visit_i64(number as i64)
+                } else if number < u64::MAX as f64 {
+                    visitor.visit_u64(number as u64)
+                } else {
+                    visitor.visit_f64(number)
+                }
+            }
+            Number(number) => visitor.visit_f64(number),
+            String(raw) => visit_text(raw, visitor),
+            LeftBracket => self.nested(|de| {
+                let value = visitor.visit_seq(Elements { de, first: true })?;
+                de.end_array()?;
+                Ok(value)
+            }),
+            LeftBrace => self.nested(|de| {
+                let value = visitor.visit_map(Members { de, first: true })?;
+                de.end_object()?;
+                Ok(value)
+            }),
+            token => Err(self.syntax(expect_value(&token))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.peek()? == Null {
+            self.index += 1;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are strings, the others objects with a single member
+    /// named after the variant.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.peek()? {
+            String(raw) => {
+                self.index += 1;
+                visitor.visit_enum(text(raw).into_deserializer())
+            }
+            LeftBrace => {
+                self.index += 1;
+                self.nested(|de| {
+                    let value = visitor.visit_enum(Variant { de: &mut *de })?;
+                    de.end_object()?;
+                    Ok(value)
+                })
+            }
+            token => Err(de::Error::custom(format!(
+                "expected a string or an object for an enum, found `{}`",
+                token
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Elements<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> SeqAccess<'de> for Elements<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.de.peek()? {
+            RightBracket => return Ok(None),
+            Comma if !self.first => self.de.index += 1,
+            _ if self.first => {}
+            token => {
+                return Err(self
+                    .de
+                    .syntax(expect_a_comma_or_right_bracket_after_value(&token)))
+            }
+        }
+        self.first = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct Members<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> MapAccess<'de> for Members<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.de.peek()? {
+            RightBrace => return Ok(None),
+            Comma if !self.first => self.de.index += 1,
+            _ if self.first => {}
+            token => {
+                return Err(self
+                    .de
+                    .syntax(expect_a_comma_or_right_brace_after_value(&token)))
+            }
+        }
+        self.first = false;
+        seed.deserialize(Key { de: &mut *self.de }).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.de.next()? {
+            Colon => seed.deserialize(&mut *self.de),
+            token => Err(self.de.syntax(expect_colon_after_key(&token))),
+        }
+    }
+}
+
+/// Deserializes an object key. Keys are always strings, but types such as
+/// `HashMap<u32, _>` get them parsed as numbers.
+struct Key<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'a, 'de> Key<'a, 'de> {
+    fn raw(&mut self) -> Result<Cow<'de, str>, Error> {
+        match self.de.next()? {
+            String(raw) => Ok(raw),
+            token => Err(self.de.syntax(expect_key_or_right_brace(&token))),
+        }
+    }
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(fn $method<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+            let text = text(self.raw()?);
+            match text.parse() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&text), &visitor)),
+            }
+        })*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Key<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        visit_text(self.raw()?, visitor)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(text(self.raw()?).into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// The `{"Variant": ...}` form of an enum, after the `{`.
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for Variant<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(Key { de: &mut *self.de })?;
+        match self.de.next()? {
+            Colon => Ok((variant, self)),
+            token => Err(self.de.syntax(expect_colon_after_key(&token))),
+        }
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Variant<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// A string in the escaped form [`JsonValue`] keeps, borrowed from the
+/// input when it needs no escaping.
+struct Escaped<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for Escaped<'de> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EscapedVisitor;
+
+        impl<'de> Visitor<'de> for EscapedVisitor {
+            type Value = Escaped<'de>;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "a string")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, text: &'de str) -> Result<Self::Value, E> {
+                Ok(Escaped(escape(text)))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+                Ok(Escaped(Cow::Owned(escape(text).into_owned())))
+            }
+
+            fn visit_string<E: de::Error>(
+                self,
+                text: std::string::String,
+            ) -> Result<Self::Value, E> {
+                match escape(&text) {
+                    Cow::Borrowed(_) => Ok(Escaped(Cow::Owned(text))),
+                    Cow::Owned(raw) => Ok(Escaped(Cow::Owned(raw))),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(EscapedVisitor)
+    }
+}
+
+/// Builds a [`JsonValue`] from any self-describing format. Strings borrow
+/// from the input where the format allows it.
+impl<'de> Deserialize<'de> for JsonValue<'de> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = JsonValue<'de>;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                write!(f, "any JSON value")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                Ok(if value {
+                    JsonValue::True
+                } else {
+                    JsonValue::False
+                })
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                Ok(JsonValue::Number(Box::new(value as f64)))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(JsonValue::Number(Box::new(value as f64)))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+                Ok(JsonValue::Number(Box::new(value)))
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, text: &'de str) -> Result<Self::Value, E> {
+                Ok(JsonValue::String(Box::new(escape(text))))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+                Ok(JsonValue::String(Box::new(Cow::Owned(
+                    escape(text).into_owned(),
+                ))))
+            }
+
+            fn visit_string<E: de::Error>(
+                self,
+                text: std::string::String,
+            ) -> Result<Self::Value, E> {
+                Escaped::deserialize(text.into_deserializer())
+                    .map(|Escaped(raw)| JsonValue::String(Box::new(raw)))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(JsonValue::Null)
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(JsonValue::Null)
+            }
+
+            fn visit_some<D: de::Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut array = vec![];
+                while let Some(value) = seq.next_element()? {
+                    array.push(value);
+                }
+                Ok(JsonValue::Array(Box::new(JsonArray { array })))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut children = vec![];
+                while let Some(Escaped(key)) = map.next_key()? {
+                    let value = map.next_value()?;
+                    children.push(KeyValue { key, value });
+                }
+                Ok(JsonValue::Object(Box::new(JsonObject { children })))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests_de {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::token::parse_to_tokens;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        motto: Cow<'a, str>,
+        port: u16,
+        ratio: f64,
+        tags: Vec<std::string::String>,
+        owner: Option<Owner>,
+        #[serde(default)]
+        verbose: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Owner {
+        id: u64,
+        admin: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { w: u8, h: u8 },
+    }
+
+    #[test]
+    fn test_structs_borrow_strings() {
+        let text = r#"{
+            "name": "server",
+            "motto": "tab\there",
+            "port": 8080,
+            "ratio": 0.5,
+            "tags": ["a", "b\"c"],
+            "owner": {"id": 7, "admin": true},
+            "unknown": [1, {"nested": null}]
+        }"#;
+        let config: Config = from_str(text).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "server",
+                motto: Cow::Borrowed("tab\there"),
+                port: 8080,
+                ratio: 0.5,
+                tags: vec!["a".to_string(), "b\"c".to_string()],
+                owner: Some(Owner { id: 7, admin: true }),
+                verbose: false,
+            }
+        );
+        // Nothing to unescape, so the field points into `text`.
+        assert!(text
+            .as_bytes()
+            .as_ptr_range()
+            .contains(&config.name.as_ptr()));
+        assert!(matches!(config.motto, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_collections_and_enums() {
+        let map: HashMap<u32, Vec<Shape>> = from_str(
+            r#"{"1": ["Point", {"Circle": 1.5}], "2": [{"Line": [-1, 2]}, {"Rect": {"w": 3, "h": 4}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(map[&1], [Shape::Point, Shape::Circle(1.5)]);
+        assert_eq!(map[&2], [Shape::Line(-1, 2), Shape::Rect { w: 3, h: 4 }]);
+
+        let tuple: (bool, Option<i8>, char, ()) = from_str(r#"[true, null, "x", null]"#).unwrap();
+        assert_eq!(tuple, (true, None, 'x', ()));
+        let map: BTreeMap<std::string::String, f32> = from_str("{}").unwrap();
+        assert!(map.is_empty());
+        assert_eq!(from_str::<u8>(" 42 ").unwrap(), 42);
+    }
+
+    #[test]
+    fn test_errors_have_locations() {
+        let e = from_str::<Owner>("{\"id\": 1,\n \"admin\": \"yes\"}").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid type: string \"yes\", expected a boolean at line 2 column 11"
+        );
+        let e = from_str::<Owner>(r#"{"id": 1}"#).unwrap_err();
+        assert_eq!(e.to_string(), "missing field `admin` at line 1 column 9");
+        let e = from_str::<u8>("300").unwrap_err();
+        assert!(matches!(e, Error::Data { .. }));
+
+        for text in ["[1,]", "{\"id\" 1}", "[1] 2", "[1", "\"\\x\""] {
+            let e = from_str::<JsonValue>(text).unwrap_err();
+            assert!(matches!(e, Error::Syntax { .. }), "{}: {:?}", text, e);
+        }
+        let e = from_str::<(u8,)>("[1, 2]").unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("the array has more elements than expected"));
+    }
+
+    #[test]
+    fn test_json_value() {
+        let text = r#"{"a": [1, -2.5, true, null], "b\n": "x\u0041", "c": {}}"#;
+        let value: JsonValue = from_str(text).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,-2.5,true,null],"b\n":"xA","c":{}}"#
+        );
+        let tokens = parse_to_tokens(r#"{"plain": "text"}"#).unwrap();
+        let parsed = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(
+            from_str::<JsonValue>(r#"{"plain": "text"}"#).unwrap(),
+            parsed
+        );
+    }
+}
@@ -1,62 +1,225 @@
 use crate::error::JsonParserError::UnexpectedToken;
-use crate::token::JsonToken;
+use crate::token::{JsonToken, TokenParseError};
+use std::fmt::{Display, Formatter};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, PartialEq)]
 pub enum JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr<'a>),
     InternalJsonParserError(JsonParserInternalError),
     UnexpectedEndOfTokens,
+    /// Objects and arrays are nested more than
+    /// [`MAX_DEPTH`](crate::parser::MAX_DEPTH) levels deep.
+    NestingTooDeep,
+    /// An [`EventReader`](crate::event::EventReader) was read again after it
+    /// had already reported an error. Only `EventReader` returns this; the
+    /// other parsers stop at their first error and never produce it.
+    ReaderFailed,
+    /// The `{` or `[` a closing bracket of the other kind was found inside.
+    /// Only [`Recovered`](crate::recover::Recovered) reports this, next to
+    /// the closing bracket; the other parsers report just the bracket.
+    Unclosed(JsonToken<'a>),
 }
+/// Any failure while turning text into values: either the text could not be
+/// tokenized, or the tokens do not form a valid document.
+#[derive(Debug, PartialEq)]
+pub enum JsonError<'a> {
+    Token(TokenParseError),
+    Parse(JsonParserError<'a>),
+}
+
+impl<'a> JsonError<'a> {
+    pub fn into_owned(self) -> JsonError<'static> {
+        match self {
+            JsonError::Token(e) => JsonError::Token(e),
+            JsonError::Parse(e) => JsonError::Parse(e.into_owned()),
+        }
+    }
+}
+
+impl<'a> JsonParserError<'a> {
+    pub fn into_owned(self) -> JsonParserError<'static> {
+        match self {
+            UnexpectedToken(UnexpectedTokenErrorDecr {
+                expect,
+                kind,
+                actual,
+                msg,
+            }) => UnexpectedToken(UnexpectedTokenErrorDecr {
+                expect,
+                kind,
+                actual: actual.into_owned(),
+                msg,
+            }),
+            JsonParserError::InternalJsonParserError(e) => {
+                JsonParserError::InternalJsonParserError(e)
+            }
+            JsonParserError::UnexpectedEndOfTokens => JsonParserError::UnexpectedEndOfTokens,
+            JsonParserError::NestingTooDeep => JsonParserError::NestingTooDeep,
+            JsonParserError::ReaderFailed => JsonParserError::ReaderFailed,
+            JsonParserError::Unclosed(token) => JsonParserError::Unclosed(token.into_owned()),
+        }
+    }
+}
+
+impl<'a> From<TokenParseError> for JsonError<'a> {
+    fn from(e: TokenParseError) -> Self {
+        JsonError::Token(e)
+    }
+}
+
+impl<'a> From<JsonParserError<'a>> for JsonError<'a> {
+    fn from(e: JsonParserError<'a>) -> Self {
+        JsonError::Parse(e)
+    }
+}
+
+/// What the parser was looking for when it met an unexpected token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expected {
+    LeftBracketOrBrace,
+    LeftBrace,
+    Colon,
+    CommaOrRightBrace,
+    KeyOrRightBrace,
+    CommaOrRightBracket,
+    Value,
+    EndOfInput,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct UnexpectedTokenErrorDecr<'a> {
     pub expect: &'static str,
-    pub actual: &'a JsonToken<'a>,
+    pub kind: Expected,
+    pub actual: JsonToken<'a>,
     pub msg: &'static str,
 }
 
+impl<'a> UnexpectedTokenErrorDecr<'a> {
+    /// `kind` spelled out for people rather than tests.
+    pub fn expected(&self) -> &'static str {
+        match self.kind {
+            Expected::LeftBracketOrBrace => "`{` or `[`",
+            Expected::LeftBrace => "`{`",
+            Expected::Colon => "`:`",
+            Expected::CommaOrRightBrace => "`,` or `}`",
+            Expected::KeyOrRightBrace => "a key or `}`",
+            Expected::CommaOrRightBracket => "`,` or `]`",
+            Expected::Value => "a value",
+            Expected::EndOfInput => "end of input",
+        }
+    }
+}
+
+impl<'a> Display for JsonParserError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnexpectedToken(e) => write!(f, "expected {}, found `{}`", e.expected(), e.actual),
+            JsonParserError::InternalJsonParserError(e) => {
+                write!(f, "internal parser error: {:?}", e)
+            }
+            JsonParserError::UnexpectedEndOfTokens => write!(f, "unexpected end of input"),
+            JsonParserError::NestingTooDeep => write!(
+                f,
+                "objects and arrays nested more than {} levels deep",
+                crate::parser::MAX_DEPTH
+            ),
+            JsonParserError::ReaderFailed => write!(f, "the reader already failed earlier"),
+            JsonParserError::Unclosed(token) => write!(f, "unclosed `{}`", token),
+        }
+    }
+}
+
+impl<'a> Display for JsonError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::Token(e) => write!(f, "{}", e),
+            JsonError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<'a> std::error::Error for JsonParserError<'a> {}
+
+impl<'a> std::error::Error for JsonError<'a> {}
+
 #[derive(Debug, PartialEq)]
 pub enum JsonParserInternalError {
     TokenIndexOutOfRange,
 }
 
 pub fn expect_first_token_is_left_bracket_or_brace<'a>(
-    token: &'a JsonToken,
+    token: &JsonToken<'a>,
 ) -> JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr {
         expect: "{ or [",
-        actual: token,
+        kind: Expected::LeftBracketOrBrace,
+        actual: token.clone(),
         msg: "it should be { or [ on the first token for json value",
     })
 }
 
-pub fn expect_first_token_is_left_brace<'a>(token: &'a JsonToken) -> JsonParserError<'a> {
+pub fn expect_first_token_is_left_brace<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr {
         expect: "{",
-        actual: token,
+        kind: Expected::LeftBrace,
+        actual: token.clone(),
         msg: "it should be { on the first token for json object",
     })
 }
 
-pub fn expect_colon_after_key<'a>(token: &'a JsonToken) -> JsonParserError<'a> {
+pub fn expect_colon_after_key<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr {
         expect: ":",
-        actual: token,
+        kind: Expected::Colon,
+        actual: token.clone(),
         msg: "it should be : after key",
     })
 }
 
-pub fn expect_a_comma_or_right_brace_after_value<'a>(token: &'a JsonToken) -> JsonParserError<'a> {
+pub fn expect_a_comma_or_right_brace_after_value<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr {
         expect: ", or }",
-        actual: token,
+        kind: Expected::CommaOrRightBrace,
+        actual: token.clone(),
         msg: "it should be , or } after value",
     })
 }
 
-pub fn expect_key_or_right_brace<'a>(token: &'a JsonToken) -> JsonParserError<'a> {
+pub fn expect_key_or_right_brace<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
     UnexpectedToken(UnexpectedTokenErrorDecr {
         expect: "} or key",
-        actual: token,
+        kind: Expected::KeyOrRightBrace,
+        actual: token.clone(),
         msg: "it should be value after :",
     })
 }
+
+pub fn expect_a_comma_or_right_bracket_after_value<'a>(
+    token: &JsonToken<'a>,
+) -> JsonParserError<'a> {
+    UnexpectedToken(UnexpectedTokenErrorDecr {
+        expect: ", or ]",
+        kind: Expected::CommaOrRightBracket,
+        actual: token.clone(),
+        msg: "it should be , or ] after value",
+    })
+}
+
+pub fn expect_value<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
+    UnexpectedToken(UnexpectedTokenErrorDecr {
+        expect: "string, number, true, false, null, {, [",
+        kind: Expected::Value,
+        actual: token.clone(),
+        msg: "it should be string, number, true, false, null, {, [",
+    })
+}
+
+pub fn expect_end_of_input<'a>(token: &JsonToken<'a>) -> JsonParserError<'a> {
+    UnexpectedToken(UnexpectedTokenErrorDecr {
+        expect: "end of input",
+        kind: Expected::EndOfInput,
+        actual: token.clone(),
+        msg: "it should be nothing after the value",
+    })
+}
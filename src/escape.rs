@@ -0,0 +1,125 @@
+//! Converting between the escaped form strings are stored in and the text
+//! they stand for.
+//!
+//! Tokens and values keep string contents exactly as written, without the
+//! quotes, so that parsing never has to copy. These functions are for the
+//! places that need the actual text, or need to turn text back into a
+//! string literal. Both borrow when there is nothing to change.
+//!
+//! ```
+//! use rust_json::escape::{escape, unescape};
+//!
+//! assert_eq!(unescape(r#"tab\tquote\" é"#), "tab\tquote\" é");
+//! assert_eq!(escape("tab\tquote\""), r#"tab\tquote\""#);
+//! ```
+
+use std::borrow::Cow;
+
+/// Decodes the escapes in a string's raw contents.
+///
+/// Unpaired surrogates become U+FFFD. The tokenizer only lets valid escapes
+/// through, so anything else after a `\` is kept as it is.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    let Some(first) = raw.find('\\') else {
+        return Cow::Borrowed(raw);
+    };
+    let mut text = String::with_capacity(raw.len());
+    text.push_str(&raw[..first]);
+    let mut chars = raw[first..].chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            text.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => text.push('\u{8}'),
+            Some('f') => text.push('\u{c}'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some('t') => text.push('\t'),
+            Some('u') => {
+                let high = hex4(&mut chars);
+                let char = match high {
+                    Some(high @ 0xD800..=0xDBFF) => {
+                        // Only consume the next escape if it completes the pair.
+                        let mut lookahead = chars.clone();
+                        match (lookahead.next(), lookahead.next(), hex4(&mut lookahead)) {
+                            (Some('\\'), Some('u'), Some(low @ 0xDC00..=0xDFFF)) => {
+                                chars = lookahead;
+                                char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                            }
+                            _ => None,
+                        }
+                    }
+                    Some(code) => char::from_u32(code),
+                    None => None,
+                };
+                text.push(char.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    Cow::Owned(text)
+}
+
+fn hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.clone().next()?.to_digit(16)?;
+        chars.next();
+    }
+    Some(code)
+}
+
+/// Escapes `text` so it can be written between double quotes: `"` and `\`
+/// get a backslash, and control characters become `\n`-style or `\u00XX`
+/// escapes.
+pub fn escape(text: &str) -> Cow<'_, str> {
+    let needs_escape = |char: char| char == '"' || char == '\\' || char < ' ';
+    let Some(first) = text.find(needs_escape) else {
+        return Cow::Borrowed(text);
+    };
+    let mut raw = String::with_capacity(text.len() + 2);
+    raw.push_str(&text[..first]);
+    for char in text[first..].chars() {
+        match char {
+            '"' => raw.push_str("\\\""),
+            '\\' => raw.push_str("\\\\"),
+            '\u{8}' => raw.push_str("\\b"),
+            '\u{c}' => raw.push_str("\\f"),
+            '\n' => raw.push_str("\\n"),
+            '\r' => raw.push_str("\\r"),
+            '\t' => raw.push_str("\\t"),
+            char if char < ' ' => raw.push_str(&format!("\\u{:04x}", char as u32)),
+            char => raw.push(char),
+        }
+    }
+    Cow::Owned(raw)
+}
+
+#[cfg(test)]
+mod tests_escape {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(unescape(r#"\"\\\/\b\f\n\r\t"#), "\"\\/\u{8}\u{c}\n\r\t");
+        assert_eq!(unescape(r"\u0041\u00e9\u20AC"), "Aé€");
+        assert_eq!(unescape(r"\uD834\uDD1E!"), "𝄞!");
+        // Unpaired surrogates cannot be represented in a `str`.
+        assert_eq!(unescape(r"\uD800\n"), "\u{FFFD}\n");
+        assert_eq!(unescape(r"\uDD1E\uD834"), "\u{FFFD}\u{FFFD}");
+        assert_eq!(unescape(r"\uD800A"), "\u{FFFD}A");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches!(escape("plain é"), Cow::Borrowed("plain é")));
+        assert_eq!(escape("a\"b\\c\nd\u{1}"), r#"a\"b\\c\nd\u0001"#);
+        for text in ["", "\u{0}\u{1f}\u{7f}", "𝄞 \" \\ \r\n"] {
+            assert_eq!(unescape(&escape(text)), text);
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! A pull parser that reports a document as a sequence of events.
+//!
+//! Building a [`JsonValue`] tree is wasteful when only a few fields of a large
+//! document are needed. [`EventReader`] walks the tokens lazily, checks that
+//! they form a valid document, and hands out one [`JsonEvent`] at a time.
+//! Subtrees that are not interesting can be jumped over with
+//! [`EventReader::skip_value`], or materialized with
+//! [`EventReader::read_value`].
+//!
+//! ```
+//! use rust_json::event::{EventReader, JsonEvent};
+//!
+//! let mut reader = EventReader::new(r#"{"big": [1, 2, 3], "name": "Jack"}"#);
+//! assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::StartObject));
+//! assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("big".into())));
+//! reader.skip_value().unwrap();
+//! assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::Key("name".into())));
+//! assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::String("Jack".into())));
+//! ```
+
+use crate::array::JsonArray;
+use crate::error::JsonParserError::{NestingTooDeep, ReaderFailed, UnexpectedEndOfTokens};
+use crate::error::{
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_key_or_right_brace, expect_value,
+    JsonError,
+};
+use crate::keyvalue::KeyValue;
+use crate::object::JsonObject;
+use crate::parser::MAX_DEPTH;
+use crate::token::JsonToken::*;
+use crate::token::{JsonToken, Tokenizer};
+use crate::value::JsonValue;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Number(f64),
+    True,
+    False,
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Root,
+    Key,
+    KeyOrEnd,
+    Value,
+    ValueOrEnd,
+    CommaOrEnd,
+    Done,
+    /// An error was returned; the document is not valid, and nothing more
+    /// can be read from it.
+    Failed,
+}
+
+/// Reads one top-level value as a stream of [`JsonEvent`]s.
+#[derive(Debug, Clone)]
+pub struct EventReader<'a> {
+    tokens: Tokenizer<'a>,
+    stack: Vec<Container>,
+    expect: Expect,
+}
+
+impl<'a> EventReader<'a> {
+    pub fn new(origin: &'a str) -> Self {
+        Self::from_tokenizer(Tokenizer::new(origin))
+    }
+
+    pub fn from_tokenizer(tokens: Tokenizer<'a>) -> Self {
+        Self {
+            tokens,
+            stack: vec![],
+            expect: Expect::Root,
+        }
+    }
+
+    /// Nesting depth of the reader: 0 outside the document, 1 directly inside
+    /// the top-level container, and so on.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Byte offset just past the last token read.
+    pub fn offset(&self) -> usize {
+        self.tokens.offset()
+    }
+
+    /// Returns the next event, or `None` once the document is complete.
+    /// After an error, every later call fails with
+    /// [`ReaderFailed`].
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent<'a>>, JsonError<'a>> {
+        let result = self.read_event();
+        if result.is_err() {
+            self.expect = Expect::Failed;
+        }
+        result
+    }
+
+    /// Skips the value the next event would start, including everything
+    /// nested in it. Call it after a [`JsonEvent::Key`] or in place of an
+    /// array element; if the next event ends the container or is a key
+    /// instead, there is no value to skip and it fails.
+    pub fn skip_value(&mut self) -> Result<(), JsonError<'a>> {
+        let depth = self.depth();
+        let not_a_value = match self.next_event()? {
+            None => Err(UnexpectedEndOfTokens)?,
+            Some(JsonEvent::EndObject) => RightBrace,
+            Some(JsonEvent::EndArray) => RightBracket,
+            Some(JsonEvent::Key(key)) => String(key),
+            Some(_) => {
+                while self.depth() > depth {
+                    if self.next_event()?.is_none() {
+                        Err(UnexpectedEndOfTokens)?
+                    }
+                }
+                return Ok(());
+            }
+        };
+        self.expect = Expect::Failed;
+        Err(expect_value(&not_a_value))?
+    }
+
+    /// Reads the rest of the document and checks that nothing but whitespace
+    /// follows it.
+    pub fn finish(mut self) -> Result<(), JsonError<'a>> {
+        while self.next_event()?.is_some() {}
+        match self.tokens.next().transpose()? {
+            Some(token) => Err(expect_end_of_input(&token))?,
+            None => Ok(()),
+        }
+    }
+
+    /// Builds the value the next event would start into a [`JsonValue`].
+    pub fn read_value(&mut self) -> Result<JsonValue<'a>, JsonError<'a>> {
+        match self.next_event()? {
+            None => Err(UnexpectedEndOfTokens)?,
+            Some(event) => self.value_from(event),
+        }
+    }
+
+    fn value_from(&mut self, event: JsonEvent<'a>) -> Result<JsonValue<'a>, JsonError<'a>> {
+        Ok(match event {
+            JsonEvent::String(str) => JsonValue::String(Box::new(str)),
+            JsonEvent::Number(num) => JsonValue::Number(Box::new(num)),
+            JsonEvent::True => JsonValue::True,
+            JsonEvent::False => JsonValue::False,
+            JsonEvent::Null => JsonValue::Null,
+            JsonEvent::StartObject => {
+                let mut obj = JsonObject { children: vec![] };
+                while let Some(JsonEvent::Key(key)) = self.next_event()? {
+                    let value = self.read_value()?;
+                    obj.children.push(KeyValue { key, value });
+                }
+                JsonValue::Object(Box::new(obj))
+            }
+            JsonEvent::StartArray => {
+                let mut arr = JsonArray { array: vec![] };
+                loop {
+                    match self.next_event()? {
+                        Some(JsonEvent::EndArray) | None => break,
+                        Some(event) => arr.array.push(self.value_from(event)?),
+                    }
+                }
+                JsonValue::Array(Box::new(arr))
+            }
+            // Validation in `read_event` never yields these here.
+            JsonEvent::Key(_) | JsonEvent::EndObject | JsonEvent::EndArray => JsonValue::Empty,
+        })
+    }
+
+    fn read_event(&mut self) -> Result<Option<JsonEvent<'a>>, JsonError<'a>> {
+        loop {
+            match self.expect {
+                Expect::Done => return Ok(None),
+                Expect::Failed => Err(ReaderFailed)?,
+                _ => {}
+            }
+            let token = match self.tokens.next().transpose()? {
+                Some(token) => token,
+                // Including before the first value: a document cannot be
+                // empty.
+                None => Err(UnexpectedEndOfTokens)?,
+            };
+            let container = self.stack.last().copied();
+            match (self.expect, token) {
+                (Expect::Key | Expect::KeyOrEnd, String(key)) => {
+                    match self.tokens.next().transpose()? {
+                        Some(Colon) => {}
+                        Some(token) => Err(expect_colon_after_key(&token))?,
+                        None => Err(UnexpectedEndOfTokens)?,
+                    }
+                    self.expect = Expect::Value;
+                    return Ok(Some(JsonEvent::Key(key)));
+                }
+                (Expect::KeyOrEnd | Expect::CommaOrEnd, RightBrace)
+                    if container == Some(Container::Object) =>
+                {
+                    return Ok(Some(self.close()))
+                }
+                (Expect::Key | Expect::KeyOrEnd, token) => Err(expect_key_or_right_brace(&token))?,
+                (Expect::ValueOrEnd | Expect::CommaOrEnd, RightBracket)
+                    if container == Some(Container::Array) =>
+                {
+                    return Ok(Some(self.close()))
+                }
+                (Expect::CommaOrEnd, Comma) => {
+                    self.expect = match container {
+                        Some(Container::Object) => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                }
+                (Expect::CommaOrEnd, token) => match container {
+                    Some(Container::Object) => {
+                        Err(expect_a_comma_or_right_brace_after_value(&token))?
+                    }
+                    _ => Err(expect_a_comma_or_right_bracket_after_value(&token))?,
+                },
+                (_, token) => return self.value(token).map(Some),
+            }
+        }
+    }
+
+    fn value(&mut self, token: JsonToken<'a>) -> Result<JsonEvent<'a>, JsonError<'a>> {
+        let event = match token {
+            String(str) => JsonEvent::String(str),
+            Number(num) => JsonEvent::Number(num),
+            True => JsonEvent::True,
+            False => JsonEvent::False,
+            Null => JsonEvent::Null,
+            LeftBrace | LeftBracket => return self.open(token),
+            token => Err(expect_value(&token))?,
+        };
+        self.after_value();
+        Ok(event)
+    }
+
+    fn open(&mut self, token: JsonToken<'a>) -> Result<JsonEvent<'a>, JsonError<'a>> {
+        if self.stack.len() == MAX_DEPTH {
+            Err(NestingTooDeep)?
+        }
+        Ok(if token == LeftBrace {
+            self.stack.push(Container::Object);
+            self.expect = Expect::KeyOrEnd;
+            JsonEvent::StartObject
+        } else {
+            self.stack.push(Container::Array);
+            self.expect = Expect::ValueOrEnd;
+            JsonEvent::StartArray
+        })
+    }
+
+    fn close(&mut self) -> JsonEvent<'a> {
+        let container = self.stack.pop();
+        self.after_value();
+        match container {
+            Some(Container::Object) => JsonEvent::EndObject,
+            _ => JsonEvent::EndArray,
+        }
+    }
+
+    fn after_value(&mut self) {
+        self.expect = if self.stack.is_empty() {
+            Expect::Done
+        } else {
+            Expect::CommaOrEnd
+        };
+    }
+}
+
+impl<'a> Iterator for EventReader<'a> {
+    type Item = Result<JsonEvent<'a>, JsonError<'a>>;
+
+    /// Ends after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.expect == Expect::Failed {
+            return None;
+        }
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests_event_reader {
+    use super::JsonEvent::*;
+    use super::{EventReader, JsonError};
+    use crate::error::JsonParserError::{ReaderFailed, UnexpectedEndOfTokens};
+    use crate::error::{expect_a_comma_or_right_brace_after_value, expect_value};
+    use crate::parser::Parser;
+    use crate::token::{parse_to_tokens, JsonToken};
+
+    #[test]
+    fn test_events() {
+        let json = r#"{"a": [1, true, null], "b": {}, "c": "x"}"#;
+        let events: Vec<_> = EventReader::new(json).map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            vec![
+                StartObject,
+                Key("a".into()),
+                StartArray,
+                Number(1.0),
+                True,
+                Null,
+                EndArray,
+                Key("b".into()),
+                StartObject,
+                EndObject,
+                Key("c".into()),
+                String("x".into()),
+                EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_and_read_value() {
+        let json = r#"[{"skip": [[1], {"x": 2}]}, "kept", {"deep": [false]}]"#;
+        let mut reader = EventReader::new(json);
+        assert_eq!(reader.next_event().unwrap(), Some(StartArray));
+        reader.skip_value().unwrap();
+        reader.skip_value().unwrap();
+        let value = reader.read_value().unwrap();
+        assert_eq!(value.to_string(), r#"{"deep":[false]}"#);
+        assert_eq!(reader.next_event().unwrap(), Some(EndArray));
+        assert_eq!(reader.next_event().unwrap(), None);
+
+        let mut reader = EventReader::new(r#" "top" "#);
+        assert_eq!(reader.read_value().unwrap().to_string(), r#""top""#);
+        assert_eq!(reader.depth(), 0);
+        reader.finish().unwrap();
+
+        let json = r#"{"name": "Jack", "list": [1, 2]}"#;
+        let tokens = parse_to_tokens(json).unwrap();
+        let expected = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(EventReader::new(json).read_value().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_structure_is_validated() {
+        let mut reader = EventReader::new(r#"{"a": 1]"#);
+        let error = reader.find_map(Result::err).unwrap();
+        assert_eq!(
+            error,
+            JsonError::Parse(expect_a_comma_or_right_brace_after_value(
+                &JsonToken::RightBracket
+            ))
+        );
+
+        let mut reader = EventReader::new("]");
+        let error = reader.find_map(Result::err).unwrap();
+        assert_eq!(
+            error,
+            JsonError::Parse(expect_value(&JsonToken::RightBracket))
+        );
+
+        let mut reader = EventReader::new(r#"[1, 2"#);
+        let error = reader.find_map(Result::err).unwrap();
+        assert_eq!(error, JsonError::Parse(UnexpectedEndOfTokens));
+    }
+
+    #[test]
+    fn test_empty_input_is_an_error() {
+        for text in ["", " \n\t"] {
+            let mut reader = EventReader::new(text);
+            assert_eq!(
+                reader.next_event(),
+                Err(JsonError::Parse(UnexpectedEndOfTokens))
+            );
+            assert_eq!(
+                EventReader::new(text).finish(),
+                Err(JsonError::Parse(UnexpectedEndOfTokens))
+            );
+        }
+    }
+
+    #[test]
+    fn test_skip_value_needs_a_value() {
+        let mut reader = EventReader::new("[1]");
+        assert_eq!(reader.next_event().unwrap(), Some(StartArray));
+        reader.skip_value().unwrap();
+        assert_eq!(
+            reader.skip_value(),
+            Err(JsonError::Parse(expect_value(&JsonToken::RightBracket)))
+        );
+
+        let mut reader = EventReader::new(r#"{"a": 1}"#);
+        assert_eq!(reader.next_event().unwrap(), Some(StartObject));
+        assert_eq!(
+            reader.skip_value(),
+            Err(JsonError::Parse(expect_value(&JsonToken::String(
+                "a".into()
+            ))))
+        );
+
+        let mut reader = EventReader::new("{}");
+        assert_eq!(reader.next_event().unwrap(), Some(StartObject));
+        assert_eq!(
+            reader.skip_value(),
+            Err(JsonError::Parse(expect_value(&JsonToken::RightBrace)))
+        );
+    }
+
+    #[test]
+    fn test_errors_are_final() {
+        let mut reader = EventReader::new(r#"{"a": 1] "#);
+        assert!(reader.find_map(Result::err).is_some());
+        assert_eq!(reader.next(), None);
+        assert_eq!(reader.next_event(), Err(JsonError::Parse(ReaderFailed)));
+        assert_eq!(reader.finish(), Err(JsonError::Parse(ReaderFailed)));
+
+        let mut reader = EventReader::new("[1]");
+        assert_eq!(reader.next_event().unwrap(), Some(StartArray));
+        reader.skip_value().unwrap();
+        assert!(reader.skip_value().is_err());
+        assert_eq!(reader.read_value(), Err(JsonError::Parse(ReaderFailed)));
+        assert_eq!(reader.finish(), Err(JsonError::Parse(ReaderFailed)));
+    }
+}
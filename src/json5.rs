@@ -0,0 +1,353 @@
+//! Lexing rules for the relaxed JSON5 dialect.
+//!
+//! The tokenizer only calls into here when it was created with
+//! [`Tokenizer::json5`](crate::token::Tokenizer::json5). Everything is mapped
+//! onto the ordinary [`JsonToken`] vocabulary: strings are re-escaped the
+//! way JSON would write them, unquoted keys become
+//! strings, and hex numbers, `Infinity` and `NaN` become numbers.
+
+use crate::token::JsonToken::{self, False, Null, Number, String, True};
+use crate::token::NumberParseErrorKind;
+use crate::token::TokenParseError::{
+    self, ControlCharacterInString, InvalidEscape, KeyParseError, NumberParseError,
+    UnexpectedCharacter, UnterminatedComment,
+};
+use std::borrow::Cow;
+
+/// Returns the index just past the comment starting at `start`, or `None` if
+/// the `/` there does not start a comment.
+pub(crate) fn skip_comment(bytes: &[u8], start: usize) -> Result<Option<usize>, TokenParseError> {
+    match bytes.get(start + 1) {
+        Some(b'/') => Ok(Some(
+            bytes[start..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map_or(bytes.len(), |offset| start + offset + 1),
+        )),
+        Some(b'*') => bytes[start + 2..]
+            .windows(2)
+            .position(|pair| pair == b"*/")
+            .map(|offset| Some(start + 2 + offset + 2))
+            .ok_or(UnterminatedComment),
+        _ => Ok(None),
+    }
+}
+
+/// Finds the closing `'` of a string whose content starts at `from`.
+pub(crate) fn find_single_quoted_end(bytes: &[u8], from: usize) -> Result<usize, TokenParseError> {
+    let mut index = from;
+    while let Some(&byte) = bytes.get(index) {
+        match byte {
+            b'\'' => return Ok(index),
+            b'\\' => index += 2,
+            _ => index += 1,
+        }
+    }
+    Err(KeyParseError)
+}
+
+/// Rewrites the content of a JSON5 string, which starts at byte `start` of
+/// the input, into the escaping of a JSON string: the escapes JSON lacks
+/// (`\xHH`, `\'`, `\v`, `\0`, line continuations and escaped ordinary
+/// characters) are replaced, and `"` and raw control characters get escaped.
+pub(crate) fn to_json_escapes(
+    content: Cow<str>,
+    start: usize,
+) -> Result<Cow<str>, TokenParseError> {
+    if !content.contains(|char: char| matches!(char, '"' | '\\' | '\0'..='\x1F')) {
+        return Ok(content);
+    }
+    let mut escaped = std::string::String::with_capacity(content.len() + 2);
+    let mut chars = content.char_indices().peekable();
+    while let Some((index, char)) = chars.next() {
+        let invalid = InvalidEscape {
+            offset: start + index,
+        };
+        match char {
+            '\\' => match chars.next().map(|(_, char)| char) {
+                Some(char @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {
+                    escaped.push('\\');
+                    escaped.push(char);
+                }
+                Some('u') => {
+                    let hex = take_hex_digits(&mut chars, 4).ok_or(invalid)?;
+                    escaped.push_str("\\u");
+                    escaped.push_str(&hex);
+                }
+                Some('x') => {
+                    let hex = take_hex_digits(&mut chars, 2).ok_or(invalid)?;
+                    escaped.push_str("\\u00");
+                    escaped.push_str(&hex);
+                }
+                Some('v') => escaped.push_str("\\u000b"),
+                Some('0') if !chars.peek().is_some_and(|(_, char)| char.is_ascii_digit()) => {
+                    escaped.push_str("\\u0000");
+                }
+                Some('0'..='9') | None => return Err(invalid),
+                // A line continuation.
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                Some('\r') => {
+                    chars.next_if(|(_, char)| *char == '\n');
+                }
+                Some(char) => push_char(&mut escaped, char),
+            },
+            '\n' | '\r' => {
+                return Err(ControlCharacterInString {
+                    offset: start + index,
+                })
+            }
+            char => push_char(&mut escaped, char),
+        }
+    }
+    Ok(Cow::Owned(escaped))
+}
+
+/// Takes the `count` hex digits of a `\u` or `\x` escape.
+fn take_hex_digits(
+    chars: &mut impl Iterator<Item = (usize, char)>,
+    count: usize,
+) -> Option<std::string::String> {
+    let hex: std::string::String = chars.take(count).map(|(_, char)| char).collect();
+    (hex.len() == count && hex.chars().all(|char| char.is_ascii_hexdigit())).then_some(hex)
+}
+
+/// Appends an unescaped character to a JSON string's content.
+fn push_char(escaped: &mut std::string::String, char: char) {
+    match char {
+        '"' => escaped.push_str("\\\""),
+        '\0'..='\x1F' => escaped.push_str(&format!("\\u{:04x}", char as u32)),
+        char => escaped.push(char),
+    }
+}
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$'
+}
+
+/// Whether the next thing after `from`, past whitespace and comments, is a
+/// `:`, which makes the token before it an object key.
+fn is_followed_by_colon(bytes: &[u8], mut from: usize) -> bool {
+    loop {
+        match bytes.get(from) {
+            Some(b' ' | b'\t' | b'\n' | b'\r' | 0x0B | 0x0C) => from += 1,
+            Some(b'/') => match skip_comment(bytes, from) {
+                Ok(Some(end)) => from = end,
+                _ => return false,
+            },
+            Some(b':') => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Reads the unquoted identifier starting at `start`. JSON5 allows those
+/// only as object keys, so a name followed by a `:` becomes a string; in
+/// any other place only the keywords are allowed, as their literal tokens.
+pub(crate) fn parse_identifier(
+    origin: &[u8],
+    start: usize,
+) -> Result<(JsonToken<'_>, usize), TokenParseError> {
+    let unexpected = UnexpectedCharacter { offset: start };
+    if !origin
+        .get(start)
+        .is_some_and(|byte| is_identifier_start(*byte))
+    {
+        return Err(unexpected);
+    }
+    let len = origin[start..]
+        .iter()
+        .position(|byte| !(is_identifier_start(*byte) || byte.is_ascii_digit()))
+        .unwrap_or(origin.len() - start);
+    let end = start + len;
+    // Identifiers are ASCII, so this cannot fail.
+    let name = std::str::from_utf8(&origin[start..end]).map_err(|_| unexpected)?;
+    let token = match name {
+        name if is_followed_by_colon(origin, end) => String(Cow::Borrowed(name)),
+        "true" => True,
+        "false" => False,
+        "null" => Null,
+        "Infinity" => Number(f64::INFINITY),
+        "NaN" => Number(f64::NAN),
+        _ => return Err(UnexpectedCharacter { offset: start }),
+    };
+    Ok((token, end))
+}
+
+/// Parses a JSON5 number: an optional sign, then `Infinity`, `NaN`, a hex
+/// integer, or a decimal that may start or end with its dot and may carry an
+/// exponent.
+pub(crate) fn parse_number(bytes: &[u8], start: usize) -> Result<(f64, usize), TokenParseError> {
+    let mut index = start;
+    let negative = match bytes[index] {
+        b'-' => {
+            index += 1;
+            true
+        }
+        b'+' => {
+            index += 1;
+            false
+        }
+        _ => false,
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    let rest = &bytes[index..];
+
+    if rest.starts_with(b"Infinity") {
+        return Ok((sign * f64::INFINITY, index + 8));
+    }
+    if rest.starts_with(b"NaN") {
+        return Ok((f64::NAN, index + 3));
+    }
+    if rest.starts_with(b"0x") || rest.starts_with(b"0X") {
+        let digits = rest[2..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_hexdigit())
+            .count();
+        let hex = std::str::from_utf8(&rest[2..2 + digits]).unwrap_or_default();
+        return match u64::from_str_radix(hex, 16) {
+            Ok(number) => Ok((sign * number as f64, index + 2 + digits)),
+            Err(_) => Err(NumberParseError(NumberParseErrorKind::InvalidHexNumber)),
+        };
+    }
+
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let mut end = digits(index);
+    if end - index > 1 && bytes[index] == b'0' {
+        return Err(NumberParseError(NumberParseErrorKind::LeadingZero));
+    }
+    if bytes.get(end) == Some(&b'.') {
+        end = digits(end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent = end + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        end = digits(exponent);
+    }
+
+    let number_str = std::str::from_utf8(&bytes[start..end]).unwrap_or_default();
+    match number_str.parse::<f64>() {
+        Ok(number) => Ok((number, end)),
+        Err(e) => Err(NumberParseError(NumberParseErrorKind::NumberParseError(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests_json5 {
+    use crate::error::expect_key_or_right_brace;
+    use crate::error::expect_value;
+    use crate::parser::Parser;
+    use crate::token::JsonToken::*;
+    use crate::token::NumberParseErrorKind;
+    use crate::token::TokenParseError::{
+        ControlCharacterInString, InvalidEscape, NumberParseError, UnexpectedCharacter,
+        UnterminatedComment,
+    };
+    use crate::token::{parse_json5_to_tokens, parse_to_tokens};
+    use std::borrow::Cow::Borrowed;
+
+    #[test]
+    fn test_json5_tokens() {
+        let json5 = r#"
+        // a comment
+        {
+            unquoted: 'single "quoted" it\'s',
+            /* block
+               comment */ $hex: -0xFF,
+            dots: [.5, 5., +1e3, Infinity, -Infinity],
+        }"#;
+        let tokens = parse_json5_to_tokens(json5).unwrap();
+        assert_eq!(tokens.tokens[1], String(Borrowed("unquoted")));
+        assert_eq!(
+            tokens.tokens[3],
+            String("single \\\"quoted\\\" it's".to_string().into())
+        );
+        assert_eq!(tokens.tokens[5], String(Borrowed("$hex")));
+        assert_eq!(tokens.tokens[7], Number(-255.0));
+        assert_eq!(
+            tokens.tokens[12..21],
+            [
+                Number(0.5),
+                Comma,
+                Number(5.0),
+                Comma,
+                Number(1000.0),
+                Comma,
+                Number(f64::INFINITY),
+                Comma,
+                Number(f64::NEG_INFINITY),
+            ]
+        );
+        assert_eq!(
+            parse_json5_to_tokens("[1 /* open").unwrap_err(),
+            UnterminatedComment
+        );
+        assert_eq!(
+            parse_json5_to_tokens("[007]").unwrap_err(),
+            NumberParseError(NumberParseErrorKind::LeadingZero)
+        );
+        assert_eq!(parse_json5_to_tokens("[0, -0.5]").unwrap().tokens.len(), 5);
+    }
+
+    #[test]
+    fn test_identifiers_are_only_keys() {
+        let tokens = parse_json5_to_tokens("{a: null, null /* key */ : true, b: NaN}").unwrap();
+        let value = Parser::new_json5(&tokens).parse().unwrap();
+        assert_eq!(value.to_string(), r#"{"a":null,"null":true,"b":null}"#);
+
+        assert_eq!(
+            parse_json5_to_tokens("{a: hello}").unwrap_err(),
+            UnexpectedCharacter { offset: 4 }
+        );
+        assert!(parse_json5_to_tokens("[undefined]").is_err());
+    }
+
+    #[test]
+    fn test_strings_are_written_as_json() {
+        let json5 = "['\\x41\\'', \"\\v\\0\\q\", 'it\\\nspans', '\t\"']";
+        let tokens = parse_json5_to_tokens(json5).unwrap();
+        let value = Parser::new_json5(&tokens).parse().unwrap();
+        let json = value.to_string();
+        assert_eq!(json, r#"["\u0041'","\u000b\u0000q","itspans","\u0009\""]"#);
+        let tokens = parse_to_tokens(&json).unwrap();
+        assert_eq!(Parser::new(&tokens).parse().unwrap(), value);
+
+        assert_eq!(
+            parse_json5_to_tokens(r"['\x4']").unwrap_err(),
+            InvalidEscape { offset: 2 }
+        );
+        assert_eq!(
+            parse_json5_to_tokens(r"['\01']").unwrap_err(),
+            InvalidEscape { offset: 2 }
+        );
+        assert_eq!(
+            parse_json5_to_tokens("['a\nb']").unwrap_err(),
+            ControlCharacterInString { offset: 3 }
+        );
+    }
+
+    #[test]
+    fn test_trailing_commas_only_in_json5() {
+        let json5 = "{a: [1, 2,], b: {c: 'd',},}";
+        let tokens = parse_json5_to_tokens(json5).unwrap();
+        let value = Parser::new_json5(&tokens).parse().unwrap();
+        assert_eq!(value.to_string(), r#"{"a":[1,2],"b":{"c":"d"}}"#);
+
+        let tokens = parse_to_tokens(r#"{"a": 1,}"#).unwrap();
+        assert_eq!(
+            Parser::new(&tokens).parse().unwrap_err(),
+            expect_key_or_right_brace(&RightBrace)
+        );
+        let tokens = parse_to_tokens("[1,]").unwrap();
+        assert_eq!(
+            Parser::new(&tokens).parse().unwrap_err(),
+            expect_value(&RightBracket)
+        );
+    }
+}
@@ -0,0 +1,1465 @@
+//! RFC 9535 JSONPath queries: `$.store.book[?@.price < 10].title`.
+//!
+//! A [`JsonPath`] is parsed once and can then be run against any number of
+//! values. Each result is a [`Node`]: a reference into the queried value and
+//! its [`NormalizedPath`], such as `$['store']['book'][0]['title']`.
+//!
+//! ```
+//! use rust_json::jsonpath::JsonPath;
+//! use rust_json::parser::Parser;
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let text = r#"{"store": {"book": [
+//!     {"author": "Nigel Rees", "price": 8.95},
+//!     {"author": "Evelyn Waugh", "price": 12.99}
+//! ]}}"#;
+//! let tokens = parse_to_tokens(text).unwrap();
+//! let value = Parser::new(&tokens).parse().unwrap();
+//!
+//! let path = JsonPath::parse("$.store.book[?@.price < 10].author").unwrap();
+//! let nodes = path.query(&value);
+//! assert_eq!(nodes[0].value.to_string(), r#""Nigel Rees""#);
+//! assert_eq!(nodes[0].path.to_string(), "$['store']['book'][0]['author']");
+//! assert_eq!(nodes[0].path.to_pointer(), "/store/book/0/author");
+//! ```
+//!
+//! Supported: name, wildcard, index, slice and filter selectors, unions of
+//! them in brackets, descendant segments (`..`), comparisons, `&&`, `||`,
+//! `!`, and the functions `length`, `count`, `value`, `match` and `search`.
+//! The last two take I-Regexp patterns and need the `regex` feature; without
+//! it, queries using them fail to parse.
+
+use crate::convert::PathSegment;
+use crate::escape::unescape;
+use crate::pointer::escape_token;
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Write};
+
+/// A parsed JSONPath query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+/// Why a query could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathError {
+    pub message: String,
+    /// Byte offset into the query.
+    pub offset: usize,
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Where a node is, as the member names and indices leading to it from the
+/// root.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NormalizedPath {
+    pub(crate) segments: Vec<PathSegment>,
+}
+
+impl NormalizedPath {
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// The same location as an RFC 6901 JSON Pointer.
+    pub fn to_pointer(&self) -> String {
+        let mut pointer = String::new();
+        for segment in &self.segments {
+            pointer.push('/');
+            match segment {
+                PathSegment::Key(key) => pointer.push_str(&escape_token(key)),
+                PathSegment::Index(index) => {
+                    let _ = write!(pointer, "{}", index);
+                }
+            }
+        }
+        pointer
+    }
+}
+
+/// The normalized form of RFC 9535 section 2.7: `$['a'][0]`.
+impl Display for NormalizedPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+                PathSegment::Key(key) => {
+                    write!(f, "['")?;
+                    for char in key.chars() {
+                        match char {
+                            '\u{8}' => write!(f, "\\b")?,
+                            '\u{c}' => write!(f, "\\f")?,
+                            '\n' => write!(f, "\\n")?,
+                            '\r' => write!(f, "\\r")?,
+                            '\t' => write!(f, "\\t")?,
+                            '\'' => write!(f, "\\'")?,
+                            '\\' => write!(f, "\\\\")?,
+                            char if char < ' ' => write!(f, "\\u{:04x}", char as u32)?,
+                            char => f.write_char(char)?,
+                        }
+                    }
+                    write!(f, "']")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value selected by a query, and where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'v, 'a> {
+    pub path: NormalizedPath,
+    pub value: &'v JsonValue<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Logical),
+}
+
+/// A query inside a filter, relative to `@` or absolute from `$`.
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    /// Whether the query can only ever produce one node: every segment picks
+    /// a single name or index.
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => matches!(
+                selectors.as_slice(),
+                [Selector::Name(_)] | [Selector::Index(_)]
+            ),
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(f64),
+    String(String),
+    True,
+    False,
+    Null,
+}
+
+/// An expression with a value, or nothing: what RFC 9535 calls `ValueType`.
+#[derive(Debug, Clone, PartialEq)]
+enum Comparable {
+    Literal(Literal),
+    /// Always singular.
+    Query(Query),
+    Length(Box<Comparable>),
+    Count(Query),
+    Value(Query),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A filter expression, true or false for each candidate.
+#[derive(Debug, Clone, PartialEq)]
+enum Logical {
+    Or(Vec<Logical>),
+    And(Vec<Logical>),
+    Not(Box<Logical>),
+    Compare(Comparable, Comparison, Comparable),
+    /// True if the query selects anything.
+    Exists(Query),
+    /// `match` when `whole`, otherwise `search`.
+    Match {
+        whole: bool,
+        text: Comparable,
+        pattern: Comparable,
+    },
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, PathError> {
+        let mut parser = QueryParser {
+            text: query,
+            offset: 0,
+        };
+        parser.expect("$")?;
+        let segments = parser.segments()?;
+        if parser.offset < query.len() {
+            return Err(parser.error("expected `.`, `..` or `[`"));
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// Runs the query, returning the selected nodes in document order.
+    pub fn query<'v, 'a>(&self, value: &'v JsonValue<'a>) -> Vec<Node<'v, 'a>> {
+        let root = Node {
+            path: NormalizedPath::default(),
+            value,
+        };
+        Context::new(value).segments(&self.segments, vec![root])
+    }
+}
+
+impl<'a> JsonValue<'a> {
+    /// Parses `query` as a [`JsonPath`] and runs it against this value.
+    pub fn query(&self, query: &str) -> Result<Vec<Node<'_, 'a>>, PathError> {
+        Ok(JsonPath::parse(query)?.query(self))
+    }
+}
+
+struct QueryParser<'q> {
+    text: &'q str,
+    offset: usize,
+}
+
+/// An expression in a filter before it is known whether it is compared or
+/// tested.
+enum Primary {
+    Literal(Literal),
+    Query(Query),
+    Value(Comparable),
+    Logical(Logical),
+}
+
+/// I-JSON's exact integer range, which indices and slices must stay in.
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+impl<'q> QueryParser<'q> {
+    fn error(&self, message: impl Into<String>) -> PathError {
+        self.error_at(message, self.offset)
+    }
+
+    fn error_at(&self, message: impl Into<String>, offset: usize) -> PathError {
+        PathError {
+            message: message.into(),
+            offset,
+        }
+    }
+
+    fn rest(&self) -> &'q str {
+        &self.text[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.offset += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), PathError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", token)))
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.offset += char.len_utf8();
+        Some(char)
+    }
+
+    fn skip_blank(&mut self) {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+        self.offset += rest.len() - trimmed.len();
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, PathError> {
+        let mut segments = vec![];
+        loop {
+            let start = self.offset;
+            self.skip_blank();
+            match self.peek() {
+                Some('.' | '[') => segments.push(self.segment()?),
+                _ => {
+                    self.offset = start;
+                    return Ok(segments);
+                }
+            }
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment, PathError> {
+        if self.eat("..") {
+            let selectors = match self.peek() {
+                Some('[') => self.bracketed()?,
+                _ => vec![self.shorthand()?],
+            };
+            Ok(Segment::Descendant(selectors))
+        } else if self.eat(".") {
+            Ok(Segment::Child(vec![self.shorthand()?]))
+        } else {
+            Ok(Segment::Child(self.bracketed()?))
+        }
+    }
+
+    /// `*` or a member name after `.` or `..`.
+    fn shorthand(&mut self) -> Result<Selector, PathError> {
+        if self.eat("*") {
+            return Ok(Selector::Wildcard);
+        }
+        let is_first = |char: char| char.is_ascii_alphabetic() || char == '_' || !char.is_ascii();
+        match self.peek() {
+            Some(char) if is_first(char) => {
+                let rest = self.rest();
+                let len = rest
+                    .find(|char: char| !is_first(char) && !char.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                self.offset += len;
+                Ok(Selector::Name(rest[..len].to_string()))
+            }
+            _ => Err(self.error("expected a member name or `*`")),
+        }
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.expect("[")?;
+        let mut selectors = vec![];
+        loop {
+            self.skip_blank();
+            selectors.push(self.selector()?);
+            self.skip_blank();
+            if !self.eat(",") {
+                self.expect("]")?;
+                return Ok(selectors);
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => Ok(Selector::Name(self.string(quote)?)),
+            Some('*') => {
+                self.offset += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.offset += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(self.or()?))
+            }
+            _ => {
+                let start = self.integer()?;
+                self.skip_blank();
+                if !self.eat(":") {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| self.error("expected a selector"));
+                }
+                self.skip_blank();
+                let end = self.integer()?;
+                self.skip_blank();
+                let step = if self.eat(":") {
+                    self.skip_blank();
+                    self.integer()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    /// An optional integer: `0`, or digits without leading zeros, maybe
+    /// negative.
+    fn integer(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.offset;
+        let rest = self.rest();
+        let digits = rest.strip_prefix('-').unwrap_or(rest);
+        let len = digits
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(digits.len());
+        if len == 0 {
+            if digits.len() < rest.len() {
+                return Err(self.error("expected digits after `-`"));
+            }
+            return Ok(None);
+        }
+        let text = &rest[..rest.len() - digits.len() + len];
+        if digits.starts_with('0') && (len > 1 || text.starts_with('-')) {
+            return Err(self.error_at("integers must not have leading zeros or be `-0`", start));
+        }
+        self.offset += text.len();
+        match text.parse::<i64>() {
+            Ok(integer) if (-MAX_INTEGER..=MAX_INTEGER).contains(&integer) => Ok(Some(integer)),
+            _ => Err(self.error_at("integer out of range", start)),
+        }
+    }
+
+    /// A string literal in `quote`s, which may be `'` or `"`.
+    fn string(&mut self, quote: char) -> Result<String, PathError> {
+        self.offset += 1;
+        let mut text = String::new();
+        loop {
+            let offset = self.offset;
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some(char) if char == quote => return Ok(text),
+                Some('\\') => {
+                    let char = match self.next() {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some(char @ ('/' | '\\')) => char,
+                        Some(char) if char == quote => char,
+                        Some('u') => self.unicode_escape(offset)?,
+                        _ => return Err(self.error_at("invalid escape", offset)),
+                    };
+                    text.push(char);
+                }
+                Some(char) if char < ' ' => {
+                    return Err(self.error_at("control characters must be escaped", offset))
+                }
+                Some(char) => text.push(char),
+            }
+        }
+    }
+
+    /// The rest of a `\u` escape, including the low half of a surrogate pair.
+    fn unicode_escape(&mut self, start: usize) -> Result<char, PathError> {
+        let invalid = |parser: &Self| parser.error_at("invalid unicode escape", start);
+        let high = self.hex4().ok_or_else(|| invalid(self))?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat("\\u") {
+                    return Err(invalid(self));
+                }
+                match self.hex4() {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    _ => return Err(invalid(self)),
+                }
+            }
+            0xDC00..=0xDFFF => return Err(invalid(self)),
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| invalid(self))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = self.rest().get(..4)?;
+        let code = u32::from_str_radix(hex, 16).ok()?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.offset += 4;
+        Some(code)
+    }
+
+    fn or(&mut self) -> Result<Logical, PathError> {
+        let mut operands = vec![self.and()?];
+        loop {
+            let start = self.offset;
+            self.skip_blank();
+            if !self.eat("||") {
+                self.offset = start;
+                break;
+            }
+            self.skip_blank();
+            operands.push(self.and()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Logical::Or(operands),
+        })
+    }
+
+    fn and(&mut self) -> Result<Logical, PathError> {
+        let mut operands = vec![self.basic()?];
+        loop {
+            let start = self.offset;
+            self.skip_blank();
+            if !self.eat("&&") {
+                self.offset = start;
+                break;
+            }
+            self.skip_blank();
+            operands.push(self.basic()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Logical::And(operands),
+        })
+    }
+
+    fn basic(&mut self) -> Result<Logical, PathError> {
+        if self.eat("!") {
+            self.skip_blank();
+            let operand = if self.peek() == Some('(') {
+                self.parenthesized()?
+            } else {
+                let start = self.offset;
+                let primary = self.primary()?;
+                self.test(primary, start)?
+            };
+            return Ok(Logical::Not(Box::new(operand)));
+        }
+        if self.peek() == Some('(') {
+            return self.parenthesized();
+        }
+        let start = self.offset;
+        let left = self.primary()?;
+        let before_operator = self.offset;
+        self.skip_blank();
+        let Some(comparison) = self.comparison() else {
+            self.offset = before_operator;
+            return self.test(left, start);
+        };
+        let left = self.comparable(left, start)?;
+        self.skip_blank();
+        let start = self.offset;
+        let right = self.primary()?;
+        let right = self.comparable(right, start)?;
+        Ok(Logical::Compare(left, comparison, right))
+    }
+
+    fn parenthesized(&mut self) -> Result<Logical, PathError> {
+        self.expect("(")?;
+        self.skip_blank();
+        let logical = self.or()?;
+        self.skip_blank();
+        self.expect(")")?;
+        Ok(logical)
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        operators
+            .into_iter()
+            .find(|(operator, _)| self.eat(operator))
+            .map(|(_, comparison)| comparison)
+    }
+
+    /// A literal, query or function call.
+    fn primary(&mut self) -> Result<Primary, PathError> {
+        let start = self.offset;
+        match self.peek() {
+            Some(root @ ('$' | '@')) => {
+                self.offset += 1;
+                Ok(Primary::Query(Query {
+                    relative: root == '@',
+                    segments: self.segments()?,
+                }))
+            }
+            Some(quote @ ('\'' | '"')) => {
+                Ok(Primary::Literal(Literal::String(self.string(quote)?)))
+            }
+            Some('-' | '0'..='9') => self.number(),
+            Some('a'..='z') => {
+                let rest = self.rest();
+                let len = rest
+                    .find(|char: char| !matches!(char, 'a'..='z' | '0'..='9' | '_'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..len];
+                self.offset += len;
+                if self.peek() == Some('(') {
+                    return self.function(name, start);
+                }
+                match name {
+                    "true" => Ok(Primary::Literal(Literal::True)),
+                    "false" => Ok(Primary::Literal(Literal::False)),
+                    "null" => Ok(Primary::Literal(Literal::Null)),
+                    _ => Err(self.error_at(format!("unknown literal `{}`", name), start)),
+                }
+            }
+            _ => Err(self.error("expected a query, literal or function")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Primary, PathError> {
+        let start = self.offset;
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let digits = |from: usize| {
+            bytes[from..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+        let mut len = usize::from(bytes[0] == b'-');
+        let integer = digits(len);
+        if integer == 0 || (integer > 1 && bytes[len] == b'0') {
+            return Err(self.error("invalid number"));
+        }
+        len += integer;
+        if bytes.get(len) == Some(&b'.') {
+            let fraction = digits(len + 1);
+            if fraction == 0 {
+                return Err(self.error_at("expected digits after `.`", start + len + 1));
+            }
+            len += 1 + fraction;
+        }
+        if matches!(bytes.get(len), Some(b'e' | b'E')) {
+            len += 1;
+            if matches!(bytes.get(len), Some(b'+' | b'-')) {
+                len += 1;
+            }
+            let exponent = digits(len);
+            if exponent == 0 {
+                return Err(self.error_at("expected digits in the exponent", start + len));
+            }
+            len += exponent;
+        }
+        self.offset += len;
+        let number = rest[..len]
+            .parse()
+            .map_err(|_| self.error_at("invalid number", start))?;
+        Ok(Primary::Literal(Literal::Number(number)))
+    }
+
+    fn function(&mut self, name: &str, start: usize) -> Result<Primary, PathError> {
+        self.expect("(")?;
+        let mut arguments = vec![];
+        self.skip_blank();
+        if !self.eat(")") {
+            loop {
+                let offset = self.offset;
+                arguments.push((self.argument()?, offset));
+                self.skip_blank();
+                if !self.eat(",") {
+                    self.expect(")")?;
+                    break;
+                }
+                self.skip_blank();
+            }
+        }
+        let count = match name {
+            "length" | "count" | "value" => 1,
+            "match" | "search" => 2,
+            _ => return Err(self.error_at(format!("unknown function `{}`", name), start)),
+        };
+        if arguments.len() != count {
+            return Err(self.error_at(
+                format!(
+                    "`{}` takes {} argument{}",
+                    name,
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ),
+                start,
+            ));
+        }
+        let mut arguments = arguments.into_iter();
+        let mut next = || arguments.next().expect("the count was checked");
+        Ok(match name {
+            "length" => {
+                let (argument, offset) = next();
+                Primary::Value(Comparable::Length(Box::new(
+                    self.comparable(argument, offset)?,
+                )))
+            }
+            "count" => {
+                let (argument, offset) = next();
+                Primary::Value(Comparable::Count(self.nodes(argument, offset)?))
+            }
+            "value" => {
+                let (argument, offset) = next();
+                Primary::Value(Comparable::Value(self.nodes(argument, offset)?))
+            }
+            _ => {
+                if cfg!(not(feature = "regex")) {
+                    return Err(
+                        self.error_at(format!("`{}` needs the `regex` feature", name), start)
+                    );
+                }
+                let (text, offset) = next();
+                let text = self.comparable(text, offset)?;
+                let (pattern, offset) = next();
+                let pattern = self.comparable(pattern, offset)?;
+                Primary::Logical(Logical::Match {
+                    whole: name == "match",
+                    text,
+                    pattern,
+                })
+            }
+        })
+    }
+
+    /// A function argument: a literal, query or call on its own, or else a
+    /// whole logical expression.
+    fn argument(&mut self) -> Result<Primary, PathError> {
+        let start = self.offset;
+        if let Ok(primary) = self.primary() {
+            let end = self.offset;
+            self.skip_blank();
+            if matches!(self.peek(), Some(',' | ')')) {
+                return Ok(primary);
+            }
+            self.offset = end;
+        }
+        self.offset = start;
+        self.or().map(Primary::Logical)
+    }
+
+    /// Checks that `primary` has a value that can be compared.
+    fn comparable(&self, primary: Primary, offset: usize) -> Result<Comparable, PathError> {
+        match primary {
+            Primary::Literal(literal) => Ok(Comparable::Literal(literal)),
+            Primary::Query(query) if query.is_singular() => Ok(Comparable::Query(query)),
+            Primary::Query(_) => Err(self.error_at(
+                "only queries of single names and indices can be compared",
+                offset,
+            )),
+            Primary::Value(value) => Ok(value),
+            Primary::Logical(_) => {
+                Err(self.error_at("a logical expression has no value to compare", offset))
+            }
+        }
+    }
+
+    /// Checks that `primary` can stand alone as a filter.
+    fn test(&self, primary: Primary, offset: usize) -> Result<Logical, PathError> {
+        match primary {
+            Primary::Query(query) => Ok(Logical::Exists(query)),
+            Primary::Logical(logical) => Ok(logical),
+            Primary::Literal(_) | Primary::Value(_) => {
+                Err(self.error_at("a value must be compared to be used as a filter", offset))
+            }
+        }
+    }
+
+    /// Checks that `primary` is a query, for `count` and `value`.
+    fn nodes(&self, primary: Primary, offset: usize) -> Result<Query, PathError> {
+        match primary {
+            Primary::Query(query) => Ok(query),
+            _ => Err(self.error_at("expected a query", offset)),
+        }
+    }
+}
+
+/// What the query engine needs of a node: with paths at the top level, and
+/// without inside filters, where only the values matter.
+trait Located<'v, 'a>: Sized {
+    fn value(&self) -> &'v JsonValue<'a>;
+    /// The member with the raw key `key`.
+    fn member(&self, key: &str, value: &'v JsonValue<'a>) -> Self;
+    fn element(&self, index: usize, value: &'v JsonValue<'a>) -> Self;
+}
+
+impl<'v, 'a> Located<'v, 'a> for &'v JsonValue<'a> {
+    fn value(&self) -> &'v JsonValue<'a> {
+        self
+    }
+
+    fn member(&self, _: &str, value: &'v JsonValue<'a>) -> Self {
+        value
+    }
+
+    fn element(&self, _: usize, value: &'v JsonValue<'a>) -> Self {
+        value
+    }
+}
+
+impl<'v, 'a> Located<'v, 'a> for Node<'v, 'a> {
+    fn value(&self) -> &'v JsonValue<'a> {
+        self.value
+    }
+
+    fn member(&self, key: &str, value: &'v JsonValue<'a>) -> Self {
+        let mut path = self.path.clone();
+        path.segments
+            .push(PathSegment::Key(unescape(key).into_owned()));
+        Node { path, value }
+    }
+
+    fn element(&self, index: usize, value: &'v JsonValue<'a>) -> Self {
+        let mut path = self.path.clone();
+        path.segments.push(PathSegment::Index(index));
+        Node { path, value }
+    }
+}
+
+/// The indices a slice selects from an array of `len`, in order.
+fn slice(len: usize, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |index: i64| if index >= 0 { index } else { len + index };
+    let mut indices = vec![];
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut index = lower;
+        while index < upper {
+            indices.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut index = upper;
+        while lower < index {
+            indices.push(index as usize);
+            index += step;
+        }
+    }
+    indices
+}
+
+/// A value being compared: a node, a literal, a number computed by a
+/// function, or nothing at all.
+enum Value<'x> {
+    Nothing,
+    Node(&'x JsonValue<'x>),
+    Literal(&'x Literal),
+    Number(f64),
+}
+
+/// A value in the form comparisons work on.
+enum Atom<'x> {
+    Number(f64),
+    String(Cow<'x, str>),
+    Bool(bool),
+    Null,
+    /// An array or object.
+    Container(&'x JsonValue<'x>),
+}
+
+impl<'x> Value<'x> {
+    fn atom(&self) -> Option<Atom<'x>> {
+        Some(match *self {
+            Value::Nothing => return None,
+            Value::Number(number) => Atom::Number(number),
+            Value::Literal(literal) => match literal {
+                Literal::Number(number) => Atom::Number(*number),
+                Literal::String(string) => Atom::String(Cow::Borrowed(string)),
+                Literal::True => Atom::Bool(true),
+                Literal::False => Atom::Bool(false),
+                Literal::Null => Atom::Null,
+            },
+            Value::Node(value) => match value {
+                JsonValue::Number(number) => Atom::Number(**number),
+                JsonValue::String(raw) => Atom::String(unescape(raw)),
+                JsonValue::True => Atom::Bool(true),
+                JsonValue::False => Atom::Bool(false),
+                JsonValue::Null | JsonValue::Empty => Atom::Null,
+                container @ (JsonValue::Array(_) | JsonValue::Object(_)) => {
+                    Atom::Container(container)
+                }
+            },
+        })
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left.atom(), right.atom()) {
+        (None, None) => true,
+        (Some(left), Some(right)) => match (left, right) {
+            (Atom::Number(a), Atom::Number(b)) => a == b,
+            (Atom::String(a), Atom::String(b)) => a == b,
+            (Atom::Bool(a), Atom::Bool(b)) => a == b,
+            (Atom::Null, Atom::Null) => true,
+            (Atom::Container(a), Atom::Container(b)) => a.equivalent(b),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn less(left: &Value, right: &Value) -> bool {
+    match (left.atom(), right.atom()) {
+        (Some(Atom::Number(a)), Some(Atom::Number(b))) => a < b,
+        (Some(Atom::String(a)), Some(Atom::String(b))) => a < b,
+        _ => false,
+    }
+}
+
+struct Context<'v, 'a> {
+    root: &'v JsonValue<'a>,
+    /// Compiled patterns of `match` and `search`, by translated pattern.
+    #[cfg(feature = "regex")]
+    regexes: std::cell::RefCell<std::collections::HashMap<String, Option<regex::Regex>>>,
+}
+
+impl<'v, 'a> Context<'v, 'a> {
+    fn new(root: &'v JsonValue<'a>) -> Self {
+        Context {
+            root,
+            #[cfg(feature = "regex")]
+            regexes: Default::default(),
+        }
+    }
+
+    fn segments<N: Located<'v, 'a>>(&self, segments: &[Segment], mut nodes: Vec<N>) -> Vec<N> {
+        for segment in segments {
+            let mut selected = vec![];
+            for node in &nodes {
+                match segment {
+                    Segment::Child(selectors) => {
+                        for selector in selectors {
+                            self.select(selector, node, &mut selected);
+                        }
+                    }
+                    Segment::Descendant(selectors) => self.descend(selectors, node, &mut selected),
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+
+    /// Applies `selectors` to `node` and then to each of its descendants.
+    fn descend<N: Located<'v, 'a>>(&self, selectors: &[Selector], node: &N, selected: &mut Vec<N>) {
+        for selector in selectors {
+            self.select(selector, node, selected);
+        }
+        match node.value() {
+            JsonValue::Object(object) => {
+                for member in object.children.iter() {
+                    self.descend(
+                        selectors,
+                        &node.member(&member.key, &member.value),
+                        selected,
+                    );
+                }
+            }
+            JsonValue::Array(array) => {
+                for (index, element) in array.array.iter().enumerate() {
+                    self.descend(selectors, &node.element(index, element), selected);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn select<N: Located<'v, 'a>>(&self, selector: &Selector, node: &N, selected: &mut Vec<N>) {
+        match (selector, node.value()) {
+            (Selector::Name(name), JsonValue::Object(object)) => {
+                if let Some(index) = object.position(name) {
+                    let member = &object.children[index];
+                    selected.push(node.member(&member.key, &member.value));
+                }
+            }
+            (Selector::Wildcard, JsonValue::Object(object)) => {
+                for member in object.children.iter() {
+                    selected.push(node.member(&member.key, &member.value));
+                }
+            }
+            (Selector::Wildcard, JsonValue::Array(array)) => {
+                for (index, element) in array.array.iter().enumerate() {
+                    selected.push(node.element(index, element));
+                }
+            }
+            (Selector::Index(index), JsonValue::Array(array)) => {
+                let len = array.array.len() as i64;
+                let index = if *index < 0 { len + index } else { *index };
+                if (0..len).contains(&index) {
+                    let index = index as usize;
+                    selected.push(node.element(index, &array.array[index]));
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonValue::Array(array)) => {
+                for index in slice(array.array.len(), *start, *end, *step) {
+                    selected.push(node.element(index, &array.array[index]));
+                }
+            }
+            (Selector::Filter(filter), JsonValue::Object(object)) => {
+                for member in object.children.iter() {
+                    if self.test(filter, &member.value) {
+                        selected.push(node.member(&member.key, &member.value));
+                    }
+                }
+            }
+            (Selector::Filter(filter), JsonValue::Array(array)) => {
+                for (index, element) in array.array.iter().enumerate() {
+                    if self.test(filter, element) {
+                        selected.push(node.element(index, element));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn nodes(&self, query: &Query, current: &'v JsonValue<'a>) -> Vec<&'v JsonValue<'a>> {
+        let start = if query.relative { current } else { self.root };
+        self.segments(&query.segments, vec![start])
+    }
+
+    fn test(&self, filter: &Logical, current: &'v JsonValue<'a>) -> bool {
+        match filter {
+            Logical::Or(operands) => operands.iter().any(|operand| self.test(operand, current)),
+            Logical::And(operands) => operands.iter().all(|operand| self.test(operand, current)),
+            Logical::Not(operand) => !self.test(operand, current),
+            Logical::Exists(query) => !self.nodes(query, current).is_empty(),
+            Logical::Compare(left, comparison, right) => {
+                let left = self.value(left, current);
+                let right = self.value(right, current);
+                match comparison {
+                    Comparison::Equal => equal(&left, &right),
+                    Comparison::NotEqual => !equal(&left, &right),
+                    Comparison::Less => less(&left, &right),
+                    Comparison::LessOrEqual => less(&left, &right) || equal(&left, &right),
+                    Comparison::Greater => less(&right, &left),
+                    Comparison::GreaterOrEqual => less(&right, &left) || equal(&left, &right),
+                }
+            }
+            Logical::Match {
+                whole,
+                text,
+                pattern,
+            } => {
+                let text = self.value(text, current);
+                let pattern = self.value(pattern, current);
+                match (text.atom(), pattern.atom()) {
+                    (Some(Atom::String(text)), Some(Atom::String(pattern))) => {
+                        self.is_match(&text, &pattern, *whole)
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn value<'x>(&self, comparable: &'x Comparable, current: &'v JsonValue<'a>) -> Value<'x>
+    where
+        'v: 'x,
+    {
+        let single = |nodes: Vec<&'v JsonValue<'a>>| match nodes.as_slice() {
+            [node] => Value::Node(node),
+            _ => Value::Nothing,
+        };
+        match comparable {
+            Comparable::Literal(literal) => Value::Literal(literal),
+            Comparable::Query(query) | Comparable::Value(query) => {
+                single(self.nodes(query, current))
+            }
+            Comparable::Count(query) => Value::Number(self.nodes(query, current).len() as f64),
+            Comparable::Length(argument) => match self.value(argument, current).atom() {
+                Some(Atom::String(string)) => Value::Number(string.chars().count() as f64),
+                Some(Atom::Container(JsonValue::Array(array))) => {
+                    Value::Number(array.array.len() as f64)
+                }
+                Some(Atom::Container(JsonValue::Object(object))) => {
+                    Value::Number(object.len() as f64)
+                }
+                _ => Value::Nothing,
+            },
+        }
+    }
+
+    /// I-Regexp matching. Patterns that do not compile match nothing.
+    #[cfg(feature = "regex")]
+    fn is_match(&self, text: &str, pattern: &str, whole: bool) -> bool {
+        // I-Regexp's `.` excludes `\r` as well as `\n`.
+        let mut translated = String::new();
+        let mut in_class = false;
+        let mut chars = pattern.chars();
+        while let Some(char) = chars.next() {
+            match char {
+                '\\' => {
+                    translated.push(char);
+                    translated.extend(chars.next());
+                }
+                '[' => {
+                    in_class = true;
+                    translated.push(char);
+                }
+                ']' => {
+                    in_class = false;
+                    translated.push(char);
+                }
+                '.' if !in_class => translated.push_str("[^\\n\\r]"),
+                char => translated.push(char),
+            }
+        }
+        if whole {
+            translated = format!("\\A(?:{})\\z", translated);
+        }
+        self.regexes
+            .borrow_mut()
+            .entry(translated)
+            .or_insert_with_key(|pattern| regex::Regex::new(pattern).ok())
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(text))
+    }
+
+    /// Unreachable: without the `regex` feature such queries do not parse.
+    #[cfg(not(feature = "regex"))]
+    fn is_match(&self, _: &str, _: &str, _: bool) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests_jsonpath {
+    use super::*;
+    use crate::value::parse;
+
+    /// The selected values and their normalized paths, as text.
+    fn run(value: &JsonValue, query: &str) -> Vec<(String, String)> {
+        value
+            .query(query)
+            .unwrap_or_else(|e| panic!("{}: {}", query, e))
+            .into_iter()
+            .map(|node| (node.path.to_string(), node.value.to_string()))
+            .collect()
+    }
+
+    fn values(value: &JsonValue, query: &str) -> Vec<String> {
+        run(value, query)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    fn error(query: &str) -> PathError {
+        JsonPath::parse(query).unwrap_err()
+    }
+
+    /// The example document of RFC 9535 section 1.5.
+    const STORE: &str = r#"{ "store": {
+        "book": [
+          { "category": "reference", "author": "Nigel Rees",
+            "title": "Sayings of the Century", "price": 8.95 },
+          { "category": "fiction", "author": "Evelyn Waugh",
+            "title": "Sword of Honour", "price": 12.99 },
+          { "category": "fiction", "author": "Herman Melville",
+            "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+          { "category": "fiction", "author": "J. R. R. Tolkien",
+            "title": "The Lord of the Rings", "isbn": "0-395-19395-8",
+            "price": 22.99 }
+        ],
+        "bicycle": { "color": "red", "price": 399 }
+    } }"#;
+
+    #[test]
+    fn test_rfc_overview_examples() {
+        let store = parse(STORE);
+        let authors = [
+            r#""Nigel Rees""#,
+            r#""Evelyn Waugh""#,
+            r#""Herman Melville""#,
+            r#""J. R. R. Tolkien""#,
+        ];
+        assert_eq!(values(&store, "$.store.book[*].author"), authors);
+        assert_eq!(values(&store, "$..author"), authors);
+        assert_eq!(values(&store, "$.store.*").len(), 2);
+        assert_eq!(
+            values(&store, "$.store..price"),
+            ["8.95", "12.99", "8.99", "22.99", "399"]
+        );
+        assert_eq!(
+            run(&store, "$..book[2]"),
+            [(
+                "$['store']['book'][2]".to_string(),
+                store.pointer("/store/book/2").unwrap().to_string()
+            )]
+        );
+        assert_eq!(
+            values(&store, "$..book[2].author"),
+            [r#""Herman Melville""#]
+        );
+        assert_eq!(values(&store, "$..book[2].publisher"), Vec::<String>::new());
+        assert_eq!(
+            values(&store, "$..book[-1].title"),
+            [r#""The Lord of the Rings""#]
+        );
+        assert_eq!(values(&store, "$..book[0,1].price"), ["8.95", "12.99"]);
+        assert_eq!(values(&store, "$..book[:2].price"), ["8.95", "12.99"]);
+        assert_eq!(values(&store, "$..book[?@.isbn].price"), ["8.99", "22.99"]);
+        assert_eq!(
+            values(&store, "$..book[?@.price<10].price"),
+            ["8.95", "8.99"]
+        );
+        assert_eq!(values(&store, "$..*").len(), 27);
+    }
+
+    #[test]
+    fn test_selectors() {
+        let value = parse(
+            r#"{"o": {"j j": {"k.k": 3}}, "'": {"@": 2}, "a": ["a", "b", "c", "d", "e", "f", "g"]}"#,
+        );
+        assert_eq!(values(&value, "$.o['j j']"), [r#"{"k.k":3}"#]);
+        assert_eq!(values(&value, "$.o['j j']['k.k']"), ["3"]);
+        assert_eq!(values(&value, r#"$.o["j j"]["k.k"]"#), ["3"]);
+        assert_eq!(values(&value, r#"$["'"]["@"]"#), ["2"]);
+        assert_eq!(values(&value, r"$['\'']['@']"), ["2"]);
+        let letters = |query| values(&value, query).concat().replace('"', "");
+        assert_eq!(letters("$.a[1]"), "b");
+        assert_eq!(letters("$.a[-2]"), "f");
+        assert_eq!(letters("$.a[7]"), "");
+        assert_eq!(letters("$.a[1:3]"), "bc");
+        assert_eq!(letters("$.a[5:]"), "fg");
+        assert_eq!(letters("$.a[1:5:2]"), "bd");
+        assert_eq!(letters("$.a[5:1:-2]"), "fd");
+        assert_eq!(letters("$.a[::-1]"), "gfedcba");
+        assert_eq!(letters("$.a[::0]"), "");
+        assert_eq!(letters("$.a[-100:100]"), "abcdefg");
+        assert_eq!(letters("$.a[0, 0, 1:2]"), "aab");
+        assert_eq!(letters("$.a[ 1 : 3 ]"), "bc");
+        assert_eq!(values(&value, "$.o[0]"), Vec::<String>::new());
+        assert_eq!(values(&value, "$.a.b"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_descendants() {
+        let value = parse(r#"{"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]}"#);
+        assert_eq!(
+            run(&value, "$..j"),
+            [
+                ("$['o']['j']".to_string(), "1".to_string()),
+                ("$['a'][2][0]['j']".to_string(), "4".to_string()),
+            ]
+        );
+        assert_eq!(values(&value, "$..[0]"), ["5", r#"{"j":4}"#]);
+        assert_eq!(values(&value, "$.o..[*, *]"), ["1", "2", "1", "2"]);
+        assert_eq!(values(&value, "$..*").len(), 11);
+    }
+
+    /// The filter examples of RFC 9535 section 2.3.5.3.
+    #[test]
+    fn test_filters() {
+        let value = parse(
+            r#"{"a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}],
+                "o": {"p": 1, "q": 2, "r": 3, "s": 5, "t": {"u": 6}}, "e": "f"}"#,
+        );
+        assert_eq!(values(&value, "$.a[?@.b == 'kilo']"), [r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&value, "$.a[?(@.b == 'kilo')]"), [r#"{"b":"kilo"}"#]);
+        assert_eq!(values(&value, "$.a[?@>3.5]"), ["5", "4", "6"]);
+        assert_eq!(values(&value, "$.a[?@.b]").len(), 4);
+        assert_eq!(values(&value, "$[?@.*]").len(), 2);
+        assert_eq!(
+            values(&value, "$[?@[?@.b]]"),
+            [value.pointer("/a").unwrap().to_string()]
+        );
+        assert_eq!(values(&value, "$.o[?@<3, ?@<3]"), ["1", "2", "1", "2"]);
+        assert_eq!(
+            values(&value, r#"$.a[?@<2 || @.b == "k"]"#),
+            ["1", r#"{"b":"k"}"#]
+        );
+        assert_eq!(values(&value, "$.o[?@>1 && @<4]"), ["2", "3"]);
+        assert_eq!(values(&value, "$.o[?@.u || @.x]"), [r#"{"u":6}"#]);
+        assert_eq!(values(&value, "$.a[?@.b == $.x]").len(), 6);
+        assert_eq!(values(&value, "$.a[?@ == @]").len(), 10);
+        assert_eq!(values(&value, "$.a[?!@.b]").len(), 6);
+        assert_eq!(values(&value, "$.a[?!(@ < 3)]").len(), 8);
+        assert_eq!(values(&value, "$[?@ == 'f']"), [r#""f""#]);
+        assert_eq!(values(&value, "$.a[?@ <= 2 && @ >= 2]"), ["2"]);
+        assert_eq!(values(&value, "$.a[?@ != 3]").len(), 9);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let value =
+            parse(r#"[{"a": [1, {"b": "x"}]}, {"a": [1, {"b": "x\u0000"}]}, {"a": null}, {}]"#);
+        assert_eq!(values(&value, "$[?@.a == $[0].a]").len(), 1);
+        assert_eq!(values(&value, "$[?@.a == null]").len(), 1);
+        assert_eq!(values(&value, "$[?@.a != null]").len(), 3);
+        assert_eq!(values(&value, "$[?@.a < 'z']").len(), 0);
+        assert_eq!(values(&value, "$[?'a' < 'b']").len(), 4);
+        assert_eq!(
+            values(&value, "$[?1 == 1.0 && -0 == 0 && 1e2 == 100]").len(),
+            4
+        );
+        assert_eq!(values(&value, "$[?true != false && null == null]").len(), 4);
+    }
+
+    #[test]
+    fn test_functions() {
+        let value = parse(
+            r#"[{"s": "héllo", "a": [1, 2, 3], "o": {"x": 1}}, {"s": "hi", "a": [], "o": {}}, {"s": 1}]"#,
+        );
+        assert_eq!(values(&value, "$[?length(@.s) == 5].s"), [r#""héllo""#]);
+        assert_eq!(values(&value, "$[?length(@.a) >= 3].a"), ["[1,2,3]"]);
+        assert_eq!(values(&value, "$[?length(@.o) == 0].o"), ["{}"]);
+        assert_eq!(
+            values(&value, "$[?length(@.s) == length('hi')].s"),
+            [r#""hi""#]
+        );
+        assert_eq!(values(&value, "$[?count(@.*) == 1]").len(), 1);
+        assert_eq!(values(&value, "$[?count(@..*) > 5]").len(), 1);
+        assert_eq!(values(&value, "$[?value(@..x) == 1]").len(), 1);
+        assert_eq!(values(&value, "$[?length(@.missing) == 0]").len(), 0);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_functions() {
+        let value = parse(r#"["2024-01-02", "x2024-01-02", "ab\r", "ab", "bé"]"#);
+        assert_eq!(
+            values(&value, r#"$[?match(@, '\\d{4}-\\d{2}-\\d{2}')]"#),
+            [r#""2024-01-02""#]
+        );
+        assert_eq!(values(&value, r#"$[?search(@, '\\d{4}-\\d{2}')]"#).len(), 2);
+        assert_eq!(values(&value, "$[?match(@, 'ab.')]").len(), 0);
+        assert_eq!(
+            values(&value, "$[?match(@, 'a.|b.')]"),
+            [r#""ab""#, r#""bé""#]
+        );
+        assert_eq!(values(&value, r"$[?match(@, 'b\\p{Ll}')]").len(), 1);
+        assert_eq!(values(&value, "$[?!search(@, '[')]").len(), 5);
+        assert_eq!(
+            values(&value, "$[?search(@, 'a') && !match(@, 'a.')]"),
+            [r#""ab\r""#]
+        );
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn test_regex_functions_need_the_feature() {
+        assert_eq!(
+            error("$[?match(@, 'a')]").to_string(),
+            "`match` needs the `regex` feature at offset 3"
+        );
+    }
+
+    #[test]
+    fn test_normalized_paths() {
+        let value = parse(r#"{"a'\\\b\u0001\t": [0, {"/~": 1}]}"#);
+        let nodes = value.query("$..*").unwrap();
+        let paths: Vec<_> = nodes.iter().map(|node| node.path.to_string()).collect();
+        assert_eq!(
+            paths,
+            [
+                r"$['a\'\\\b\u0001\t']",
+                r"$['a\'\\\b\u0001\t'][0]",
+                r"$['a\'\\\b\u0001\t'][1]",
+                r"$['a\'\\\b\u0001\t'][1]['/~']",
+            ]
+        );
+        assert_eq!(nodes[3].path.to_pointer(), "/a'\\\u{8}\u{1}\t/1/~1~0");
+        assert_eq!(
+            value.pointer(&nodes[3].path.to_pointer()),
+            Some(nodes[3].value)
+        );
+        assert_eq!(
+            nodes[3].path.segments(),
+            [
+                PathSegment::Key("a'\\\u{8}\u{1}\t".to_string()),
+                PathSegment::Index(1),
+                PathSegment::Key("/~".to_string()),
+            ]
+        );
+        assert_eq!(run(&value, "$"), [("$".to_string(), value.to_string())]);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let message = |query| error(query).message;
+        assert_eq!(error("a").to_string(), "expected `$` at offset 0");
+        assert_eq!(error("$.a ").offset, 3);
+        assert_eq!(error(" $").offset, 0);
+        assert_eq!(message("$.1"), "expected a member name or `*`");
+        assert_eq!(
+            message("$[01]"),
+            "integers must not have leading zeros or be `-0`"
+        );
+        assert_eq!(
+            message("$[-0]"),
+            "integers must not have leading zeros or be `-0`"
+        );
+        assert_eq!(message("$[9007199254740992]"), "integer out of range");
+        assert_eq!(message("$[]"), "expected a selector");
+        assert_eq!(message("$['a]"), "unterminated string");
+        assert_eq!(message(r"$['\a']"), "invalid escape");
+        assert_eq!(message(r#"$['\"']"#), "invalid escape");
+        assert_eq!(message(r"$['\uD800']"), "invalid unicode escape");
+        assert_eq!(message("$['\u{1}']"), "control characters must be escaped");
+        assert_eq!(message("$[?@.a == 'x' == 1]"), "expected `]`");
+        assert_eq!(
+            message("$[?1]"),
+            "a value must be compared to be used as a filter"
+        );
+        assert_eq!(
+            message("$[?length(@.a)]"),
+            "a value must be compared to be used as a filter"
+        );
+        assert_eq!(
+            message("$[?@.* == 1]"),
+            "only queries of single names and indices can be compared"
+        );
+        assert_eq!(
+            message("$[?@..a == 1]"),
+            "only queries of single names and indices can be compared"
+        );
+        assert_eq!(
+            message("$[?length(@.*) == 1]"),
+            "only queries of single names and indices can be compared"
+        );
+        assert_eq!(message("$[?count(1) == 1]"), "expected a query");
+        assert_eq!(
+            message("$[?length(@.a == 1) == 1]"),
+            "a logical expression has no value to compare"
+        );
+        assert_eq!(message("$[?foo(@) == 1]"), "unknown function `foo`");
+        assert_eq!(
+            message("$[?length(@, @) == 1]"),
+            "`length` takes 1 argument"
+        );
+        assert_eq!(message("$[?@.a == tru]"), "unknown literal `tru`");
+        assert_eq!(
+            message("$[?@.a == {}]"),
+            "expected a query, literal or function"
+        );
+        assert_eq!(
+            message("$[?@.a == [1]]"),
+            "expected a query, literal or function"
+        );
+        assert_eq!(message("$[?@.a == 1.]"), "expected digits after `.`");
+        assert_eq!(message("$[?@.a == 01]"), "invalid number");
+        assert_eq!(message("$[?length (@.a) == 1]"), "unknown literal `length`");
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let value = parse(r#"{"a": [1, 2, {"b": 3}]}"#);
+        assert_eq!(values(&value, "$ .a [ 0 , 1 ]"), ["1", "2"]);
+        assert_eq!(values(&value, "$.a[? @.b  ==  3 ].b"), ["3"]);
+        assert_eq!(values(&value, "$.a[?( @ == 1 || @ == 2 )]"), ["1", "2"]);
+        assert_eq!(values(&value, "$.a[?! @.b]"), ["1", "2"]);
+        assert_eq!(values(&value, "$.a[?count( @.* ) == 1]"), [r#"{"b":3}"#]);
+    }
+}
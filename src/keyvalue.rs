@@ -1,12 +1,22 @@
 use crate::value::JsonValue;
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct KeyValue<'a> {
-    pub key: &'a str,
+    pub key: Cow<'a, str>,
     pub value: JsonValue<'a>,
 }
 
+impl<'a> KeyValue<'a> {
+    pub fn into_owned(self) -> KeyValue<'static> {
+        KeyValue {
+            key: Cow::Owned(self.key.into_owned()),
+            value: self.value.into_owned(),
+        }
+    }
+}
+
 impl<'a> Display for KeyValue<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "\"{}\"", self.key)?;
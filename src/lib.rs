@@ -1,7 +1,30 @@
 pub mod array;
-mod error;
+pub mod compare;
+pub mod convert;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod error;
+pub mod escape;
+pub mod event;
+mod json5;
+pub mod jsonpath;
 pub mod keyvalue;
+pub mod lines;
+pub mod merge;
 pub mod object;
 pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod push;
+pub mod read;
+pub mod recover;
+pub mod report;
+pub mod scan;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod span;
+pub mod stream;
 pub mod token;
 pub mod value;
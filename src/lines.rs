@@ -0,0 +1,304 @@
+//! JSON Lines (NDJSON): one document, of any kind of value, per line.
+//!
+//! [`JsonLines`] reads from a `&str` and borrows from it, [`JsonLinesReader`]
+//! reads from any [`Read`] and yields owned values. Blank lines are skipped,
+//! and every error carries the 1-based line it was found on; byte offsets in
+//! errors count from the start of that line. A byte order mark is only
+//! accepted in front of the first line. [`JsonLinesWriter`]
+//! writes values back out compactly, one per line.
+//!
+//! ```
+//! use rust_json::lines::{JsonLines, JsonLinesWriter};
+//!
+//! let input = "{\"id\": 1}\n\n{\"id\": 2}\n";
+//! let mut writer = JsonLinesWriter::new(vec![]);
+//! for value in JsonLines::new(input) {
+//!     writer.write(&value.unwrap()).unwrap();
+//! }
+//! assert_eq!(writer.into_inner(), b"{\"id\":1}\n{\"id\":2}\n");
+//! ```
+
+use crate::error::JsonError;
+use crate::event::EventReader;
+use crate::token::TokenParseError::UnexpectedCharacter;
+use crate::token::{Tokenizer, BOM};
+use crate::value::JsonValue;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::str::Lines;
+
+/// A document that failed to parse, and the line it was on. Offsets in
+/// `error` are relative to the start of the line.
+#[derive(Debug, PartialEq)]
+pub struct LineError<'a> {
+    pub line: usize,
+    pub error: JsonError<'a>,
+}
+
+/// Why [`JsonLinesReader`] failed: the reader itself, or one of the lines.
+#[derive(Debug)]
+pub enum LineReadError {
+    Io(io::Error),
+    Syntax(LineError<'static>),
+}
+
+impl<'a> Display for LineError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl<'a> std::error::Error for LineError<'a> {}
+
+impl Display for LineReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineReadError::Io(e) => write!(f, "read failed: {}", e),
+            LineReadError::Syntax(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LineReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LineReadError::Io(e) => Some(e),
+            LineReadError::Syntax(e) => Some(e),
+        }
+    }
+}
+
+/// Parses one line, which must hold exactly one value. `text` is the line
+/// `tokens` reads.
+fn parse_line<'a>(
+    tokens: Tokenizer<'a>,
+    text: &[u8],
+    line: usize,
+) -> Result<JsonValue<'a>, LineError<'a>> {
+    // The tokenizer allows a byte order mark at its start, which is only the
+    // start of the input on the first line.
+    if line > 1 && text.starts_with(BOM) {
+        let error = JsonError::Token(UnexpectedCharacter { offset: 0 });
+        return Err(LineError { line, error });
+    }
+    let mut reader = EventReader::from_tokenizer(tokens);
+    reader
+        .read_value()
+        .and_then(|value| reader.finish().map(|_| value))
+        .map_err(|error| LineError { line, error })
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Iterates over the documents in a newline-delimited `&str`.
+#[derive(Debug, Clone)]
+pub struct JsonLines<'a> {
+    lines: Lines<'a>,
+    line: usize,
+}
+
+impl<'a> JsonLines<'a> {
+    pub fn new(origin: &'a str) -> Self {
+        Self {
+            lines: origin.lines(),
+            line: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for JsonLines<'a> {
+    type Item = Result<JsonValue<'a>, LineError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for text in self.lines.by_ref() {
+            self.line += 1;
+            if !is_blank(text.as_bytes()) {
+                return Some(parse_line(Tokenizer::new(text), text.as_bytes(), self.line));
+            }
+        }
+        None
+    }
+}
+
+/// Iterates over the documents read line by line from a [`Read`].
+#[derive(Debug)]
+pub struct JsonLinesReader<R> {
+    reader: BufReader<R>,
+    buffer: Vec<u8>,
+    line: usize,
+}
+
+impl<R: Read> JsonLinesReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buffer: vec![],
+            line: 0,
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<usize> {
+        self.buffer.clear();
+        loop {
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonLinesReader<R> {
+    type Item = Result<JsonValue<'static>, LineReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.read_line() {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(LineReadError::Io(e))),
+            }
+            if is_blank(&self.buffer) {
+                continue;
+            }
+            let value = parse_line(Tokenizer::from_bytes(&self.buffer), &self.buffer, self.line);
+            return Some(
+                value
+                    .map(JsonValue::into_owned)
+                    .map_err(|LineError { line, error }| {
+                        LineReadError::Syntax(LineError {
+                            line,
+                            error: error.into_owned(),
+                        })
+                    }),
+            );
+        }
+    }
+}
+
+/// Writes values compactly, each followed by `\n`.
+#[derive(Debug)]
+pub struct JsonLinesWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        writeln!(self.writer, "{}", value)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests_json_lines {
+    use super::*;
+    use crate::error::expect_end_of_input;
+    use crate::error::JsonParserError::UnexpectedEndOfTokens;
+    use crate::token::JsonToken::LeftBrace;
+    use crate::token::TokenParseError::InvalidUtf8;
+
+    #[test]
+    fn test_lines_and_errors() {
+        let input = "{\"a\": 1}\r\n  \n[1, 2]\n{\"b\": \n{} {}\n";
+        let results: Vec<_> = JsonLines::new(input).collect();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().to_string(), r#"{"a":1}"#);
+        assert_eq!(results[1].as_ref().unwrap().to_string(), "[1,2]");
+        assert_eq!(
+            results[2],
+            Err(LineError {
+                line: 4,
+                error: JsonError::Parse(UnexpectedEndOfTokens),
+            })
+        );
+        assert_eq!(
+            results[3],
+            Err(LineError {
+                line: 5,
+                error: JsonError::Parse(expect_end_of_input(&LeftBrace)),
+            })
+        );
+    }
+
+    #[test]
+    fn test_scalar_lines() {
+        let input = "1\n\"two\"\ntrue\nnull\n-0.5\n";
+        let values: Vec<_> = JsonLines::new(input)
+            .map(|value| value.unwrap().to_string())
+            .collect();
+        assert_eq!(values, vec!["1", r#""two""#, "true", "null", "-0.5"]);
+
+        let mut reader = JsonLinesReader::new(b"3\n\"x\" 4\n".as_slice());
+        assert_eq!(reader.next().unwrap().unwrap().to_string(), "3");
+        assert!(matches!(
+            reader.next().unwrap(),
+            Err(LineReadError::Syntax(LineError { line: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_reader_round_trips_through_writer() {
+        let input = b"\n{\"name\": \"Jack\"}\n[true, null]".as_slice();
+        let mut writer = JsonLinesWriter::new(vec![]);
+        for value in JsonLinesReader::new(input) {
+            writer.write(&value.unwrap()).unwrap();
+        }
+        let output = writer.into_inner();
+        assert_eq!(output, b"{\"name\":\"Jack\"}\n[true,null]\n");
+
+        let mut reader = JsonLinesReader::new(b"[]\n[\"\xFF\"]\n".as_slice());
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next().unwrap() {
+            Err(LineReadError::Syntax(LineError { line, error })) => {
+                assert_eq!(line, 2);
+                assert_eq!(error, JsonError::Token(InvalidUtf8 { offset: 2 }));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_byte_order_mark_only_on_the_first_line() {
+        let input = "\u{feff}[1]\n\u{feff}[2]\n";
+        let results: Vec<_> = JsonLines::new(input).collect();
+        assert_eq!(results[0].as_ref().unwrap().to_string(), "[1]");
+        assert_eq!(
+            results[1],
+            Err(LineError {
+                line: 2,
+                error: JsonError::Token(UnexpectedCharacter { offset: 0 }),
+            })
+        );
+
+        let mut reader = JsonLinesReader::new(input.as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next().unwrap(),
+            Err(LineReadError::Syntax(LineError { line: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_errors_display_and_have_a_source() {
+        use std::error::Error;
+
+        let error = JsonLinesReader::new(b"1\n{\n".as_slice())
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(error.to_string(), "line 2: unexpected end of input");
+        assert_eq!(error.source().unwrap().to_string(), error.to_string());
+    }
+}
@@ -0,0 +1,318 @@
+//! `rust_json`: validate, pretty-print or minify JSON from the command line.
+//!
+//! The exit status makes it usable as a pre-commit hook: 0 when every input
+//! is fine, 1 when one is invalid (or, with `--check`, not formatted), and 2
+//! when the command line is wrong or a file cannot be read.
+
+use rust_json::parser::Parser;
+use rust_json::recover::Recovered;
+use rust_json::span::{SpannedKind, SpannedValue};
+use rust_json::token::{parse_to_tokens, Tokenizer};
+use rust_json::value::JsonValue;
+use std::fs;
+use std::hint::black_box;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+usage: rust_json <command> [options] [files...]
+       rust_json bench <count> [files...]
+
+commands:
+  validate      check that every input is valid JSON
+  fmt           pretty-print every input
+  minify        print every input on a single line
+  bench         time tokenize, parse and serialize <count> times each and
+                report throughput; defaults to tests/json1.json..json3.json
+
+options:
+  --check       print nothing; fail if an input is not formatted already
+  -w, --write   rewrite files in place instead of printing them
+  --indent <n>  spaces per level for fmt (default 2)
+  -h, --help    show this message
+
+With no files, or with `-`, standard input is read.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Validate,
+    Fmt,
+    Minify,
+    Bench { count: u32 },
+}
+
+/// What `bench` measures when it is not given any files.
+const FIXTURES: [&str; 3] = ["tests/json1.json", "tests/json2.json", "tests/json3.json"];
+
+#[derive(Debug)]
+struct Options {
+    command: Command,
+    check: bool,
+    write: bool,
+    indent: usize,
+    files: Vec<String>,
+}
+
+/// How an input turned out; the worst one decides the exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Ok = 0,
+    Invalid = 1,
+    Failed = 2,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let parse_count = |count: Option<String>| {
+        count
+            .and_then(|count| count.parse().ok())
+            .ok_or("bench needs an iteration count")
+    };
+    let command = match args.next().as_deref() {
+        Some("validate") => Command::Validate,
+        Some("fmt") => Command::Fmt,
+        Some("minify") => Command::Minify,
+        Some("bench") => Command::Bench {
+            count: parse_count(args.next())?,
+        },
+        // `tester.py` passes just the count.
+        Some(count) if count.parse::<u32>().is_ok() => Command::Bench {
+            count: parse_count(Some(count.to_string()))?,
+        },
+        Some(other) => return Err(format!("unknown command `{}`", other)),
+        None => return Err("missing command".to_string()),
+    };
+    let mut options = Options {
+        command,
+        check: false,
+        write: false,
+        indent: 2,
+        files: vec![],
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "-w" | "--write" => options.write = true,
+            "--indent" => {
+                options.indent = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or("--indent needs a number")?
+            }
+            "-" => options.files.push(arg),
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ => options.files.push(arg),
+        }
+    }
+    if options.check && options.write {
+        return Err("--check and --write cannot be used together".to_string());
+    }
+    if options.files.is_empty() {
+        match options.command {
+            Command::Bench { .. } => options.files = FIXTURES.map(String::from).to_vec(),
+            _ => options.files.push("-".to_string()),
+        }
+    }
+    Ok(options)
+}
+
+fn read_input(file: &str) -> io::Result<Vec<u8>> {
+    if file == "-" {
+        let mut bytes = vec![];
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        fs::read(file)
+    }
+}
+
+fn process(options: &Options, file: &str) -> Status {
+    let name = if file == "-" { "<stdin>" } else { file };
+    let bytes = match read_input(file) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("error: {}: {}", name, e);
+            return Status::Failed;
+        }
+    };
+
+    let recovered = Recovered::from_tokenizer(Tokenizer::from_bytes(&bytes));
+    // Valid input is valid UTF-8, so nothing is replaced and offsets hold.
+    let source = String::from_utf8_lossy(&bytes);
+    let value = match recovered.value {
+        Some(value) if recovered.diagnostics.is_empty() => value,
+        _ => {
+            for diagnostic in &recovered.diagnostics {
+                eprint!("{}", diagnostic.report(&source).path(name));
+            }
+            return Status::Invalid;
+        }
+    };
+
+    let output = match options.command {
+        Command::Validate | Command::Bench { .. } => return Status::Ok,
+        Command::Fmt => {
+            let mut out = String::new();
+            write_value(
+                &mut out,
+                &value,
+                &source,
+                Some(&" ".repeat(options.indent)),
+                0,
+            );
+            out + "\n"
+        }
+        Command::Minify => {
+            let mut out = String::new();
+            write_value(&mut out, &value, &source, None, 0);
+            out + "\n"
+        }
+    };
+    let result = if options.check {
+        if output.as_bytes() == bytes {
+            return Status::Ok;
+        }
+        eprintln!("{}: not formatted", name);
+        return Status::Invalid;
+    } else if options.write && file != "-" {
+        if output.as_bytes() == bytes {
+            return Status::Ok;
+        }
+        fs::write(file, output)
+    } else {
+        io::stdout().lock().write_all(output.as_bytes())
+    };
+    match result {
+        Ok(()) => Status::Ok,
+        Err(e) => {
+            eprintln!("error: {}: {}", name, e);
+            Status::Failed
+        }
+    }
+}
+
+/// Writes `value` like `JsonValue::to_string`, or `to_string_pretty` when
+/// given an `indent`, except that numbers are copied from `source` as they
+/// were written: going through `f64` would turn `9007199254740993` into
+/// `9007199254740992` and `1.50` into `1.5`.
+fn write_value(
+    out: &mut String,
+    value: &SpannedValue,
+    source: &str,
+    indent: Option<&str>,
+    depth: usize,
+) {
+    let newline = |out: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            out.push('\n');
+            (0..depth).for_each(|_| out.push_str(indent));
+        }
+    };
+    match &value.kind {
+        SpannedKind::Scalar(JsonValue::Number(_)) => out.push_str(&source[value.span.range()]),
+        SpannedKind::Scalar(scalar) => out.push_str(&scalar.to_string()),
+        SpannedKind::Object(members) if !members.is_empty() => {
+            out.push('{');
+            for (index, member) in members.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                newline(out, depth + 1);
+                out.push('"');
+                out.push_str(&member.key);
+                out.push_str(if indent.is_some() { "\": " } else { "\":" });
+                write_value(out, &member.value, source, indent, depth + 1);
+            }
+            newline(out, depth);
+            out.push('}');
+        }
+        SpannedKind::Array(elements) if !elements.is_empty() => {
+            out.push('[');
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                newline(out, depth + 1);
+                write_value(out, element, source, indent, depth + 1);
+            }
+            newline(out, depth);
+            out.push(']');
+        }
+        SpannedKind::Object(_) => out.push_str("{}"),
+        SpannedKind::Array(_) => out.push_str("[]"),
+    }
+}
+
+/// Average time of one call to `f` over `count` calls.
+fn time<T>(count: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..count {
+        black_box(f());
+    }
+    start.elapsed() / count
+}
+
+fn bench(count: u32, file: &str) -> Status {
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("error: {}: {}", file, e);
+            return Status::Failed;
+        }
+    };
+    let tokens = match parse_to_tokens(&text) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            eprintln!("error: {}: {}", file, e);
+            return Status::Invalid;
+        }
+    };
+    let value = match Parser::new(&tokens).parse() {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("error: {}: {}", file, e);
+            return Status::Invalid;
+        }
+    };
+
+    let count = count.max(1);
+    let tokenize = time(count, || parse_to_tokens(black_box(&text)));
+    let parse = time(count, || Parser::new(black_box(&tokens)).parse().is_ok());
+    let serialize = time(count, || black_box(&value).to_string());
+
+    println!("{} ({} bytes, {} iterations)", file, text.len(), count);
+    for (name, elapsed) in [
+        ("tokenize", tokenize),
+        ("parse", parse),
+        ("serialize", serialize),
+    ] {
+        let throughput = text.len() as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+        println!("  {:<10} {:>12?}  {:>8.1} MiB/s", name, elapsed, throughput);
+    }
+    Status::Ok
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(Status::Failed as u8);
+        }
+    };
+    let status = options
+        .files
+        .iter()
+        .map(|file| match options.command {
+            Command::Bench { count } => bench(count, file),
+            _ => process(&options, file),
+        })
+        .max()
+        .unwrap_or(Status::Ok);
+    ExitCode::from(status as u8)
+}
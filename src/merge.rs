@@ -0,0 +1,220 @@
+//! RFC 7386 JSON Merge Patch: a partial document whose objects merge into
+//! the target recursively, where `null` deletes a member and anything else
+//! replaces what was there.
+//!
+//! ```
+//! use rust_json::merge::{create_merge_patch, merge_patch};
+//! use rust_json::parser::Parser;
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let tokens = parse_to_tokens(r#"{"title": "Hello", "author": {"name": "A", "email": "a@b"}}"#).unwrap();
+//! let original = Parser::new(&tokens).parse().unwrap();
+//! let tokens = parse_to_tokens(r#"{"title": "Goodbye", "author": {"email": null}}"#).unwrap();
+//! let patch = Parser::new(&tokens).parse().unwrap();
+//!
+//! let mut updated = original.clone();
+//! merge_patch(&mut updated, &patch);
+//! assert_eq!(updated.to_string(), r#"{"title":"Goodbye","author":{"name":"A"}}"#);
+//! assert_eq!(create_merge_patch(&original, &updated).to_string(), patch.to_string());
+//! ```
+
+use crate::escape::unescape;
+use crate::keyvalue::KeyValue;
+use crate::value::JsonValue;
+use std::collections::HashMap;
+use std::iter;
+
+fn empty_object<'a>() -> JsonValue<'a> {
+    JsonValue::Object(Box::new(iter::empty().collect()))
+}
+
+/// Applies `patch` to `target`. An object patch turns a target that is not
+/// an object into an empty one first, then removes the members it sets to
+/// `null` and merges the others; any other patch replaces the target.
+pub fn merge_patch<'a>(target: &mut JsonValue<'a>, patch: &JsonValue<'a>) {
+    let JsonValue::Object(members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !matches!(target, JsonValue::Object(_)) {
+        *target = empty_object();
+    }
+    let JsonValue::Object(object) = target else {
+        unreachable!("the target was just made an object")
+    };
+    // Keys are owned since the object changes under them; removals are
+    // applied at the end so that positions stay valid.
+    let mut positions: HashMap<String, usize> = object
+        .positions()
+        .into_iter()
+        .map(|(key, index)| (key.into_owned(), index))
+        .collect();
+    let mut removed = vec![false; object.len()];
+    for KeyValue { key, value } in members.children.iter() {
+        let text = unescape(key);
+        match (positions.get(&*text), value) {
+            (Some(&index), JsonValue::Null) => {
+                removed[index] = true;
+                positions.remove(&*text);
+            }
+            (None, JsonValue::Null) => {}
+            (Some(&index), value) => merge_patch(&mut object.children[index].value, value),
+            (None, value) => {
+                // Merged into nothing, so that nulls inside are dropped.
+                let mut merged = JsonValue::Null;
+                merge_patch(&mut merged, value);
+                positions.insert(text.into_owned(), object.children.len());
+                removed.push(false);
+                object.children.push(KeyValue {
+                    key: key.clone(),
+                    value: merged,
+                });
+            }
+        }
+    }
+    let mut removed = removed.into_iter();
+    object.children.retain(|_| !removed.next().unwrap_or(false));
+}
+
+/// The merge patch turning `original` into `updated`: a member per changed
+/// key when both are objects, so `{}` when nothing changed, and `updated`
+/// itself otherwise.
+///
+/// Merge patches cannot set a member to `null`, so `null` members of objects
+/// in `updated` that `original` lacks or has with another value are lost.
+pub fn create_merge_patch<'a>(original: &JsonValue, updated: &JsonValue<'a>) -> JsonValue<'a> {
+    match (original, updated) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            let (old_positions, new_positions) = (old.positions(), new.positions());
+            let mut members = vec![];
+            for member in old.iter() {
+                if !new_positions.contains_key(&unescape(&member.key)) {
+                    members.push(KeyValue {
+                        key: member.key.clone().into_owned().into(),
+                        value: JsonValue::Null,
+                    });
+                }
+            }
+            for member in new.children.iter() {
+                let value = match old_positions.get(&unescape(&member.key)) {
+                    Some(&index) if old.children[index].value.equivalent(&member.value) => continue,
+                    Some(&index) => create_merge_patch(&old.children[index].value, &member.value),
+                    None => member.value.clone(),
+                };
+                members.push(KeyValue {
+                    key: member.key.clone(),
+                    value,
+                });
+            }
+            JsonValue::Object(Box::new(members.into_iter().collect()))
+        }
+        _ => updated.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests_merge {
+    use super::*;
+    use crate::value::parse;
+
+    #[test]
+    fn test_rfc_examples() {
+        // RFC 7386 appendix A.
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, "{}"),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            ("[1,2]", r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            ("{}", r#"{"a":{"bb":{"ccc":null}}}"#, r#"{"a":{"bb":{}}}"#),
+        ];
+        for (target, patch, expected) in cases {
+            let mut value = parse(target);
+            merge_patch(&mut value, &parse(patch));
+            assert_eq!(
+                value.to_string(),
+                expected,
+                "{} merged with {}",
+                target,
+                patch
+            );
+        }
+    }
+
+    #[test]
+    fn test_escaped_keys() {
+        let mut value = parse(r#"{"ab": 1, "c": 2}"#);
+        merge_patch(&mut value, &parse(r#"{"ab": null, "c\n": 3}"#));
+        assert_eq!(value.to_string(), r#"{"c":2,"c\n":3}"#);
+    }
+
+    #[test]
+    fn test_create_merge_patch() {
+        let cases = [
+            (r#"{"a": 1}"#, r#"{"a": 1}"#, "{}"),
+            ("[1]", "[1]", "[1]"),
+            ("[1]", "[2]", "[2]"),
+            (
+                r#"{"a": 1, "b": 2}"#,
+                r#"{"b": 2, "c": 3}"#,
+                r#"{"a":null,"c":3}"#,
+            ),
+            (
+                r#"{"a": {"b": 1, "c": [1]}}"#,
+                r#"{"a": {"b": 1, "c": [2]}}"#,
+                r#"{"a":{"c":[2]}}"#,
+            ),
+            (r#"{"a": {"b": 1}}"#, r#"{"a": 1}"#, r#"{"a":1}"#),
+            ("1", r#"{"a": 1}"#, r#"{"a":1}"#),
+        ];
+        for (original, updated, expected) in cases {
+            let (original, updated) = (parse(original), parse(updated));
+            let patch = create_merge_patch(&original, &updated);
+            assert_eq!(patch.to_string(), expected);
+            let mut value = original.clone();
+            merge_patch(&mut value, &patch);
+            assert!(value.equivalent(&updated), "{} gave {}", patch, value);
+        }
+    }
+
+    #[test]
+    fn test_large_objects() {
+        let members = |value: f64| {
+            let object = (0..20_000)
+                .map(|i| KeyValue {
+                    key: format!("k{}", i).into(),
+                    value: JsonValue::Number(Box::new(if i == 7 { value } else { 0.0 })),
+                })
+                .collect();
+            JsonValue::Object(Box::new(object))
+        };
+        let (original, updated) = (members(0.0), members(1.0));
+        let patch = create_merge_patch(&original, &updated);
+        assert_eq!(patch.to_string(), r#"{"k7":1}"#);
+        let mut value = original.clone();
+        merge_patch(
+            &mut value,
+            &parse(r#"{"k1": null, "k7": 1, "k3": null, "new": 2}"#),
+        );
+        let JsonValue::Object(object) = &value else {
+            panic!("{}", value)
+        };
+        assert_eq!(object.len(), 19_999);
+        assert_eq!(object.get("k7"), Some(&JsonValue::Number(Box::new(1.0))));
+        assert_eq!(object.position("new"), Some(19_998));
+        assert_eq!(object.position("k2"), Some(1));
+    }
+}
@@ -1,29 +1,93 @@
+use crate::escape::unescape;
 use crate::keyvalue::KeyValue;
 use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::slice::Iter;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct JsonObject<'a> {
     pub(crate) children: Vec<KeyValue<'a>>,
 }
 
 impl<'a> JsonObject<'a> {
-    pub fn iter(&self) -> JsonObjectIter {
+    pub fn iter(&self) -> JsonObjectIter<'_> {
         JsonObjectIter {
             iter: self.children.iter(),
         }
     }
 
-    pub fn get_by_key(&self, str: &str) -> Option<&'a JsonValue> {
+    pub fn get_by_key(&self, str: &str) -> Option<&JsonValue<'a>> {
         let candidate: Vec<&KeyValue> = self
             .children
             .iter()
-            .filter(|KeyValue { key, .. }| str == *key)
+            .filter(|KeyValue { key, .. }| key == str)
             .collect();
-        let value = candidate.get(0);
+        let value = candidate.first();
         value.map(|KeyValue { key: _, value }| value)
     }
+
+    /// The member named `key`, compared as unescaped text so that
+    /// `"\u0070ort"` is found as `port`. The first one wins if the key
+    /// repeats.
+    pub fn get(&self, key: &str) -> Option<&JsonValue<'a>> {
+        self.position(key).map(|index| &self.children[index].value)
+    }
+
+    /// The position of the member [`get`](Self::get) finds.
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.children
+            .iter()
+            .position(|member| unescape(&member.key) == key)
+    }
+
+    /// Every key (unescaped text) with the position [`position`] finds it
+    /// at, for looking up many keys without scanning the object each time.
+    ///
+    /// [`position`]: Self::position
+    pub fn positions(&self) -> HashMap<Cow<'_, str>, usize> {
+        let mut positions = HashMap::with_capacity(self.children.len());
+        for (index, member) in self.children.iter().enumerate() {
+            positions.entry(unescape(&member.key)).or_insert(index);
+        }
+        positions
+    }
+
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    pub fn into_owned(self) -> JsonObject<'static> {
+        JsonObject {
+            children: self
+                .children
+                .into_iter()
+                .map(KeyValue::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl<'a> FromIterator<KeyValue<'a>> for JsonObject<'a> {
+    fn from_iter<I: IntoIterator<Item = KeyValue<'a>>>(iter: I) -> Self {
+        JsonObject {
+            children: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for JsonObject<'a> {
+    type Item = KeyValue<'a>;
+    type IntoIter = std::vec::IntoIter<KeyValue<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.children.into_iter()
+    }
 }
 
 impl<'a> Display for JsonObject<'a> {
@@ -1,26 +1,33 @@
 use crate::array::JsonArray;
 use crate::error::JsonParserInternalError::TokenIndexOutOfRange;
 use crate::error::{
-    expect_a_comma_or_right_brace_after_value, expect_colon_after_key,
-    expect_first_token_is_left_brace, expect_first_token_is_left_bracket_or_brace,
-    expect_key_or_right_brace, JsonParserError, JsonParserInternalError, UnexpectedTokenErrorDecr,
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_first_token_is_left_brace,
+    expect_first_token_is_left_bracket_or_brace, expect_key_or_right_brace, expect_value,
+    JsonParserError, JsonParserInternalError,
 };
 use crate::keyvalue::KeyValue;
 use crate::object::JsonObject;
 use crate::parser::JsonParserError::{
-    InternalJsonParserError, UnexpectedEndOfTokens, UnexpectedToken,
+    InternalJsonParserError, NestingTooDeep, UnexpectedEndOfTokens,
 };
 use crate::token::JsonToken::*;
 use crate::token::{JsonToken, JsonTokenStream};
 use crate::value::JsonValue;
-use crate::value::JsonValue::{Array, Empty, Object};
+use crate::value::JsonValue::{Array, Object};
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// How deeply objects and arrays may nest. Every parser in the crate fails
+/// with [`JsonParserError::NestingTooDeep`] beyond this rather than run out
+/// of stack on hostile input such as 100000 `[`.
+pub const MAX_DEPTH: usize = 128;
+
 #[derive(Debug)]
 pub struct Parser<'a> {
     tokens: &'a Vec<JsonToken<'a>>,
     pos: RefCell<usize>,
+    json5: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -29,27 +36,30 @@ impl<'a> Parser<'a> {
         Rc::new(Self {
             tokens,
             pos: RefCell::new(0),
+            json5: false,
         })
     }
 
-    pub fn parse(self: Rc<Self>) -> Result<JsonValue<'a>, JsonParserError<'a>> {
-        let first = self.tokens.first();
-        match first {
-            Some(first) => match first {
-                LeftBrace => parse_object(Rc::clone(&self)).map(|obj| Object(Box::new(obj))),
-                LeftBracket => parse_array(Rc::clone(&self)).map(|arr| Array(Box::new(arr))),
-                token => Err(expect_first_token_is_left_bracket_or_brace(token))?,
-            },
-            None => Err(UnexpectedEndOfTokens)?,
-        }
-    }
-
-    fn peek(&self) -> Option<&JsonToken<'a>> {
-        self.tokens.get(*self.pos.borrow())
+    /// A parser for tokens from
+    /// [`parse_json5_to_tokens`](crate::token::parse_json5_to_tokens), which
+    /// additionally allows a trailing comma in objects and arrays.
+    pub fn new_json5(tokens: &'a JsonTokenStream) -> Rc<Self> {
+        let tokens = &tokens.tokens;
+        Rc::new(Self {
+            tokens,
+            pos: RefCell::new(0),
+            json5: true,
+        })
     }
 
-    fn peek_offset(&self, offset: usize) -> Option<&JsonToken<'a>> {
-        self.tokens.get(*self.pos.borrow() + offset)
+    /// Parses the tokens as one document. As in RFC 8259, any value may
+    /// stand at the top level, and nothing may follow it.
+    pub fn parse(self: Rc<Self>) -> Result<JsonValue<'a>, JsonParserError<'a>> {
+        let value = parse_value(Rc::clone(&self), 0)?;
+        match self.next() {
+            Some(token) => Err(expect_end_of_input(token)),
+            None => Ok(value),
+        }
     }
 
     fn next(&self) -> Option<&'a JsonToken<'a>> {
@@ -61,14 +71,6 @@ impl<'a> Parser<'a> {
         self.tokens.get(pos)
     }
 
-    fn next_offset(&self, offset: usize) -> Option<&'a JsonToken<'a>> {
-        let pos = {
-            let pos = self.pos.borrow();
-            *pos + offset
-        };
-        *self.pos.borrow_mut() = pos;
-        self.tokens.get(pos)
-    }
     fn last(&self) -> Result<&'a JsonToken<'a>, JsonParserInternalError> {
         let pos = {
             let pos = self.pos.borrow();
@@ -82,7 +84,8 @@ impl<'a> Parser<'a> {
     }
 }
 
-fn parse_object(tokens: Rc<Parser>) -> Result<JsonObject, JsonParserError> {
+/// `depth` is the number of containers around the one being parsed.
+fn parse_object(tokens: Rc<Parser>, depth: usize) -> Result<JsonObject, JsonParserError> {
     let mut obj = JsonObject { children: vec![] };
     match tokens.next() {
         Some(LeftBrace) => {}
@@ -91,7 +94,8 @@ fn parse_object(tokens: Rc<Parser>) -> Result<JsonObject, JsonParserError> {
     }
     loop {
         match tokens.next() {
-            Some(RightBrace) => {
+            // Only JSON5 allows `}` straight after a comma.
+            Some(RightBrace) if obj.children.is_empty() || tokens.json5 => {
                 break;
             }
             Some(String(key)) => {
@@ -103,8 +107,8 @@ fn parse_object(tokens: Rc<Parser>) -> Result<JsonObject, JsonParserError> {
                     };
                 } else {
                     obj.children.push(KeyValue {
-                        key,
-                        value: parse_value(Rc::clone(&tokens))?,
+                        key: key.clone(),
+                        value: parse_value(Rc::clone(&tokens), depth + 1)?,
                     });
                 }
                 let next = tokens.next();
@@ -125,51 +129,51 @@ fn parse_object(tokens: Rc<Parser>) -> Result<JsonObject, JsonParserError> {
     Ok(obj)
 }
 
-fn parse_value(tokens: Rc<Parser>) -> Result<JsonValue, JsonParserError> {
+fn parse_value(tokens: Rc<Parser>, depth: usize) -> Result<JsonValue, JsonParserError> {
     match tokens.next() {
         None => Err(UnexpectedEndOfTokens)?,
         Some(token) => match token {
-            String(str) => Ok(JsonValue::String(Box::new(str))),
+            String(str) => Ok(JsonValue::String(Box::new(str.clone()))),
             Number(num) => Ok(JsonValue::Number(Box::new(*num))),
             True => Ok(JsonValue::True),
             False => Ok(JsonValue::False),
             Null => Ok(JsonValue::Null),
+            LeftBrace | LeftBracket if depth == MAX_DEPTH => Err(NestingTooDeep),
             LeftBrace => {
                 tokens.last().map_err(InternalJsonParserError)?;
-                parse_object(Rc::clone(&tokens)).map(|obj| Object(Box::new(obj)))
+                parse_object(Rc::clone(&tokens), depth).map(|obj| Object(Box::new(obj)))
             }
             LeftBracket => {
                 tokens.last().map_err(InternalJsonParserError)?;
-                parse_array(Rc::clone(&tokens)).map(|arr| Array(Box::new(arr)))
-            }
-            RightBracket => {
-                tokens.last().map_err(InternalJsonParserError)?;
-                Ok(Empty)
-            }
-            RightBrace => {
-                tokens.last().map_err(InternalJsonParserError)?;
-                Ok(Empty)
+                parse_array(Rc::clone(&tokens), depth).map(|arr| Array(Box::new(arr)))
             }
-            _ => Err(UnexpectedToken(UnexpectedTokenErrorDecr {
-                expect: "string, number, true, false, null, {, [",
-                actual: token,
-                msg: "it should be string, number, true, false, null, {, [",
-            })),
+            _ => Err(expect_value(token)),
         },
     }
 }
 
-fn parse_array(iter: Rc<Parser>) -> Result<JsonArray, JsonParserError> {
+fn parse_array(iter: Rc<Parser>, depth: usize) -> Result<JsonArray, JsonParserError> {
     let mut arr = JsonArray { array: vec![] };
+    match iter.next() {
+        Some(LeftBracket) => {}
+        Some(token) => Err(expect_first_token_is_left_bracket_or_brace(token))?,
+        None => Err(UnexpectedEndOfTokens)?,
+    }
     loop {
         match iter.next() {
             None => Err(UnexpectedEndOfTokens)?,
-            Some(token) => match token {
-                RightBracket => break,
-                _token => parse_value(Rc::clone(&iter)).map(|value| {
-                    arr.array.push(value);
-                })?,
-            },
+            // Only JSON5 allows `]` straight after a comma.
+            Some(RightBracket) if arr.array.is_empty() || iter.json5 => break,
+            Some(_) => {
+                iter.last().map_err(InternalJsonParserError)?;
+                arr.array.push(parse_value(Rc::clone(&iter), depth + 1)?);
+            }
+        }
+        match iter.next() {
+            None => Err(UnexpectedEndOfTokens)?,
+            Some(Comma) => {}
+            Some(RightBracket) => break,
+            Some(token) => Err(expect_a_comma_or_right_bracket_after_value(token))?,
         }
     }
     Ok(arr)
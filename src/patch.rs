@@ -0,0 +1,913 @@
+//! RFC 6902 JSON Patch: a list of `add`, `remove`, `replace`, `move`, `copy`
+//! and `test` operations, each addressing the document with a JSON Pointer.
+//!
+//! [`apply_patch`] applies every operation or none of them, and [`diff`]
+//! builds the patch that turns one document into another.
+//!
+//! ```
+//! use rust_json::convert::FromJson;
+//! use rust_json::parser::Parser;
+//! use rust_json::patch::{apply_patch, diff, Patch};
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let tokens = parse_to_tokens(r#"[{"op": "add", "path": "/tags/-", "value": "new"}]"#).unwrap();
+//! let operations = Parser::new(&tokens).parse().unwrap();
+//! let patch = Patch::from_json(&operations).unwrap();
+//!
+//! let tokens = parse_to_tokens(r#"{"tags": ["old"]}"#).unwrap();
+//! let before = Parser::new(&tokens).parse().unwrap();
+//! let mut after = before.clone();
+//! apply_patch(&mut after, &patch).unwrap();
+//! assert_eq!(after.to_string(), r#"{"tags":["old","new"]}"#);
+//!
+//! assert_eq!(
+//!     diff(&before, &after).to_string(),
+//!     r#"[{"op":"add","path":"/tags/1","value":"new"}]"#
+//! );
+//! ```
+
+use crate::convert::{FromJson, FromJsonError, ToJson};
+use crate::escape::unescape;
+use crate::keyvalue::KeyValue;
+use crate::pointer::{escape_token, split_last, PointerError, Slot};
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::mem;
+
+/// One step of a patch. Paths are JSON Pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation<'a> {
+    /// Adds or replaces an object member, or inserts an array element.
+    Add {
+        path: String,
+        value: JsonValue<'a>,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replaces a value that must already exist.
+    Replace {
+        path: String,
+        value: JsonValue<'a>,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Checks that the value at `path` equals `value`.
+    Test {
+        path: String,
+        value: JsonValue<'a>,
+    },
+}
+
+const OPERATIONS: &[&str] = &["add", "remove", "replace", "move", "copy", "test"];
+
+impl<'a> Operation<'a> {
+    pub fn into_owned(self) -> Operation<'static> {
+        match self {
+            Operation::Add { path, value } => Operation::Add {
+                path,
+                value: value.into_owned(),
+            },
+            Operation::Remove { path } => Operation::Remove { path },
+            Operation::Replace { path, value } => Operation::Replace {
+                path,
+                value: value.into_owned(),
+            },
+            Operation::Move { from, path } => Operation::Move { from, path },
+            Operation::Copy { from, path } => Operation::Copy { from, path },
+            Operation::Test { path, value } => Operation::Test {
+                path,
+                value: value.into_owned(),
+            },
+        }
+    }
+}
+
+fn member<'v>(key: &'static str, value: JsonValue<'v>) -> KeyValue<'v> {
+    KeyValue {
+        key: Cow::Borrowed(key),
+        value,
+    }
+}
+
+impl ToJson for Operation<'_> {
+    fn to_json(&self) -> JsonValue<'_> {
+        let (op, path, from, value) = match self {
+            Operation::Add { path, value } => ("add", path, None, Some(value)),
+            Operation::Remove { path } => ("remove", path, None, None),
+            Operation::Replace { path, value } => ("replace", path, None, Some(value)),
+            Operation::Move { from, path } => ("move", path, Some(from), None),
+            Operation::Copy { from, path } => ("copy", path, Some(from), None),
+            Operation::Test { path, value } => ("test", path, None, Some(value)),
+        };
+        let mut members = vec![member("op", op.to_json()), member("path", path.to_json())];
+        if let Some(from) = from {
+            members.push(member("from", from.to_json()));
+        }
+        if let Some(value) = value {
+            members.push(member("value", value.clone()));
+        }
+        JsonValue::Object(Box::new(members.into_iter().collect()))
+    }
+}
+
+impl<'v> FromJson<'v> for Operation<'v> {
+    /// Members other than `op`, `path`, `from` and `value` are ignored.
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        let op = value.get("op").parse::<Cow<str>>()?;
+        let path = || value.get("path").parse::<String>();
+        let from = || value.get("from").parse::<String>();
+        let operand = || value.get("value").parse::<JsonValue<'v>>();
+        Ok(match &*op {
+            "add" => Operation::Add {
+                path: path()?,
+                value: operand()?,
+            },
+            "remove" => Operation::Remove { path: path()? },
+            "replace" => Operation::Replace {
+                path: path()?,
+                value: operand()?,
+            },
+            "move" => Operation::Move {
+                from: from()?,
+                path: path()?,
+            },
+            "copy" => Operation::Copy {
+                from: from()?,
+                path: path()?,
+            },
+            "test" => Operation::Test {
+                path: path()?,
+                value: operand()?,
+            },
+            other => return Err(FromJsonError::unknown_variant(other, OPERATIONS).at_key("op")),
+        })
+    }
+}
+
+/// A JSON Patch document: operations applied in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch<'a> {
+    pub operations: Vec<Operation<'a>>,
+}
+
+impl<'a> Patch<'a> {
+    pub fn into_owned(self) -> Patch<'static> {
+        Patch {
+            operations: self
+                .operations
+                .into_iter()
+                .map(Operation::into_owned)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+impl ToJson for Patch<'_> {
+    fn to_json(&self) -> JsonValue<'_> {
+        self.operations.to_json()
+    }
+}
+
+impl<'v> FromJson<'v> for Patch<'v> {
+    fn from_json(value: &'v JsonValue<'_>) -> Result<Self, FromJsonError> {
+        Vec::from_json(value).map(|operations| Patch { operations })
+    }
+}
+
+impl Display for Patch<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+/// Why a patch was not applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    /// The position of the failing operation in the patch.
+    pub index: usize,
+    pub kind: PatchErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    /// `path` or `from` could not be followed.
+    Pointer(PointerError),
+    /// A `test` found a different value at this path.
+    TestFailed(String),
+    /// A `move` whose `path` is inside its `from`.
+    MoveIntoChild { from: String, path: String },
+}
+
+impl From<PointerError> for PatchErrorKind {
+    fn from(error: PointerError) -> Self {
+        PatchErrorKind::Pointer(error)
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "operation {}: ", self.index)?;
+        match &self.kind {
+            PatchErrorKind::Pointer(error) => write!(f, "{}", error),
+            PatchErrorKind::TestFailed(path) => write!(f, "test failed at `{}`", path),
+            PatchErrorKind::MoveIntoChild { from, path } => {
+                write!(f, "cannot move `{}` into its own child `{}`", from, path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// How to take back one applied operation.
+enum Undo<'a> {
+    /// Take out what was put at `pointer`, putting back what it replaced.
+    Add {
+        pointer: String,
+        replaced: Option<JsonValue<'a>>,
+    },
+    /// Put back a removed value.
+    Remove {
+        parent: String,
+        slot: Slot<'a>,
+        value: JsonValue<'a>,
+    },
+    /// Take a moved value out of `pointer` and put it back where it was.
+    Move {
+        pointer: String,
+        replaced: Option<JsonValue<'a>>,
+        parent: String,
+        slot: Slot<'a>,
+    },
+}
+
+const UNDO: &str = "undoing a change that was just made";
+
+impl<'a> Undo<'a> {
+    fn revert(self, document: &mut JsonValue<'a>) {
+        match self {
+            Undo::Add { pointer, replaced } => {
+                take(document, &pointer, replaced);
+            }
+            Undo::Remove {
+                parent,
+                slot,
+                value,
+            } => document.restore_entry(&parent, slot, value).expect(UNDO),
+            Undo::Move {
+                pointer,
+                replaced,
+                parent,
+                slot,
+            } => {
+                let value = take(document, &pointer, replaced);
+                document.restore_entry(&parent, slot, value).expect(UNDO)
+            }
+        }
+    }
+}
+
+/// Takes out the value an `add` put at `pointer`.
+fn take<'a>(
+    document: &mut JsonValue<'a>,
+    pointer: &str,
+    replaced: Option<JsonValue<'a>>,
+) -> JsonValue<'a> {
+    match replaced {
+        Some(old) => mem::replace(document.pointer_mut(pointer).expect(UNDO), old),
+        None => document.remove_at(pointer).expect(UNDO),
+    }
+}
+
+/// Does an `add`, returning the pointer the value ended up at (`-` becomes
+/// the index it was appended at) and the value it replaced.
+fn add<'a>(
+    document: &mut JsonValue<'a>,
+    path: &str,
+    value: JsonValue<'a>,
+) -> Result<(String, Option<JsonValue<'a>>), (PointerError, JsonValue<'a>)> {
+    let replaced = document.insert_entry(path, value)?;
+    let pointer = match split_last(path) {
+        Some((parent, "-")) => match document.pointer(parent) {
+            Some(JsonValue::Array(array)) => format!("{}/{}", parent, array.array.len() - 1),
+            _ => path.to_string(),
+        },
+        _ => path.to_string(),
+    };
+    Ok((pointer, replaced))
+}
+
+fn parent(path: &str) -> String {
+    split_last(path)
+        .map_or("", |(parent, _)| parent)
+        .to_string()
+}
+
+fn apply<'a>(
+    document: &mut JsonValue<'a>,
+    operation: &Operation<'a>,
+    undo: &mut Vec<Undo<'a>>,
+) -> Result<(), PatchErrorKind> {
+    match operation {
+        Operation::Add { path, value } => {
+            let (pointer, replaced) = add(document, path, value.clone()).map_err(|(e, _)| e)?;
+            undo.push(Undo::Add { pointer, replaced });
+        }
+        Operation::Remove { path } => {
+            let (slot, value) = document.remove_entry(path)?;
+            undo.push(Undo::Remove {
+                parent: parent(path),
+                slot,
+                value,
+            });
+        }
+        Operation::Replace { path, value } => {
+            let target = document.try_pointer_mut(path)?;
+            let replaced = Some(mem::replace(target, value.clone()));
+            undo.push(Undo::Add {
+                pointer: path.clone(),
+                replaced,
+            });
+        }
+        Operation::Move { from, path } if from == path => {
+            document.try_pointer(from)?;
+        }
+        Operation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(PatchErrorKind::MoveIntoChild {
+                    from: from.clone(),
+                    path: path.clone(),
+                });
+            }
+            let (slot, value) = document.remove_entry(from)?;
+            match add(document, path, value) {
+                Ok((pointer, replaced)) => undo.push(Undo::Move {
+                    pointer,
+                    replaced,
+                    parent: parent(from),
+                    slot,
+                }),
+                Err((error, value)) => {
+                    document
+                        .restore_entry(&parent(from), slot, value)
+                        .expect(UNDO);
+                    return Err(error.into());
+                }
+            }
+        }
+        Operation::Copy { from, path } => {
+            let value = document.try_pointer(from)?.clone();
+            let (pointer, replaced) = add(document, path, value).map_err(|(e, _)| e)?;
+            undo.push(Undo::Add { pointer, replaced });
+        }
+        Operation::Test { path, value } => {
+            if !document.try_pointer(path)?.equivalent(value) {
+                return Err(PatchErrorKind::TestFailed(path.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies `patch` to `document`. If an operation fails, the operations
+/// before it are undone, leaving `document` as it was.
+pub fn apply_patch<'a>(document: &mut JsonValue<'a>, patch: &Patch<'a>) -> Result<(), PatchError> {
+    let mut undo = vec![];
+    for (index, operation) in patch.operations.iter().enumerate() {
+        if let Err(kind) = apply(document, operation, &mut undo) {
+            for step in undo.into_iter().rev() {
+                step.revert(document);
+            }
+            return Err(PatchError { index, kind });
+        }
+    }
+    Ok(())
+}
+
+/// The operations turning `from` into `to`. Object members are compared by
+/// key and array elements by a longest common subsequence, so unchanged
+/// parts produce no operations; values that changed in place are diffed
+/// further down rather than replaced whole.
+pub fn diff<'a>(from: &JsonValue, to: &JsonValue<'a>) -> Patch<'a> {
+    let mut operations = vec![];
+    diff_values(from, to, &mut String::new(), &mut operations);
+    Patch { operations }
+}
+
+/// Runs `f` with `path` extended by one reference token.
+fn with_token<R>(path: &mut String, token: &str, f: impl FnOnce(&mut String) -> R) -> R {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&escape_token(token));
+    let result = f(path);
+    path.truncate(len);
+    result
+}
+
+fn diff_values<'a>(
+    from: &JsonValue,
+    to: &JsonValue<'a>,
+    path: &mut String,
+    operations: &mut Vec<Operation<'a>>,
+) {
+    if from.equivalent(to) {
+        return;
+    }
+    match (from, to) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            let (old_positions, new_positions) = (old.positions(), new.positions());
+            for member in old.iter() {
+                let key = unescape(&member.key);
+                if !new_positions.contains_key(&key) {
+                    let path = with_token(path, &key, |path| path.clone());
+                    operations.push(Operation::Remove { path });
+                }
+            }
+            for member in new.children.iter() {
+                let key = unescape(&member.key);
+                with_token(path, &key, |path| match old_positions.get(&key) {
+                    Some(&index) => {
+                        diff_values(&old.children[index].value, &member.value, path, operations)
+                    }
+                    None => operations.push(Operation::Add {
+                        path: path.clone(),
+                        value: member.value.clone(),
+                    }),
+                });
+            }
+        }
+        (JsonValue::Array(old), JsonValue::Array(new)) => {
+            diff_arrays(&old.array, &new.array, path, operations)
+        }
+        _ => operations.push(Operation::Replace {
+            path: path.clone(),
+            value: to.clone(),
+        }),
+    }
+}
+
+/// Arrays with more pairs of elements than this are not searched for a
+/// common subsequence; their elements are only compared position by
+/// position.
+const LCS_LIMIT: usize = 1 << 20;
+
+enum Edit {
+    Keep,
+    /// Drop this element of the old array.
+    Delete(usize),
+    /// Insert this element of the new array.
+    Insert(usize),
+}
+
+/// How an element of one array relates to the other, by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Aligned {
+    Kept(usize, usize),
+    Changed(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// The edits keeping the longest common subsequence of `from` and `to`,
+/// deletions before insertions where both are possible so that they pair up
+/// into changes.
+fn common_subsequence<E>(from: &[JsonValue], to: &[JsonValue], equal: &E) -> Vec<Edit>
+where
+    E: Fn(&JsonValue, &JsonValue) -> bool,
+{
+    let (n, m) = (from.len(), to.len());
+    // lcs[i * (m + 1) + j]: the longest common subsequence of from[i..] and to[j..].
+    let mut lcs = vec![0usize; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if equal(&from[i], &to[j]) {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+    let mut edits = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && equal(&from[i], &to[j]) {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+            edits.push(Edit::Delete(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// The edits keeping only the elements that are equal at the same position.
+fn positional<E>(from: &[JsonValue], to: &[JsonValue], equal: &E) -> Vec<Edit>
+where
+    E: Fn(&JsonValue, &JsonValue) -> bool,
+{
+    let mut edits = vec![];
+    for (i, (a, b)) in from.iter().zip(to).enumerate() {
+        if equal(a, b) {
+            edits.push(Edit::Keep);
+        } else {
+            edits.extend([Edit::Delete(i), Edit::Insert(i)]);
+        }
+    }
+    edits.extend((to.len()..from.len()).map(Edit::Delete));
+    edits.extend((from.len()..to.len()).map(Edit::Insert));
+    edits
+}
+
+/// Roughly how many operations `edits` turn into: each run between kept
+/// elements pairs its deletions and insertions into changes.
+fn cost(edits: &[Edit]) -> usize {
+    edits
+        .split(|edit| matches!(edit, Edit::Keep))
+        .map(|run| {
+            let deletes = run.iter().filter(|edit| matches!(edit, Edit::Delete(_)));
+            let deletes = deletes.count();
+            deletes.max(run.len() - deletes)
+        })
+        .sum()
+}
+
+/// Lines up the elements of two arrays, keeping as many as possible and
+/// preferring a change in place to a removal and an addition.
+///
+/// A common subsequence finds insertions and removals, while comparing by
+/// position finds elements that changed in place; whichever needs fewer
+/// steps wins. Within each stretch between kept elements, changes come
+/// first, then removals, then additions.
+pub(crate) fn align<E>(from: &[JsonValue], to: &[JsonValue], equal: E) -> Vec<Aligned>
+where
+    E: Fn(&JsonValue, &JsonValue) -> bool,
+{
+    let mut edits = positional(from, to, &equal);
+    // Nothing beats keeping every element, which is the common case when
+    // comparing mostly equal documents.
+    if cost(&edits) > 0 && from.len().saturating_mul(to.len()) <= LCS_LIMIT {
+        let common = common_subsequence(from, to, &equal);
+        if cost(&common) <= cost(&edits) {
+            edits = common;
+        }
+    }
+    let mut aligned = vec![];
+    let (mut i, mut j) = (0, 0);
+    for run in edits.split(|edit| matches!(edit, Edit::Keep)) {
+        let deletes: Vec<_> = run
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Delete(i) => Some(*i),
+                _ => None,
+            })
+            .collect();
+        let inserts: Vec<_> = run
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Insert(j) => Some(*j),
+                _ => None,
+            })
+            .collect();
+        let pairs = deletes.len().min(inserts.len());
+        aligned.extend((0..pairs).map(|k| Aligned::Changed(deletes[k], inserts[k])));
+        aligned.extend(deletes[pairs..].iter().map(|&i| Aligned::Removed(i)));
+        aligned.extend(inserts[pairs..].iter().map(|&j| Aligned::Added(j)));
+        i += deletes.len();
+        j += inserts.len();
+        // The kept element after this run, unless this is the last run.
+        if i < from.len() && j < to.len() {
+            aligned.push(Aligned::Kept(i, j));
+            i += 1;
+            j += 1;
+        }
+    }
+    aligned
+}
+
+fn diff_arrays<'a>(
+    from: &[JsonValue],
+    to: &[JsonValue<'a>],
+    path: &mut String,
+    operations: &mut Vec<Operation<'a>>,
+) {
+    let prefix = from
+        .iter()
+        .zip(to)
+        .take_while(|(a, b)| a.equivalent(b))
+        .count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a.equivalent(b))
+        .count();
+    let from = &from[prefix..from.len() - suffix];
+    let to = &to[prefix..to.len() - suffix];
+
+    // Where the next element goes in the array as patched so far.
+    let mut index = prefix;
+    for aligned in align(from, to, |a, b| a.equivalent(b)) {
+        match aligned {
+            Aligned::Kept(..) => index += 1,
+            Aligned::Changed(i, j) => {
+                with_token(path, &index.to_string(), |path| {
+                    diff_values(&from[i], &to[j], path, operations)
+                });
+                index += 1;
+            }
+            Aligned::Removed(_) => {
+                let path = with_token(path, &index.to_string(), |path| path.clone());
+                operations.push(Operation::Remove { path });
+            }
+            Aligned::Added(j) => {
+                let path = with_token(path, &index.to_string(), |path| path.clone());
+                operations.push(Operation::Add {
+                    path,
+                    value: to[j].clone(),
+                });
+                index += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_patch {
+    use super::*;
+    use crate::value::parse;
+
+    fn patch(text: &str) -> Patch<'static> {
+        Patch::from_json(&parse(text)).unwrap().into_owned()
+    }
+
+    /// Applies `operations` to `document` and returns the result as text, or
+    /// the error.
+    fn applied(document: &str, operations: &str) -> Result<JsonValue<'static>, String> {
+        let mut value = parse(document);
+        apply_patch(&mut value, &patch(operations)).map_err(|e| e.to_string())?;
+        Ok(value)
+    }
+
+    fn assert_applies(document: &str, operations: &str, expected: &str) {
+        let result = applied(document, operations).unwrap();
+        assert!(
+            result.equivalent(&parse(expected)),
+            "{} gave {}",
+            operations,
+            result
+        );
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        // RFC 6902 appendix A.
+        assert_applies(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#,
+            r#"{"baz": "qux", "foo": "bar"}"#,
+        );
+        assert_applies(
+            r#"{"foo": ["bar", "baz"]}"#,
+            r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#,
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+        );
+        assert_applies(
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "remove", "path": "/baz"}]"#,
+            r#"{"foo": "bar"}"#,
+        );
+        assert_applies(
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+            r#"[{"op": "remove", "path": "/foo/1"}]"#,
+            r#"{"foo": ["bar", "baz"]}"#,
+        );
+        assert_applies(
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#,
+            r#"{"baz": "boo", "foo": "bar"}"#,
+        );
+        assert_applies(
+            r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+            r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+            r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#,
+        );
+        assert_applies(
+            r#"{"foo": ["all", "grass", "cows", "eat"]}"#,
+            r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#,
+            r#"{"foo": ["all", "cows", "eat", "grass"]}"#,
+        );
+        assert_applies(
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+            r#"[{"op": "test", "path": "/baz", "value": "qux"},
+                {"op": "test", "path": "/foo/1", "value": 2}]"#,
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+        );
+        assert_eq!(
+            applied(
+                r#"{"baz": "qux"}"#,
+                r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#
+            ),
+            Err("operation 0: test failed at `/baz`".to_string())
+        );
+        assert_applies(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#,
+            r#"{"foo": "bar", "child": {"grandchild": {}}}"#,
+        );
+        assert_applies(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]"#,
+            r#"{"foo": "bar", "baz": "qux"}"#,
+        );
+        assert_eq!(
+            applied(
+                r#"{"foo": "bar"}"#,
+                r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#
+            ),
+            Err("operation 0: no value at `/baz`".to_string())
+        );
+        assert_applies(
+            r#"{"/": 9, "~1": 10}"#,
+            r#"[{"op": "test", "path": "/~01", "value": 10}]"#,
+            r#"{"/": 9, "~1": 10}"#,
+        );
+        assert_eq!(
+            applied(
+                r#"{"/": 9, "~1": 10}"#,
+                r#"[{"op": "test", "path": "/~01", "value": "10"}]"#
+            ),
+            Err("operation 0: test failed at `/~01`".to_string())
+        );
+        assert_applies(
+            r#"{"foo": ["bar"]}"#,
+            r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#,
+            r#"{"foo": ["bar", ["abc", "def"]]}"#,
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        assert_applies(
+            r#"{"a": {"b": 1}}"#,
+            r#"[{"op": "copy", "from": "/a", "path": "/c"},
+                {"op": "replace", "path": "/c/b", "value": 2}]"#,
+            r#"{"a": {"b": 1}, "c": {"b": 2}}"#,
+        );
+        assert_applies(
+            r#"{"a": 1}"#,
+            r#"[{"op": "replace", "path": "", "value": [1]}]"#,
+            "[1]",
+        );
+        assert_applies(
+            r#"{"a": 1}"#,
+            r#"[{"op": "move", "from": "/a", "path": "/a"}]"#,
+            r#"{"a": 1}"#,
+        );
+        assert_eq!(
+            applied(
+                r#"{"a": {"b": 1}}"#,
+                r#"[{"op": "move", "from": "/a", "path": "/a/b/c"}]"#
+            ),
+            Err("operation 0: cannot move `/a` into its own child `/a/b/c`".to_string())
+        );
+        assert_eq!(
+            applied(
+                r#"{"a": 1}"#,
+                r#"[{"op": "replace", "path": "/b", "value": 2}]"#
+            ),
+            Err("operation 0: no value at `/b`".to_string())
+        );
+        assert_eq!(
+            applied("[1]", r#"[{"op": "remove", "path": ""}]"#),
+            Err("operation 0: the whole document cannot be removed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        let document = r#"{"a": 1, "b": [1, 2, 3], "c": {"d": "e"}}"#;
+        let operations = r#"[
+            {"op": "remove", "path": "/a"},
+            {"op": "add", "path": "/b/-", "value": 4},
+            {"op": "move", "from": "/b/0", "path": "/c/d"},
+            {"op": "copy", "from": "/c", "path": "/b/1"},
+            {"op": "replace", "path": "", "value": null},
+            {"op": "test", "path": "", "value": false}
+        ]"#;
+        let mut value = parse(document);
+        let error = apply_patch(&mut value, &patch(operations)).unwrap_err();
+        assert_eq!(error.index, 5);
+        assert_eq!(error.kind, PatchErrorKind::TestFailed(String::new()));
+        // Member order is restored too.
+        assert_eq!(value, parse(document));
+
+        // A move whose destination is invalid once its source is gone.
+        let mut value = parse("[1, 2]");
+        let operations = r#"[{"op": "move", "from": "/0", "path": "/2"}]"#;
+        assert!(apply_patch(&mut value, &patch(operations)).is_err());
+        assert_eq!(value, parse("[1, 2]"));
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let text =
+            r#"[{"op":"move","path":"/b","from":"/a"},{"op":"test","path":"/b","value":null}]"#;
+        assert_eq!(patch(text).to_string(), text);
+        let error = |text: &str| Patch::from_json(&parse(text)).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"[{"op": "add", "path": "/a"}]"#),
+            "[0]: missing field `value`"
+        );
+        assert_eq!(
+            error(r#"[{"op": "remove", "path": "/a"}, {"op": "delete", "path": "/a"}]"#),
+            "[1].op: unknown variant `delete`, expected one of `add`, `remove`, `replace`, `move`, `copy`, `test`"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let cases = [
+            ("1", "1", 0),
+            ("1", "2", 1),
+            (r#"{"a": 1, "b": 2}"#, r#"{"b": 2, "a": 1}"#, 0),
+            (r#"{"a": 1, "b": 2}"#, r#"{"b": 3, "c": 1}"#, 3),
+            (r#"{"a/b": {"~": 1}}"#, r#"{"a/b": {"~": 2}}"#, 1),
+            ("[1, 2, 3, 4, 5]", "[1, 2, 4, 5]", 1),
+            ("[1, 2, 3]", "[0, 1, 2, 3, 4]", 2),
+            ("[1, 2, 3]", "[3, 2, 1]", 2),
+            ("[1, 2, 3]", "[]", 3),
+            ("[]", "[1, 2]", 2),
+            (r#"[{"a": 1}, {"b": 2}]"#, r#"[{"a": 1}, {"b": 3}]"#, 1),
+            (r#"[1, {"x": [1, 2]}, 3]"#, r#"[{"x": [1, 3]}, 3, 4]"#, 3),
+            ("[1, 2, 3, 4]", "[5, 6]", 4),
+            (r#"{"a": [1]}"#, r#"{"a": {"0": 1}}"#, 1),
+        ];
+        for (from, to, count) in cases {
+            let (from, to) = (parse(from), parse(to));
+            let patch = diff(&from, &to);
+            assert_eq!(
+                patch.operations.len(),
+                count,
+                "{} to {}: {}",
+                from,
+                to,
+                patch
+            );
+            let mut value = from.clone();
+            apply_patch(&mut value, &patch).unwrap();
+            assert!(
+                value.equivalent(&to),
+                "{} to {}: {} gave {}",
+                from,
+                to,
+                patch,
+                value
+            );
+        }
+        assert_eq!(
+            diff(&parse(r#"{"a/b": [1, 2]}"#), &parse(r#"{"a/b": [1, 3]}"#)).to_string(),
+            r#"[{"op":"replace","path":"/a~1b/1","value":3}]"#
+        );
+    }
+
+    #[test]
+    fn test_diff_large_objects() {
+        // Linear in the number of members, with the keys in another order.
+        let members = |changed: usize| {
+            let object = (0..20_000)
+                .rev()
+                .map(|i| KeyValue {
+                    key: format!("k{}", i).into(),
+                    value: JsonValue::Number(Box::new(if i == changed { -1.0 } else { 0.0 })),
+                })
+                .collect();
+            JsonValue::Object(Box::new(object))
+        };
+        let from = members(usize::MAX);
+        let mut to = members(7);
+        if let JsonValue::Object(object) = &mut to {
+            object.children.reverse();
+        }
+        assert_eq!(
+            diff(&from, &to).to_string(),
+            r#"[{"op":"replace","path":"/k7","value":-1}]"#
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! RFC 6901 JSON Pointers: `/servers/0/port` names the `port` member of the
+//! first element of `servers`. `~1` stands for `/` and `~0` for `~` inside a
+//! reference token; the empty pointer is the whole document.
+//!
+//! ```
+//! use rust_json::parser::Parser;
+//! use rust_json::token::parse_to_tokens;
+//! use rust_json::value::JsonValue;
+//!
+//! let tokens = parse_to_tokens(r#"{"format": {"array": [1, 2, 3, 4]}}"#).unwrap();
+//! let mut value = Parser::new(&tokens).parse().unwrap();
+//! assert_eq!(value.pointer("/format/array/3").unwrap().to_string(), "4");
+//!
+//! value.insert_at("/format/array/-", JsonValue::Null).unwrap();
+//! value.remove_at("/format/array/0").unwrap();
+//! assert_eq!(value.to_string(), r#"{"format":{"array":[2,3,4,null]}}"#);
+//! ```
+
+use crate::escape;
+use crate::keyvalue::KeyValue;
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::mem;
+
+/// Why a pointer could not be followed. Each variant holds the pointer up to
+/// and including the reference token where it went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// Neither empty nor starting with `/`, or a `~` not followed by `0` or
+    /// `1`. Holds the whole pointer.
+    Syntax(String),
+    /// No member with that name, or an array index past the end.
+    NotFound(String),
+    /// An array index that is not a number, or has leading zeros.
+    InvalidIndex(String),
+    /// The token steps into a string, number, boolean or null.
+    NotAContainer(String),
+    /// The empty pointer was given where a member or element is needed.
+    Root,
+}
+
+impl Display for PointerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PointerError::Syntax(pointer) => write!(f, "`{}` is not a JSON pointer", pointer),
+            PointerError::NotFound(pointer) => write!(f, "no value at `{}`", pointer),
+            PointerError::InvalidIndex(pointer) => {
+                write!(f, "`{}` does not end in a valid array index", pointer)
+            }
+            PointerError::NotAContainer(pointer) => {
+                write!(
+                    f,
+                    "`{}` steps into a value that is neither object nor array",
+                    pointer
+                )
+            }
+            PointerError::Root => write!(f, "the whole document cannot be removed"),
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Escapes `~` and `/` so that `token` can be appended to a pointer.
+pub fn escape_token(token: &str) -> Cow<'_, str> {
+    if token.contains(['~', '/']) {
+        Cow::Owned(token.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// One unescaped reference token.
+struct Token<'p> {
+    text: Cow<'p, str>,
+    /// The pointer up to and including this token, for errors.
+    prefix: &'p str,
+}
+
+fn unescape_token(raw: &str) -> Option<Cow<'_, str>> {
+    if !raw.contains('~') {
+        return Some(Cow::Borrowed(raw));
+    }
+    let mut text = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '~' => match chars.next() {
+                Some('0') => text.push('~'),
+                Some('1') => text.push('/'),
+                _ => return None,
+            },
+            char => text.push(char),
+        }
+    }
+    Some(Cow::Owned(text))
+}
+
+fn parse(pointer: &str) -> Result<Vec<Token<'_>>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let syntax = || PointerError::Syntax(pointer.to_string());
+    let rest = pointer.strip_prefix('/').ok_or_else(syntax)?;
+    let mut end = 0;
+    rest.split('/')
+        .map(|raw| {
+            end += 1 + raw.len();
+            Ok(Token {
+                text: unescape_token(raw).ok_or_else(syntax)?,
+                prefix: &pointer[..end],
+            })
+        })
+        .collect()
+}
+
+/// `0`, or digits without a leading zero.
+fn parse_index(token: &Token) -> Result<usize, PointerError> {
+    let text = &*token.text;
+    let digits = !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+    match text.parse() {
+        Ok(index) if digits && (text == "0" || !text.starts_with('0')) => Ok(index),
+        _ => Err(PointerError::InvalidIndex(token.prefix.to_string())),
+    }
+}
+
+/// Finds the member or element of an existing container that `token` names;
+/// `-` never names an element.
+fn position(value: &JsonValue, token: &Token) -> Result<usize, PointerError> {
+    let not_found = || PointerError::NotFound(token.prefix.to_string());
+    match value {
+        JsonValue::Object(object) => object.position(&token.text).ok_or_else(not_found),
+        JsonValue::Array(_) if token.text == "-" => Err(not_found()),
+        JsonValue::Array(array) => match parse_index(token)? {
+            index if index < array.array.len() => Ok(index),
+            _ => Err(not_found()),
+        },
+        _ => Err(PointerError::NotAContainer(token.prefix.to_string())),
+    }
+}
+
+fn child<'v, 'a>(value: &'v JsonValue<'a>, index: usize) -> &'v JsonValue<'a> {
+    match value {
+        JsonValue::Object(object) => &object.children[index].value,
+        JsonValue::Array(array) => &array.array[index],
+        _ => unreachable!("position only succeeds on containers"),
+    }
+}
+
+fn child_mut<'v, 'a>(value: &'v mut JsonValue<'a>, index: usize) -> &'v mut JsonValue<'a> {
+    match value {
+        JsonValue::Object(object) => &mut object.children[index].value,
+        JsonValue::Array(array) => &mut array.array[index],
+        _ => unreachable!("position only succeeds on containers"),
+    }
+}
+
+fn walk<'v, 'a>(
+    mut value: &'v JsonValue<'a>,
+    tokens: &[Token],
+) -> Result<&'v JsonValue<'a>, PointerError> {
+    for token in tokens {
+        value = child(value, position(value, token)?);
+    }
+    Ok(value)
+}
+
+fn walk_mut<'v, 'a>(
+    mut value: &'v mut JsonValue<'a>,
+    tokens: &[Token],
+) -> Result<&'v mut JsonValue<'a>, PointerError> {
+    for token in tokens {
+        let index = position(value, token)?;
+        value = child_mut(value, index);
+    }
+    Ok(value)
+}
+
+impl<'a> JsonValue<'a> {
+    /// The value `pointer` refers to, if there is one.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue<'a>> {
+        self.try_pointer(pointer).ok()
+    }
+
+    /// Like [`JsonValue::pointer`], but says why nothing was found.
+    pub fn try_pointer(&self, pointer: &str) -> Result<&JsonValue<'a>, PointerError> {
+        walk(self, &parse(pointer)?)
+    }
+
+    /// The value `pointer` refers to, for changing in place.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue<'a>> {
+        self.try_pointer_mut(pointer).ok()
+    }
+
+    /// Like [`JsonValue::pointer_mut`], but says why nothing was found.
+    pub fn try_pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonValue<'a>, PointerError> {
+        walk_mut(self, &parse(pointer)?)
+    }
+
+    /// Puts `value` where `pointer` refers to, as JSON Patch `add` does: an
+    /// object member is added or replaced, and an array element is inserted
+    /// before the given index, or appended for `-`. The parent must exist.
+    ///
+    /// Returns the value that was replaced, if any.
+    pub fn insert_at(
+        &mut self,
+        pointer: &str,
+        value: JsonValue<'a>,
+    ) -> Result<Option<JsonValue<'a>>, PointerError> {
+        self.insert_entry(pointer, value)
+            .map_err(|(error, _)| error)
+    }
+
+    /// Like [`JsonValue::insert_at`], but hands `value` back on failure.
+    pub(crate) fn insert_entry(
+        &mut self,
+        pointer: &str,
+        value: JsonValue<'a>,
+    ) -> Result<Option<JsonValue<'a>>, (PointerError, JsonValue<'a>)> {
+        let tokens = match parse(pointer) {
+            Ok(tokens) => tokens,
+            Err(error) => return Err((error, value)),
+        };
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(mem::replace(self, value)));
+        };
+        let parent = match walk_mut(self, parents) {
+            Ok(parent) => parent,
+            Err(error) => return Err((error, value)),
+        };
+        match parent {
+            JsonValue::Object(object) => match object.position(&last.text) {
+                Some(index) => Ok(Some(mem::replace(&mut object.children[index].value, value))),
+                None => {
+                    object.children.push(KeyValue {
+                        key: Cow::Owned(escape::escape(&last.text).into_owned()),
+                        value,
+                    });
+                    Ok(None)
+                }
+            },
+            JsonValue::Array(array) => {
+                let len = array.array.len();
+                let index = if last.text == "-" {
+                    len
+                } else {
+                    match parse_index(last) {
+                        Ok(index) => index,
+                        Err(error) => return Err((error, value)),
+                    }
+                };
+                if index > len {
+                    return Err((PointerError::NotFound(last.prefix.to_string()), value));
+                }
+                array.array.insert(index, value);
+                Ok(None)
+            }
+            _ => Err((PointerError::NotAContainer(last.prefix.to_string()), value)),
+        }
+    }
+
+    /// Takes the member or element `pointer` refers to out of its parent.
+    pub fn remove_at(&mut self, pointer: &str) -> Result<JsonValue<'a>, PointerError> {
+        self.remove_entry(pointer).map(|(_, value)| value)
+    }
+
+    /// Like [`JsonValue::remove_at`], also saying where in its parent the
+    /// value was.
+    pub(crate) fn remove_entry(
+        &mut self,
+        pointer: &str,
+    ) -> Result<(Slot<'a>, JsonValue<'a>), PointerError> {
+        let tokens = parse(pointer)?;
+        let (last, parents) = tokens.split_last().ok_or(PointerError::Root)?;
+        let parent = walk_mut(self, parents)?;
+        let index = position(parent, last)?;
+        match parent {
+            JsonValue::Object(object) => {
+                let KeyValue { key, value } = object.children.remove(index);
+                let key = Some(key);
+                Ok((Slot { index, key }, value))
+            }
+            JsonValue::Array(array) => {
+                let slot = Slot { index, key: None };
+                Ok((slot, array.array.remove(index)))
+            }
+            _ => unreachable!("position only succeeds on containers"),
+        }
+    }
+
+    /// Puts a value taken out by [`JsonValue::remove_entry`] back into the
+    /// container at `parent`.
+    pub(crate) fn restore_entry(
+        &mut self,
+        parent: &str,
+        slot: Slot<'a>,
+        value: JsonValue<'a>,
+    ) -> Result<(), PointerError> {
+        match (self.try_pointer_mut(parent)?, slot.key) {
+            (JsonValue::Object(object), Some(key)) => {
+                object.children.insert(slot.index, KeyValue { key, value })
+            }
+            (JsonValue::Array(array), None) => array.array.insert(slot.index, value),
+            _ => return Err(PointerError::NotAContainer(parent.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// Where a member or element was in its parent: its position, and for
+/// members the raw key.
+#[derive(Debug)]
+pub(crate) struct Slot<'a> {
+    index: usize,
+    key: Option<Cow<'a, str>>,
+}
+
+/// Splits off the last reference token, still escaped: `/a/b~1c` is `/a`
+/// and `b~1c`. `None` for the empty pointer.
+pub(crate) fn split_last(pointer: &str) -> Option<(&str, &str)> {
+    let slash = pointer.rfind('/')?;
+    Some((&pointer[..slash], &pointer[slash + 1..]))
+}
+
+#[cfg(test)]
+mod tests_pointer {
+    use super::*;
+    use crate::value::parse;
+
+    #[test]
+    fn test_rfc_examples() {
+        let document = parse(
+            r#"{"foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3,
+                "g|h": 4, "i\\j": 5, "k\"l": 6, " ": 7, "m~n": 8}"#,
+        );
+        let cases = [
+            ("", document.to_string()),
+            ("/foo", r#"["bar","baz"]"#.to_string()),
+            ("/foo/0", r#""bar""#.to_string()),
+            ("/", "0".to_string()),
+            ("/a~1b", "1".to_string()),
+            ("/c%d", "2".to_string()),
+            ("/e^f", "3".to_string()),
+            ("/g|h", "4".to_string()),
+            ("/i\\j", "5".to_string()),
+            ("/k\"l", "6".to_string()),
+            ("/ ", "7".to_string()),
+            ("/m~0n", "8".to_string()),
+        ];
+        for (pointer, expected) in cases {
+            assert_eq!(
+                document.pointer(pointer).unwrap().to_string(),
+                expected,
+                "{}",
+                pointer
+            );
+        }
+    }
+
+    #[test]
+    fn test_lookup_errors() {
+        let document = parse(r#"{"a": [1, {"b": true}], "n": 1}"#);
+        let error = |pointer| document.try_pointer(pointer).unwrap_err();
+        assert_eq!(error("a"), PointerError::Syntax("a".to_string()));
+        assert_eq!(error("/a~2"), PointerError::Syntax("/a~2".to_string()));
+        assert_eq!(error("/x/y"), PointerError::NotFound("/x".to_string()));
+        assert_eq!(error("/a/2"), PointerError::NotFound("/a/2".to_string()));
+        assert_eq!(error("/a/-"), PointerError::NotFound("/a/-".to_string()));
+        assert_eq!(
+            error("/a/01"),
+            PointerError::InvalidIndex("/a/01".to_string())
+        );
+        assert_eq!(
+            error("/a/+1"),
+            PointerError::InvalidIndex("/a/+1".to_string())
+        );
+        assert_eq!(
+            error("/n/0"),
+            PointerError::NotAContainer("/n/0".to_string())
+        );
+        assert_eq!(document.pointer("/a/1/b"), Some(&JsonValue::True));
+        assert_eq!(error("/a/1/c").to_string(), "no value at `/a/1/c`");
+    }
+
+    #[test]
+    fn test_mutation() {
+        let mut document = parse(r#"{"a": [1, 2], "b": {"c": null}}"#);
+        *document.pointer_mut("/b/c").unwrap() = JsonValue::True;
+        assert_eq!(document.insert_at("/a/0", JsonValue::Null), Ok(None));
+        assert_eq!(document.insert_at("/a/-", JsonValue::False), Ok(None));
+        assert_eq!(
+            document.insert_at("/b/c", JsonValue::Null),
+            Ok(Some(JsonValue::True))
+        );
+        assert_eq!(document.insert_at("/b/d~1e", parse("[]")), Ok(None));
+        assert_eq!(
+            document.to_string(),
+            r#"{"a":[null,1,2,false],"b":{"c":null,"d/e":[]}}"#
+        );
+        assert_eq!(document.remove_at("/a/1"), Ok(parse("1")));
+        assert_eq!(document.remove_at("/b/d~1e"), Ok(parse("[]")));
+        assert_eq!(
+            document.to_string(),
+            r#"{"a":[null,2,false],"b":{"c":null}}"#
+        );
+
+        assert_eq!(
+            document.insert_at("/a/4", JsonValue::Null),
+            Err(PointerError::NotFound("/a/4".to_string()))
+        );
+        assert_eq!(
+            document.insert_at("/x/y", JsonValue::Null),
+            Err(PointerError::NotFound("/x".to_string()))
+        );
+        assert_eq!(document.remove_at(""), Err(PointerError::Root));
+        assert_eq!(
+            document
+                .insert_at("", JsonValue::Null)
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            r#"{"a":[null,2,false],"b":{"c":null}}"#
+        );
+        assert_eq!(document, JsonValue::Null);
+    }
+
+    #[test]
+    fn test_escaped_keys() {
+        let mut document = parse(r#"{"A": 1, "tab\t": 2}"#);
+        assert_eq!(document.pointer("/A"), Some(&parse("1")));
+        document.insert_at("/new\n", JsonValue::Null).unwrap();
+        assert_eq!(document.pointer("/tab\t"), Some(&parse("2")));
+        assert_eq!(document.to_string(), r#"{"A":1,"tab\t":2,"new\n":null}"#);
+        assert_eq!(escape_token("a/b~c"), "a~1b~0c");
+    }
+}
@@ -0,0 +1,548 @@
+//! A push parser for documents that arrive in chunks.
+//!
+//! [`parse_to_tokens`](crate::token::parse_to_tokens) and
+//! [`Parser`](crate::parser::Parser) need the whole document up front.
+//! [`PushParser`] instead accepts successive byte chunks, keeps strings,
+//! numbers and literals that are cut by a chunk boundary, and builds the tree
+//! as tokens complete, so the raw input never has to be buffered.
+//!
+//! ```
+//! use rust_json::push::PushParser;
+//!
+//! let mut parser = PushParser::new();
+//! assert!(parser.feed(br#"{"name": "Ja"#).unwrap().is_empty());
+//! let values = parser.feed(br#"ck", "age": 39}"#).unwrap();
+//! assert_eq!(values[0].to_string(), r#"{"name":"Jack","age":39}"#);
+//! assert!(parser.finish().unwrap().is_empty());
+//! ```
+
+use crate::array::JsonArray;
+use crate::error::JsonParserError::{NestingTooDeep, UnexpectedEndOfTokens};
+use crate::error::{
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_key_or_right_brace, expect_value,
+    JsonError, JsonParserError,
+};
+use crate::keyvalue::KeyValue;
+use crate::object::JsonObject;
+use crate::parser::MAX_DEPTH;
+use crate::scan;
+use crate::token::JsonToken::*;
+use crate::token::TokenParseError::{
+    ControlCharacterInString, InvalidEscape, InvalidUtf8, KeyParseError, UnexpectedCharacter,
+};
+use crate::token::{check_string, parse_number, JsonToken, TokenParseError, BOM};
+use crate::value::JsonValue;
+use std::borrow::Cow;
+
+/// Parses a stream of top-level values fed in arbitrary chunks.
+#[derive(Debug, Default)]
+pub struct PushParser {
+    lexer: PushLexer,
+    builder: TreeBuilder,
+    tokens: Vec<JsonToken<'static>>,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser for a single document: as in [`Parser::parse`](crate::parser::Parser::parse),
+    /// any token after the first value is an error.
+    pub(crate) fn single_document() -> Self {
+        let mut parser = Self::default();
+        parser.builder.single = true;
+        parser
+    }
+
+    /// Feeds the next chunk and returns every top-level value it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<JsonValue<'static>>, JsonError<'static>> {
+        self.lexer.feed(chunk, &mut self.tokens)?;
+        self.build()
+    }
+
+    /// Ends the input, flushing a trailing number and failing if a value is
+    /// still incomplete.
+    pub fn finish(mut self) -> Result<Vec<JsonValue<'static>>, JsonError<'static>> {
+        self.lexer.finish(&mut self.tokens)?;
+        let values = self.build()?;
+        if self.builder.stack.is_empty() {
+            Ok(values)
+        } else {
+            Err(UnexpectedEndOfTokens)?
+        }
+    }
+
+    /// Whether no partial token or value is currently buffered.
+    pub fn is_idle(&self) -> bool {
+        self.lexer.state == LexState::Idle && self.builder.stack.is_empty()
+    }
+
+    fn build(&mut self) -> Result<Vec<JsonValue<'static>>, JsonError<'static>> {
+        let mut values = vec![];
+        for token in self.tokens.drain(..) {
+            self.builder.push(token, &mut values)?;
+        }
+        Ok(values)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum LexState {
+    #[default]
+    Idle,
+    /// Inside the byte order mark at the very start of the input.
+    Bom,
+    String {
+        escaped: bool,
+    },
+    Number,
+    Literal,
+}
+
+/// The tokenizer's rules as a state machine, with the bytes of the token in
+/// progress kept in `partial`. Complete tokens are checked by the same
+/// functions [`Tokenizer`](crate::token::Tokenizer) uses, so both accept
+/// exactly the same text.
+#[derive(Debug, Default)]
+struct PushLexer {
+    state: LexState,
+    partial: Vec<u8>,
+    /// Absolute offset of the next chunk.
+    offset: usize,
+    /// Absolute offset of the token in `partial`; for strings, of the
+    /// content after the opening quote.
+    partial_start: usize,
+}
+
+impl PushLexer {
+    fn feed(
+        &mut self,
+        chunk: &[u8],
+        out: &mut Vec<JsonToken<'static>>,
+    ) -> Result<(), TokenParseError> {
+        let mut index = 0;
+        while index < chunk.len() {
+            let byte = chunk[index];
+            match self.state {
+                LexState::Idle => {
+                    self.partial_start = self.offset + index;
+                    match byte {
+                        b'"' => {
+                            self.state = LexState::String { escaped: false };
+                            self.partial_start += 1;
+                        }
+                        b'0'..=b'9' | b'-' => {
+                            self.state = LexState::Number;
+                            self.partial.push(byte);
+                        }
+                        b't' | b'f' | b'n' => {
+                            self.state = LexState::Literal;
+                            self.partial.push(byte);
+                        }
+                        // A byte order mark is allowed in front of the document.
+                        0xEF if self.partial_start == 0 => {
+                            self.state = LexState::Bom;
+                            self.partial.push(byte);
+                        }
+                        b'{' => out.push(LeftBrace),
+                        b'}' => out.push(RightBrace),
+                        b'[' => out.push(LeftBracket),
+                        b']' => out.push(RightBracket),
+                        b',' => out.push(Comma),
+                        b':' => out.push(Colon),
+                        b' ' | b'\t' | b'\n' | b'\r' => {}
+                        _ => {
+                            return Err(UnexpectedCharacter {
+                                offset: self.partial_start,
+                            })
+                        }
+                    }
+                    index += 1;
+                }
+                LexState::Bom => {
+                    self.partial.push(byte);
+                    if !BOM.starts_with(&self.partial) {
+                        return Err(UnexpectedCharacter { offset: 0 });
+                    }
+                    if self.partial == BOM {
+                        self.partial.clear();
+                        self.state = LexState::Idle;
+                    }
+                    index += 1;
+                }
+                LexState::String { escaped: true } => {
+                    self.partial.push(byte);
+                    self.state = LexState::String { escaped: false };
+                    index += 1;
+                }
+                LexState::String { escaped: false } => {
+                    match scan::find_quote_or_backslash(chunk, index) {
+                        Some(at) if chunk[at] == b'"' => {
+                            self.partial.extend_from_slice(&chunk[index..at]);
+                            out.push(self.take_string()?);
+                            index = at + 1;
+                        }
+                        Some(at) => {
+                            self.partial.extend_from_slice(&chunk[index..=at]);
+                            self.state = LexState::String { escaped: true };
+                            index = at + 1;
+                        }
+                        None => {
+                            self.partial.extend_from_slice(&chunk[index..]);
+                            index = chunk.len();
+                        }
+                    }
+                }
+                LexState::Number => match byte {
+                    b'0'..=b'9' | b'+' | b'-' | b'.' | b'e' | b'E' => {
+                        self.partial.push(byte);
+                        index += 1;
+                    }
+                    // The delimiter is handled again from the idle state.
+                    _ => out.push(self.take_number()?),
+                },
+                LexState::Literal => {
+                    self.partial.push(byte);
+                    match literal(&self.partial) {
+                        Some(Some(token)) => {
+                            self.partial.clear();
+                            self.state = LexState::Idle;
+                            out.push(token);
+                        }
+                        Some(None) => {}
+                        None => {
+                            return Err(UnexpectedCharacter {
+                                offset: self.partial_start,
+                            })
+                        }
+                    }
+                    index += 1;
+                }
+            }
+        }
+        self.offset += chunk.len();
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<JsonToken<'static>>) -> Result<(), TokenParseError> {
+        match self.state {
+            LexState::Idle => Ok(()),
+            LexState::String { .. } => Err(KeyParseError),
+            LexState::Number => {
+                out.push(self.take_number()?);
+                Ok(())
+            }
+            LexState::Bom | LexState::Literal => Err(UnexpectedCharacter {
+                offset: self.partial_start,
+            }),
+        }
+    }
+
+    fn take_string(&mut self) -> Result<JsonToken<'static>, TokenParseError> {
+        self.state = LexState::Idle;
+        let partial = std::mem::take(&mut self.partial);
+        let start = self.partial_start;
+        check_string(&partial, 0, partial.len()).map_err(|e| match e {
+            ControlCharacterInString { offset } => ControlCharacterInString {
+                offset: start + offset,
+            },
+            InvalidEscape { offset } => InvalidEscape {
+                offset: start + offset,
+            },
+            e => e,
+        })?;
+        match std::string::String::from_utf8(partial) {
+            Ok(str) => Ok(JsonToken::String(Cow::Owned(str))),
+            Err(e) => Err(InvalidUtf8 {
+                offset: start + e.utf8_error().valid_up_to(),
+            }),
+        }
+    }
+
+    fn take_number(&mut self) -> Result<JsonToken<'static>, TokenParseError> {
+        self.state = LexState::Idle;
+        let partial = std::mem::take(&mut self.partial);
+        let (number, end) = parse_number(&partial, 0)?;
+        // Whatever follows a complete number, such as the `+` of `1+2`, can
+        // only be an error for the tokenizer as well.
+        if end < partial.len() {
+            return Err(UnexpectedCharacter {
+                offset: self.partial_start + end,
+            });
+        }
+        Ok(Number(number))
+    }
+}
+
+/// `Some(Some(token))` for a complete literal, `Some(None)` for a prefix of
+/// one, `None` otherwise.
+fn literal(partial: &[u8]) -> Option<Option<JsonToken<'static>>> {
+    [("true", True), ("false", False), ("null", Null)]
+        .into_iter()
+        .find(|(const_str, _)| const_str.as_bytes().starts_with(partial))
+        .map(|(const_str, token)| (const_str.len() == partial.len()).then_some(token))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Key,
+    KeyOrEnd,
+    Colon,
+    Value,
+    ValueOrEnd,
+    CommaOrEnd,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object {
+        object: JsonObject<'static>,
+        key: Option<Cow<'static, str>>,
+        expect: Expect,
+    },
+    Array {
+        array: JsonArray<'static>,
+        expect: Expect,
+    },
+}
+
+/// Assembles tokens into values, one open container per stack frame.
+#[derive(Debug, Default)]
+struct TreeBuilder {
+    stack: Vec<Frame>,
+    /// Only one top-level value is allowed.
+    single: bool,
+    /// A top-level value has been completed.
+    done: bool,
+}
+
+impl TreeBuilder {
+    fn push(
+        &mut self,
+        token: JsonToken<'static>,
+        out: &mut Vec<JsonValue<'static>>,
+    ) -> Result<(), JsonParserError<'static>> {
+        let Some(frame) = self.stack.last_mut() else {
+            if self.single && self.done {
+                return Err(expect_end_of_input(&token));
+            }
+            return self.value(token, out);
+        };
+        match frame {
+            Frame::Object { key, expect, .. } => match (*expect, token) {
+                (Expect::Key | Expect::KeyOrEnd, String(str)) => {
+                    *key = Some(str);
+                    *expect = Expect::Colon;
+                    Ok(())
+                }
+                (Expect::KeyOrEnd | Expect::CommaOrEnd, RightBrace) => self.close(out),
+                (Expect::Key | Expect::KeyOrEnd, token) => Err(expect_key_or_right_brace(&token)),
+                (Expect::Colon, Colon) => {
+                    *expect = Expect::Value;
+                    Ok(())
+                }
+                (Expect::Colon, token) => Err(expect_colon_after_key(&token)),
+                (Expect::CommaOrEnd, Comma) => {
+                    *expect = Expect::Key;
+                    Ok(())
+                }
+                (Expect::CommaOrEnd, token) => {
+                    Err(expect_a_comma_or_right_brace_after_value(&token))
+                }
+                (_, token) => self.value(token, out),
+            },
+            Frame::Array { expect, .. } => match (*expect, token) {
+                (Expect::ValueOrEnd | Expect::CommaOrEnd, RightBracket) => self.close(out),
+                (Expect::CommaOrEnd, Comma) => {
+                    *expect = Expect::Value;
+                    Ok(())
+                }
+                (Expect::CommaOrEnd, token) => {
+                    Err(expect_a_comma_or_right_bracket_after_value(&token))
+                }
+                (_, token) => self.value(token, out),
+            },
+        }
+    }
+
+    fn value(
+        &mut self,
+        token: JsonToken<'static>,
+        out: &mut Vec<JsonValue<'static>>,
+    ) -> Result<(), JsonParserError<'static>> {
+        let value = match token {
+            String(str) => JsonValue::String(Box::new(str)),
+            Number(num) => JsonValue::Number(Box::new(num)),
+            True => JsonValue::True,
+            False => JsonValue::False,
+            Null => JsonValue::Null,
+            LeftBrace | LeftBracket => return self.open(token),
+            token => return Err(expect_value(&token)),
+        };
+        self.attach(value, out);
+        Ok(())
+    }
+
+    fn open(&mut self, token: JsonToken<'static>) -> Result<(), JsonParserError<'static>> {
+        if self.stack.len() == MAX_DEPTH {
+            return Err(NestingTooDeep);
+        }
+        self.stack.push(match token {
+            LeftBrace => Frame::Object {
+                object: JsonObject { children: vec![] },
+                key: None,
+                expect: Expect::KeyOrEnd,
+            },
+            _ => Frame::Array {
+                array: JsonArray { array: vec![] },
+                expect: Expect::ValueOrEnd,
+            },
+        });
+        Ok(())
+    }
+
+    fn close(&mut self, out: &mut Vec<JsonValue<'static>>) -> Result<(), JsonParserError<'static>> {
+        let value = match self.stack.pop() {
+            Some(Frame::Object { object, .. }) => JsonValue::Object(Box::new(object)),
+            Some(Frame::Array { array, .. }) => JsonValue::Array(Box::new(array)),
+            None => return Err(UnexpectedEndOfTokens),
+        };
+        self.attach(value, out);
+        Ok(())
+    }
+
+    /// Hands a finished value to the enclosing container, or emits it if it
+    /// was top-level.
+    fn attach(&mut self, value: JsonValue<'static>, out: &mut Vec<JsonValue<'static>>) {
+        match self.stack.last_mut() {
+            None => {
+                self.done = true;
+                out.push(value);
+            }
+            Some(Frame::Object {
+                object,
+                key,
+                expect,
+            }) => {
+                let key = key.take().unwrap_or_default();
+                object.children.push(KeyValue { key, value });
+                *expect = Expect::CommaOrEnd;
+            }
+            Some(Frame::Array { array, expect }) => {
+                array.array.push(value);
+                *expect = Expect::CommaOrEnd;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_push_parser {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::token::{parse_bytes_to_tokens, parse_to_tokens};
+
+    #[test]
+    fn test_every_split_matches_whole_parse() {
+        let json = r#"{"name": "Jack (\"Bee\") Nimble", "format": {"width": -1920.5,
+            "interlace": false, "none": null, "ok": true, "array": [1,2,[3]], "é": "ü"}}"#;
+        let tokens = parse_to_tokens(json).unwrap();
+        let expected = Parser::new(&tokens).parse().unwrap().to_string();
+        let bytes = json.as_bytes();
+        for split in 0..=bytes.len() {
+            let mut parser = PushParser::new();
+            let mut values = parser.feed(&bytes[..split]).unwrap();
+            values.extend(parser.feed(&bytes[split..]).unwrap());
+            values.extend(parser.finish().unwrap());
+            assert_eq!(values.len(), 1, "split at {}", split);
+            assert_eq!(values[0].to_string(), expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_byte_at_a_time_emits_each_document() {
+        let mut parser = PushParser::new();
+        let mut values = vec![];
+        for byte in br#"[1, 2] {"a": []} [true]"# {
+            values.extend(parser.feed(&[*byte]).unwrap());
+        }
+        assert!(parser.is_idle());
+        let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(values, vec!["[1,2]", r#"{"a":[]}"#, "[true]"]);
+    }
+
+    #[test]
+    fn test_scalars_and_exponents() {
+        let mut parser = PushParser::new();
+        let mut values = vec![];
+        for byte in b"\xEF\xBB\xBF1e3 \"a\" -0.5E-1 true[2e+2]null 7" {
+            values.extend(parser.feed(&[*byte]).unwrap());
+        }
+        values.extend(parser.finish().unwrap());
+        let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(
+            values,
+            vec!["1000", r#""a""#, "-0.05", "true", "[200]", "null", "7"]
+        );
+    }
+
+    /// Whatever the tokenizer rejects, the lexer rejects too.
+    #[test]
+    fn test_grammar_matches_tokenizer() {
+        let cases: [&[u8]; 14] = [
+            b"[+1]",
+            b"[01]",
+            b"[1.]",
+            b"[.5]",
+            b"[1e]",
+            b"[1.2.3]",
+            b"[-]",
+            b"[\"\t\"]",
+            br#"["\q"]"#,
+            br#"["\u12"]"#,
+            b"[tru]",
+            b"[nul",
+            b"[x]",
+            b"\xEF\xBB[]",
+        ];
+        for json in cases {
+            let text = std::string::String::from_utf8_lossy(json);
+            assert!(parse_bytes_to_tokens(json).is_err(), "{}", text);
+            let mut parser = PushParser::new();
+            let result = parser.feed(json).and_then(|_| parser.finish());
+            assert!(result.is_err(), "{}", text);
+        }
+
+        let mut parser = PushParser::new();
+        parser.feed(b"[\"ab").unwrap();
+        assert_eq!(
+            parser.feed(b"\\q\"]").unwrap_err(),
+            JsonError::Token(InvalidEscape { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut parser = PushParser::new();
+        assert_eq!(
+            parser.feed(br#"{"a" 1}"#).unwrap_err(),
+            JsonError::Parse(expect_colon_after_key(&Number(1.0)))
+        );
+
+        let mut parser = PushParser::new();
+        parser.feed(b"[\"a\xFF").unwrap();
+        assert_eq!(
+            parser.feed(b"\"]").unwrap_err(),
+            JsonError::Token(InvalidUtf8 { offset: 3 })
+        );
+
+        let mut parser = PushParser::new();
+        parser.feed(br#"{"a": [1"#).unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err(),
+            JsonError::Parse(UnexpectedEndOfTokens)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Parsing straight from an [`io::Read`] such as a file or stdin.
+
+use crate::error::JsonError;
+use crate::error::JsonParserError::UnexpectedEndOfTokens;
+use crate::push::PushParser;
+use crate::value::JsonValue;
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind, Read};
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Why [`from_reader`] failed: the reader itself, or the text it produced.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Syntax(JsonError<'static>),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "read failed: {}", e),
+            ReadError::Syntax(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Syntax(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl From<JsonError<'static>> for ReadError {
+    fn from(e: JsonError<'static>) -> Self {
+        ReadError::Syntax(e)
+    }
+}
+
+/// Reads and parses one document.
+///
+/// The input is fed through a [`PushParser`] in fixed-size chunks, so it is
+/// never held in memory as a whole. Like [`Parser::parse`](crate::parser::Parser::parse),
+/// any value may stand at the top level, and nothing but whitespace may
+/// follow it, so the reader is read to the end.
+pub fn from_reader<R: Read>(mut reader: R) -> Result<JsonValue<'static>, ReadError> {
+    let mut parser = PushParser::single_document();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut values = vec![];
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        values.extend(parser.feed(&buffer[..len])?);
+    }
+    values.extend(parser.finish()?);
+    match values.pop() {
+        Some(value) => Ok(value),
+        None => Err(JsonError::Parse(UnexpectedEndOfTokens).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests_from_reader {
+    use super::*;
+    use crate::error::expect_end_of_input;
+    use crate::token::JsonToken::LeftBracket;
+    use crate::token::TokenParseError::KeyParseError;
+
+    /// Hands out its input a few bytes at a time, then fails if asked to.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        fail_at_end: bool,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.input.is_empty() && self.fail_at_end {
+                return Err(io::Error::new(ErrorKind::BrokenPipe, "gone"));
+            }
+            let len = buf.len().min(self.input.len()).min(3);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let reader = Trickle {
+            input: br#"{"name": "Jack", "list": [1, 2]}"#,
+            fail_at_end: false,
+        };
+        let value = from_reader(reader).unwrap();
+        assert_eq!(value.to_string(), r#"{"name":"Jack","list":[1,2]}"#);
+    }
+
+    #[test]
+    fn test_scalar_roots_and_trailing_content() {
+        let value = from_reader(b" 12.5e1 ".as_slice()).unwrap();
+        assert_eq!(value.to_string(), "125");
+        let error = from_reader(b"[1] [2]".as_slice()).unwrap_err();
+        assert!(matches!(
+            error,
+            ReadError::Syntax(JsonError::Parse(e)) if e == expect_end_of_input(&LeftBracket)
+        ));
+        assert!(from_reader(b"1 x".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_io_and_syntax_errors_are_distinct() {
+        let reader = Trickle {
+            input: br#"{"name": "Ja"#,
+            fail_at_end: true,
+        };
+        let error = from_reader(reader).unwrap_err();
+        assert!(matches!(error, ReadError::Io(e) if e.kind() == ErrorKind::BrokenPipe));
+
+        let reader = Trickle {
+            input: br#"{"name": "Ja"#,
+            fail_at_end: false,
+        };
+        let error = from_reader(reader).unwrap_err();
+        assert!(matches!(
+            error,
+            ReadError::Syntax(JsonError::Token(KeyParseError))
+        ));
+    }
+
+    #[test]
+    fn test_errors_display_and_have_a_source() {
+        use std::error::Error;
+
+        let reader = Trickle {
+            input: b"[",
+            fail_at_end: true,
+        };
+        let error = from_reader(reader).unwrap_err();
+        assert_eq!(error.to_string(), "read failed: gone");
+        assert_eq!(error.source().unwrap().to_string(), "gone");
+
+        let error = from_reader(b"[1] [2]".as_slice()).unwrap_err();
+        assert_eq!(error.to_string(), "expected end of input, found `[`");
+        assert_eq!(error.source().unwrap().to_string(), error.to_string());
+    }
+}
@@ -0,0 +1,572 @@
+//! A parser that keeps going after syntax errors, for editors.
+//!
+//! [`Recovered::parse`] never gives up on the first problem. Bad tokens
+//! stand in for the value or key they were meant to be, without adding it
+//! to the tree; a missing colon or comma is assumed where it was obviously meant;
+//! anything else is skipped up to the next comma or closing bracket, where
+//! parsing resynchronizes. The result is the best-effort [`SpannedValue`]
+//! tree together with one [`Diagnostic`] per problem found.
+//!
+//! ```
+//! use rust_json::recover::Recovered;
+//!
+//! let text = r#"{"a": 1 "b": [2,, 3], "c": }"#;
+//! let recovered = Recovered::parse(text);
+//! assert_eq!(recovered.diagnostics.len(), 3);
+//! let value = recovered.value.unwrap().into_value();
+//! assert_eq!(value.to_string(), r#"{"a":1,"b":[2,3]}"#);
+//! ```
+
+use crate::error::JsonParserError::{NestingTooDeep, Unclosed, UnexpectedEndOfTokens};
+use crate::error::{
+    expect_a_comma_or_right_brace_after_value, expect_a_comma_or_right_bracket_after_value,
+    expect_colon_after_key, expect_end_of_input, expect_key_or_right_brace, expect_value,
+    JsonError, JsonParserError,
+};
+use crate::parser::MAX_DEPTH;
+use crate::span::{Span, SpannedKind, SpannedMember, SpannedValue};
+use crate::token::JsonToken::{
+    Colon, Comma, False, LeftBrace, LeftBracket, Null, Number, RightBrace, RightBracket, True,
+};
+use crate::token::{JsonToken, Tokenizer};
+use crate::value::JsonValue;
+
+/// One problem in the input and the bytes it was found at. Problems at the
+/// end of the input have an empty span there.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic<'a> {
+    pub span: Span,
+    pub error: JsonError<'a>,
+    /// The last token before the problem, if there is one. Tells a
+    /// trailing comma from a value missing after a colon.
+    pub previous: Option<JsonToken<'a>>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn into_owned(self) -> Diagnostic<'static> {
+        Diagnostic {
+            span: self.span,
+            error: self.error.into_owned(),
+            previous: self.previous.map(JsonToken::into_owned),
+        }
+    }
+}
+
+/// What could be made of a document, and everything wrong with it.
+#[derive(Debug, PartialEq)]
+pub struct Recovered<'a> {
+    /// `None` only if the input holds no value at all.
+    pub value: Option<SpannedValue<'a>>,
+    /// In the order they appear in the input.
+    pub diagnostics: Vec<Diagnostic<'a>>,
+}
+
+impl<'a> Recovered<'a> {
+    pub fn parse(origin: &'a str) -> Recovered<'a> {
+        Self::from_tokenizer(Tokenizer::new(origin))
+    }
+
+    /// Like [`Recovered::parse`], reading from an already configured
+    /// tokenizer, e.g. one that accepts comments.
+    pub fn from_tokenizer(tokens: Tokenizer<'a>) -> Recovered<'a> {
+        let mut parser = RecoveringParser::new(tokens);
+        let value = parser.document();
+        parser
+            .diagnostics
+            .sort_by_key(|diagnostic| diagnostic.span.start);
+        Recovered {
+            value,
+            diagnostics: parser.diagnostics,
+        }
+    }
+
+    /// Whether the input parsed without a single problem.
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty() && self.value.is_some()
+    }
+}
+
+type Spanned<'a> = (Span, JsonToken<'a>);
+
+struct RecoveringParser<'a> {
+    tokens: Vec<Spanned<'a>>,
+    /// Indices into `tokens` of the placeholders for tokens that failed to
+    /// read, in order.
+    placeholders: Vec<usize>,
+    pos: usize,
+    /// Where the input ends, for problems found there.
+    end: usize,
+    /// How many containers enclose the current position.
+    depth: usize,
+    diagnostics: Vec<Diagnostic<'a>>,
+    /// Where the last problem was reported. A closing bracket that does not
+    /// belong is seen by every enclosing container on the way out, and
+    /// should be reported only once.
+    last_reported: Option<Span>,
+}
+
+impl<'a> RecoveringParser<'a> {
+    fn new(mut tokenizer: Tokenizer<'a>) -> Self {
+        let mut tokens = vec![];
+        let mut placeholders = vec![];
+        let mut diagnostics = vec![];
+        while let Some(token) = tokenizer.next_recovering() {
+            match token {
+                Ok(token) => tokens.push(token),
+                Err((span, error)) => {
+                    diagnostics.push(Diagnostic {
+                        span,
+                        error: error.into(),
+                        previous: tokens.last().map(|(_, token): &Spanned| token.clone()),
+                    });
+                    // Most bad tokens are a mistyped value, so one takes its
+                    // place rather than causing a second error at the next
+                    // comma.
+                    placeholders.push(tokens.len());
+                    tokens.push((span, Null));
+                }
+            }
+        }
+        Self {
+            tokens,
+            placeholders,
+            pos: 0,
+            end: tokenizer.offset(),
+            depth: 0,
+            diagnostics,
+            last_reported: None,
+        }
+    }
+
+    fn peek(&self) -> Option<&Spanned<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Whether the next token stands in for one that failed to read, and was
+    /// reported already.
+    fn at_placeholder(&self) -> bool {
+        self.placeholders.binary_search(&self.pos).is_ok()
+    }
+
+    fn peek_is(&self, offset: usize, token: &JsonToken) -> bool {
+        self.tokens
+            .get(self.pos + offset)
+            .is_some_and(|(_, next)| next == token)
+    }
+
+    /// End of the last token consumed.
+    fn last_end(&self) -> usize {
+        self.tokens
+            .get(self.pos.wrapping_sub(1))
+            .map_or(0, |(span, _)| span.end)
+    }
+
+    /// Records a problem, unless one was just reported at the same span.
+    fn report(&mut self, span: Span, error: impl Into<JsonError<'a>>) {
+        if self.last_reported == Some(span) {
+            return;
+        }
+        self.last_reported = Some(span);
+        self.push(span, error);
+    }
+
+    fn push(&mut self, span: Span, error: impl Into<JsonError<'a>>) {
+        let before = self
+            .tokens
+            .partition_point(|(token, _)| token.end <= span.start);
+        self.diagnostics.push(Diagnostic {
+            span,
+            error: error.into(),
+            previous: before
+                .checked_sub(1)
+                .map(|index| self.tokens[index].1.clone()),
+        });
+    }
+
+    /// Each container left open at the end is a problem of its own, so these
+    /// are all kept.
+    fn report_end(&mut self) {
+        self.push(Span::new(self.end, self.end), UnexpectedEndOfTokens);
+    }
+
+    /// Reports the closing bracket at `span`, which does not match the
+    /// `bracket` at `open`, and that `bracket` as never closed. The closing
+    /// bracket is left to the enclosing containers, which do not report it
+    /// again.
+    fn report_mismatch(
+        &mut self,
+        (open, bracket): Spanned<'a>,
+        span: Span,
+        error: JsonParserError<'a>,
+    ) {
+        self.report(span, error);
+        self.push(open, Unclosed(bracket));
+    }
+
+    fn document(&mut self) -> Option<SpannedValue<'a>> {
+        let mut value = None;
+        let mut placeholder = false;
+        while self.peek().is_some() {
+            placeholder |= self.at_placeholder();
+            let pos = self.pos;
+            if let Some(parsed) = self.value() {
+                value = Some(parsed);
+                break;
+            }
+            // A stray closing bracket or comma before any value.
+            if self.pos == pos {
+                self.pos += 1;
+            }
+        }
+        // Bad tokens after the value were reported already.
+        while self.at_placeholder() {
+            self.pos += 1;
+        }
+        if let Some((span, token)) = self.peek().cloned() {
+            self.report(span, expect_end_of_input(&token));
+            self.pos = self.tokens.len();
+        } else if value.is_none() && !placeholder {
+            self.report_end();
+        }
+        value
+    }
+
+    /// Parses a value. Reports and returns `None` at a token that cannot
+    /// start one, which is consumed unless it is a separator or closing
+    /// bracket the caller can resynchronize at. A placeholder is consumed
+    /// without a report.
+    fn value(&mut self) -> Option<SpannedValue<'a>> {
+        let Some((span, token)) = self.peek().cloned() else {
+            self.report_end();
+            return None;
+        };
+        if self.at_placeholder() {
+            self.pos += 1;
+            return None;
+        }
+        let scalar = match token {
+            // Skipped whole, so that the rest of the document still parses.
+            LeftBrace | LeftBracket if self.depth == MAX_DEPTH => {
+                self.report(span, NestingTooDeep);
+                self.synchronize();
+                return None;
+            }
+            LeftBrace | LeftBracket => {
+                self.depth += 1;
+                let value = match token {
+                    LeftBrace => self.object(),
+                    _ => self.array(),
+                };
+                self.depth -= 1;
+                return Some(value);
+            }
+            JsonToken::String(str) => JsonValue::String(Box::new(str)),
+            Number(num) => JsonValue::Number(Box::new(num)),
+            True => JsonValue::True,
+            False => JsonValue::False,
+            Null => JsonValue::Null,
+            token => {
+                self.report(span, expect_value(&token));
+                if !matches!(token, Comma | RightBrace | RightBracket) {
+                    self.pos += 1;
+                }
+                return None;
+            }
+        };
+        self.pos += 1;
+        Some(SpannedValue {
+            span,
+            kind: SpannedKind::Scalar(scalar),
+        })
+    }
+
+    /// Skips to the next comma or closing bracket that is not nested inside
+    /// the skipped tokens, without consuming it.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        while let Some((_, token)) = self.peek() {
+            match token {
+                LeftBrace | LeftBracket => depth += 1,
+                RightBrace | RightBracket if depth > 0 => depth -= 1,
+                Comma | RightBrace | RightBracket => return,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn object(&mut self) -> SpannedValue<'a> {
+        let open = self.tokens[self.pos].0;
+        self.pos += 1;
+        let mut members = vec![];
+        let mut after_comma = false;
+        loop {
+            let Some((span, token)) = self.peek().cloned() else {
+                self.report_end();
+                break;
+            };
+            let key = match token {
+                // A key that failed to read: its value is parsed, but the
+                // member is left out.
+                _ if self.at_placeholder() => {
+                    self.pos += 1;
+                    None
+                }
+                RightBrace => {
+                    if after_comma {
+                        self.report(span, expect_key_or_right_brace(&token));
+                    }
+                    self.pos += 1;
+                    break;
+                }
+                // The bracket closes something further out; leave it to
+                // the parent.
+                RightBracket => {
+                    let error = expect_a_comma_or_right_brace_after_value(&token);
+                    self.report_mismatch((open, LeftBrace), span, error);
+                    break;
+                }
+                JsonToken::String(key) => {
+                    self.pos += 1;
+                    Some(key)
+                }
+                token => {
+                    self.report(span, expect_key_or_right_brace(&token));
+                    self.synchronize();
+                    after_comma = self.eat_comma();
+                    continue;
+                }
+            };
+            match self.peek().cloned() {
+                Some((_, Colon)) => self.pos += 1,
+                // `{"a" ]`: the object is cut short, not missing a colon.
+                Some((_, RightBracket)) => continue,
+                Some((span, token)) => self.report(span, expect_colon_after_key(&token)),
+                None => {}
+            }
+            if let (Some(key), Some(value)) = (key, self.value()) {
+                members.push(SpannedMember {
+                    key,
+                    key_span: span,
+                    value,
+                });
+            }
+            match self.peek().cloned() {
+                Some((_, Comma)) => {}
+                Some((_, RightBrace)) | None => {}
+                Some(_) if self.at_placeholder() => self.synchronize(),
+                // `"a": 1 "b": 2` is missing a comma, not a broken value.
+                Some((span, token @ JsonToken::String(_))) if self.peek_is(1, &Colon) => {
+                    self.report(span, expect_a_comma_or_right_brace_after_value(&token));
+                    after_comma = false;
+                    continue;
+                }
+                Some((span, token)) => {
+                    self.report(span, expect_a_comma_or_right_brace_after_value(&token));
+                    self.synchronize();
+                }
+            }
+            after_comma = self.eat_comma();
+        }
+        SpannedValue {
+            span: Span::new(open.start, self.last_end()),
+            kind: SpannedKind::Object(members),
+        }
+    }
+
+    fn array(&mut self) -> SpannedValue<'a> {
+        let open = self.tokens[self.pos].0;
+        self.pos += 1;
+        let mut elements = vec![];
+        let mut after_comma = false;
+        loop {
+            let Some((span, token)) = self.peek().cloned() else {
+                self.report_end();
+                break;
+            };
+            match token {
+                RightBracket => {
+                    if after_comma {
+                        self.report(span, expect_value(&token));
+                    }
+                    self.pos += 1;
+                    break;
+                }
+                RightBrace => {
+                    let error = expect_a_comma_or_right_bracket_after_value(&token);
+                    self.report_mismatch((open, LeftBracket), span, error);
+                    break;
+                }
+                _ => {}
+            }
+            if let Some(value) = self.value() {
+                elements.push(value);
+            }
+            match self.peek().cloned() {
+                Some((_, Comma | RightBracket)) | None => {}
+                // A bad token right after a value, reported already.
+                Some(_) if self.at_placeholder() => {
+                    after_comma = false;
+                    continue;
+                }
+                // `[1 2]` is missing a comma between two values.
+                Some((span, token @ (JsonToken::String(_) | Number(_) | True | False | Null)))
+                | Some((span, token @ (LeftBrace | LeftBracket))) => {
+                    self.report(span, expect_a_comma_or_right_bracket_after_value(&token));
+                    after_comma = false;
+                    continue;
+                }
+                Some((span, token)) => {
+                    self.report(span, expect_a_comma_or_right_bracket_after_value(&token));
+                    self.synchronize();
+                }
+            }
+            after_comma = self.eat_comma();
+        }
+        SpannedValue {
+            span: Span::new(open.start, self.last_end()),
+            kind: SpannedKind::Array(elements),
+        }
+    }
+
+    fn eat_comma(&mut self) -> bool {
+        let comma = self.peek_is(0, &Comma);
+        if comma {
+            self.pos += 1;
+        }
+        comma
+    }
+}
+
+#[cfg(test)]
+mod tests_recover {
+    use super::*;
+    use crate::token::TokenParseError;
+
+    fn errors(recovered: &Recovered, text: &str) -> Vec<(String, &'static str)> {
+        recovered
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let message = match &diagnostic.error {
+                    JsonError::Parse(crate::error::JsonParserError::UnexpectedToken(e)) => e.expect,
+                    JsonError::Parse(crate::error::JsonParserError::Unclosed(_)) => "unclosed",
+                    JsonError::Parse(_) => "end",
+                    JsonError::Token(_) => "token",
+                };
+                (text[diagnostic.span.range()].to_string(), message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_input_has_no_diagnostics() {
+        let text = r#"{"a": [1, {"b": null}], "c": "d"}"#;
+        let recovered = Recovered::parse(text);
+        assert!(recovered.is_ok());
+        assert_eq!(recovered.value.unwrap(), SpannedValue::parse(text).unwrap());
+    }
+
+    #[test]
+    fn test_resynchronizes_and_reports_everything() {
+        let text = r#"{"a": 1 "b": [2,, 3 4], "c" 5, "d": }, "e": {"f": ] } "#;
+        let recovered = Recovered::parse(text);
+        assert_eq!(
+            errors(&recovered, text),
+            vec![
+                ("\"b\"".to_string(), ", or }"),
+                (",".to_string(), "string, number, true, false, null, {, ["),
+                ("4".to_string(), ", or ]"),
+                ("5".to_string(), ":"),
+                ("}".to_string(), "string, number, true, false, null, {, ["),
+                (",".to_string(), "end of input"),
+            ]
+        );
+        let value = recovered.value.unwrap();
+        assert_eq!(
+            &text[value.span.range()],
+            &text[..text.find("},").unwrap() + 1]
+        );
+        assert_eq!(
+            value.into_value().to_string(),
+            r#"{"a":1,"b":[2,3,4],"c":5}"#
+        );
+    }
+
+    #[test]
+    fn test_bad_tokens_and_unclosed_containers() {
+        let text = "[1, 1.2.3, {\"a\": tru, \"b\": 2";
+        let recovered = Recovered::parse(text);
+        assert!(matches!(
+            recovered.diagnostics[0].error,
+            JsonError::Token(TokenParseError::NumberParseError(_))
+        ));
+        assert_eq!(
+            errors(&recovered, text),
+            vec![
+                ("1.2.3".to_string(), "token"),
+                ("tru".to_string(), "token"),
+                ("".to_string(), "end"),
+                ("".to_string(), "end"),
+            ]
+        );
+        let value = recovered.value.unwrap();
+        assert_eq!(value.span, Span::new(0, text.len()));
+        assert_eq!(value.into_value().to_string(), r#"[1,{"b":2}]"#);
+
+        let recovered = Recovered::parse("  ");
+        assert_eq!(recovered.value, None);
+        assert_eq!(recovered.diagnostics[0].span, Span::new(2, 2));
+    }
+
+    #[test]
+    fn test_bad_tokens_stand_in_for_values_and_keys() {
+        let text = r#"[1.2.3, 2, {"\q": 3, "b": 4} tru]"#;
+        let recovered = Recovered::parse(text);
+        assert_eq!(
+            errors(&recovered, text),
+            vec![
+                ("1.2.3".to_string(), "token"),
+                (r#""\q""#.to_string(), "token"),
+                ("tru".to_string(), "token"),
+            ]
+        );
+        let value = recovered.value.unwrap().into_value();
+        assert_eq!(value.to_string(), r#"[2,{"b":4}]"#);
+
+        let recovered = Recovered::parse("tru");
+        assert_eq!(recovered.diagnostics.len(), 1);
+        assert_eq!(recovered.value, None);
+        let recovered = Recovered::parse("1 x");
+        assert_eq!(recovered.diagnostics.len(), 1);
+        assert!(recovered.value.is_some());
+    }
+
+    #[test]
+    fn test_stray_bracket_is_reported_once() {
+        // The bracket once, and the container it was found in as unclosed.
+        for (text, open, close, expect) in [
+            (r#"{"a" ]"#, "{", "]", ", or }"),
+            (r#"{"a": 1 ]"#, "{", "]", ", or }"),
+            ("[1 }", "[", "}", ", or ]"),
+            (r#"[{"a": 1 ]"#, "{", "]", ", or }"),
+            (r#"{"a": [1 }"#, "[", "}", ", or ]"),
+        ] {
+            let recovered = Recovered::parse(text);
+            assert_eq!(
+                errors(&recovered, text),
+                vec![(open.to_string(), "unclosed"), (close.to_string(), expect)],
+                "{text}"
+            );
+            let opened = text.rfind(open).unwrap();
+            assert_eq!(recovered.diagnostics[0].span, Span::new(opened, opened + 1));
+            assert_eq!(
+                recovered.diagnostics[0].error.to_string(),
+                format!("unclosed `{}`", open)
+            );
+        }
+
+        let value = Recovered::parse(r#"{"a" ]"#).value.unwrap();
+        assert_eq!(value.span, Span::new(0, 4));
+    }
+}
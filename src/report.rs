@@ -0,0 +1,272 @@
+//! Renders [`Diagnostic`]s for people: the offending line of the input with
+//! a caret under the bad token, what was expected instead, and a hint at the
+//! likely fix.
+//!
+//! ```
+//! use rust_json::recover::Recovered;
+//!
+//! let text = "{\n  \"a\": 1\n  \"b\": 2\n}";
+//! let recovered = Recovered::parse(text);
+//! let report = recovered.diagnostics[0].report(text).path("config.json");
+//! assert_eq!(
+//!     report.to_string(),
+//!     "error: expected `,` or `}`, found `\"b\"`\n \
+//!       --> config.json:3:3\n  \
+//!        |\n\
+//!      3 |   \"b\": 2\n  \
+//!        |   ^^^\n  \
+//!        = help: did you forget a comma?\n"
+//! );
+//! ```
+
+use crate::error::JsonParserError::{UnexpectedEndOfTokens, UnexpectedToken};
+use crate::error::{Expected, JsonError};
+use crate::recover::Diagnostic;
+use crate::span::LineIndex;
+use crate::token::JsonToken::*;
+use crate::token::TokenParseError;
+use std::fmt::{Display, Formatter};
+
+/// Whether the word `text` starts with is `true`, `false` or `null` cut
+/// short or in the wrong case.
+fn is_literal_typo(text: &str) -> bool {
+    let end = text
+        .find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(text.len());
+    let word = text[..end].to_ascii_lowercase();
+    ["true", "false", "null"]
+        .iter()
+        .any(|literal| literal.starts_with(&word))
+}
+
+impl<'a> Diagnostic<'a> {
+    /// A likely fix for the problem, if one can be guessed. `source` is the
+    /// input the diagnostic was found in.
+    pub fn hint(&self, source: &str) -> Option<&'static str> {
+        let e = match &self.error {
+            JsonError::Parse(UnexpectedToken(e)) => e,
+            JsonError::Parse(UnexpectedEndOfTokens) => {
+                return Some("the input ends early; is a closing `}` or `]` missing?")
+            }
+            JsonError::Parse(_) => return None,
+            JsonError::Token(TokenParseError::KeyParseError) => {
+                return Some("add a `\"` to close the string")
+            }
+            JsonError::Token(TokenParseError::UnterminatedComment) => {
+                return Some("add `*/` to close the comment")
+            }
+            JsonError::Token(TokenParseError::InvalidUtf8 { .. }) => {
+                return Some("the input is not UTF-8; check the file's encoding")
+            }
+            JsonError::Token(TokenParseError::UnexpectedCharacter { offset }) => {
+                let rest = source.get(*offset..)?;
+                return match rest.chars().next()? {
+                    // Other quotes, or a string or key with no quotes at all.
+                    '\'' | '`' | '\u{2018}' | '\u{2019}' | '\u{201C}' | '\u{201D}' => {
+                        Some("strings and keys must be in double quotes")
+                    }
+                    // `tru`, `True`: a mistyped literal.
+                    char if char.is_alphabetic() && is_literal_typo(rest) => {
+                        Some("literals are lowercase `true`, `false` and `null`")
+                    }
+                    char if char.is_alphabetic() => {
+                        Some("strings and keys must be in double quotes")
+                    }
+                    '+' | '.' | '0'..='9' => Some(
+                        "numbers are written like `1`, `-0.5` or `2e10`, without a leading `+` or `.`",
+                    ),
+                    _ => None,
+                };
+            }
+            JsonError::Token(TokenParseError::ControlCharacterInString { .. }) => {
+                return Some("escape it, e.g. `\\n` for a line break or `\\t` for a tab")
+            }
+            JsonError::Token(TokenParseError::InvalidEscape { .. }) => return Some(
+                "the escapes are `\\\"` `\\\\` `\\/` `\\b` `\\f` `\\n` `\\r` `\\t` and `\\uXXXX`",
+            ),
+            JsonError::Token(TokenParseError::NumberParseError(_)) => return None,
+        };
+        Some(match (e.kind, &e.actual, &self.previous) {
+            (
+                Expected::CommaOrRightBrace | Expected::CommaOrRightBracket,
+                String(_) | Number(_) | True | False | Null | LeftBrace | LeftBracket,
+                _,
+            ) => "did you forget a comma?",
+            (Expected::CommaOrRightBrace, RightBracket, _) => {
+                "this `]` has no matching `[`; is a `}` missing?"
+            }
+            (Expected::CommaOrRightBracket, RightBrace, _) => {
+                "this `}` has no matching `{`; is a `]` missing?"
+            }
+            (Expected::Colon, _, _) => "did you forget a `:` after the key?",
+            (Expected::KeyOrRightBrace, RightBrace, Some(Comma))
+            | (Expected::Value, RightBracket, Some(Comma)) => {
+                "JSON does not allow trailing commas; remove the last `,`"
+            }
+            (Expected::KeyOrRightBrace, _, _) => "object keys must be strings in double quotes",
+            (Expected::EndOfInput, _, _) => {
+                "a document holds a single value; wrap several in an array"
+            }
+            (Expected::Value, Comma, _) => "there is one `,` too many",
+            (Expected::Value, RightBrace | RightBracket, Some(Colon)) => {
+                "a value is missing after the `:`"
+            }
+            _ => return None,
+        })
+    }
+
+    /// Renders the diagnostic against the `source` it was found in.
+    pub fn report<'r>(&'r self, source: &'r str) -> Report<'r, 'a> {
+        Report {
+            diagnostic: self,
+            source,
+            path: None,
+        }
+    }
+}
+
+/// A [`Diagnostic`] ready to be printed, see [`Diagnostic::report`].
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'r, 'a> {
+    diagnostic: &'r Diagnostic<'a>,
+    source: &'r str,
+    path: Option<&'r str>,
+}
+
+impl<'r, 'a> Report<'r, 'a> {
+    /// Names the file in the location line.
+    pub fn path(self, path: &'r str) -> Self {
+        Self {
+            path: Some(path),
+            ..self
+        }
+    }
+}
+
+impl<'r, 'a> Display for Report<'r, 'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let span = self.diagnostic.span;
+        let index = LineIndex::new(self.source);
+        let location = index.location(span.start);
+        let text = index.line(location.line).unwrap_or_default();
+        let gutter = " ".repeat(location.line.to_string().len());
+
+        writeln!(f, "error: {}", self.diagnostic.error)?;
+        match self.path {
+            Some(path) => writeln!(
+                f,
+                "{}--> {}:{}:{}",
+                gutter, path, location.line, location.column
+            )?,
+            None => writeln!(f, "{}--> {}:{}", gutter, location.line, location.column)?,
+        }
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", location.line, text)?;
+
+        // Keep tabs so the caret lines up under them.
+        let padding: std::string::String = text
+            .chars()
+            .take(location.column - 1)
+            .map(|char| if char == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = index.location(span.end);
+        let width = if end.line == location.line {
+            end.column - location.column
+        } else {
+            text.chars().count() + 1 - location.column
+        };
+        writeln!(f, "{} | {}{}", gutter, padding, "^".repeat(width.max(1)))?;
+        if let Some(hint) = self.diagnostic.hint(self.source) {
+            writeln!(f, "{} = help: {}", gutter, hint)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_report {
+    use crate::recover::Recovered;
+
+    fn render(text: &str) -> Vec<std::string::String> {
+        let recovered = Recovered::parse(text);
+        recovered
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.report(text).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_hints() {
+        let reports = render("{\"a\": [1, 2,], \"b\" true, 3: 4}");
+        assert_eq!(reports.len(), 3);
+        assert!(reports[0]
+            .ends_with("= help: JSON does not allow trailing commas; remove the last `,`\n"));
+        assert!(reports[1].starts_with("error: expected `:`, found `true`\n"));
+        assert!(reports[1].ends_with("= help: did you forget a `:` after the key?\n"));
+        assert!(reports[2].ends_with("= help: object keys must be strings in double quotes\n"));
+
+        for text in ["{\"a\": }", "{\"a\": ]"] {
+            let reports = render(text);
+            let last = reports.last().unwrap();
+            assert!(last.ends_with("= help: a value is missing after the `:`\n"));
+        }
+        assert!(render("{\"a\": ]")[0].starts_with("error: unclosed `{`\n --> 1:1\n"));
+
+        let reports = render("[\"unterminated]");
+        assert_eq!(
+            reports[0],
+            "error: unterminated string\n \
+              --> 1:2\n  \
+               |\n\
+             1 | [\"unterminated]\n  \
+               |  ^^^^^^^^^^^^^^\n  \
+               = help: add a `\"` to close the string\n"
+        );
+    }
+
+    #[test]
+    fn test_hints_for_unexpected_characters() {
+        let quotes = "= help: strings and keys must be in double quotes\n";
+        let numbers = "= help: numbers are written like `1`, `-0.5` or `2e10`, \
+                       without a leading `+` or `.`\n";
+        let literals = "= help: literals are lowercase `true`, `false` and `null`\n";
+        for (text, hint) in [
+            ("['a']", Some(quotes)),
+            ("{a: 1}", Some(quotes)),
+            ("[\u{201C}a\u{201D}]", Some(quotes)),
+            ("[+1]", Some(numbers)),
+            ("{\"a\": .5}", Some(numbers)),
+            ("[1, #]", None),
+            ("{\"a\": tru}", Some(literals)),
+            ("{\"a\": True}", Some(literals)),
+            ("[nul, FALSE]", Some(literals)),
+            ("[nullable]", Some(quotes)),
+        ] {
+            let report = &render(text)[0];
+            match hint {
+                Some(hint) => assert!(report.ends_with(hint), "{}", report),
+                None => assert!(!report.contains("help"), "{}", report),
+            }
+        }
+    }
+
+    #[test]
+    fn test_carets_line_up() {
+        let text = "{\n\t\"é\": 1.2.3\n}";
+        assert_eq!(
+            render(text)[0],
+            "error: invalid number: more than one decimal point\n \
+              --> 2:7\n  \
+               |\n\
+             2 | \t\"é\": 1.2.3\n  \
+               | \t     ^^^^^\n"
+        );
+
+        // Problems at the end of the input point just past it.
+        let reports = render("[1,\n");
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].contains("--> 2:1\n"));
+        assert!(reports[0].contains("2 | \n  | ^\n"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Byte scanning helpers used by the tokenizer.
+//!
+//! The hot loops of tokenizing are "find the end of this string" and "skip
+//! the whitespace before the next token". Both are done eight bytes at a time
+//! with SWAR (SIMD within a register) on plain `u64` words, which works on
+//! stable Rust and every target. Inputs shorter than a word, and the tail of
+//! every input, fall back to the scalar versions in [`scalar`].
+//!
+//! Structural characters (`{}[],:`) are not searched for in bulk. The
+//! tokenizer reads one token at a time, and the byte after the whitespace
+//! skip is already the next token's first byte, so a search for the next
+//! structural character would never step over anything: every byte before
+//! it belongs to a string, number or literal, which is scanned anyway.
+
+const WORD: usize = std::mem::size_of::<u64>();
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+const LOW_BITS: u64 = !HI;
+
+/// Returns a word with the high bit set in every byte lane equal to `byte`.
+///
+/// Lanes above the first match may report false positives, so callers must
+/// only ever look at the lowest set bit.
+#[inline]
+fn lanes_equal(word: u64, byte: u8) -> u64 {
+    let x = word ^ (LO * byte as u64);
+    x.wrapping_sub(LO) & !x & HI
+}
+
+/// Like [`lanes_equal`], but exact in every lane, for callers that need
+/// more than the lowest match.
+#[inline]
+fn lanes_equal_exact(word: u64, byte: u8) -> u64 {
+    let x = word ^ (LO * byte as u64);
+    // A lane of `x` is zero iff neither its low seven bits nor its high bit
+    // are set; the addition cannot carry into the next lane.
+    !(((x & LOW_BITS) + LOW_BITS) | x) & HI
+}
+
+#[inline]
+fn load(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0u8; WORD];
+    buf.copy_from_slice(&bytes[at..at + WORD]);
+    u64::from_le_bytes(buf)
+}
+
+/// Finds the first `"` or `\` at or after `from`.
+pub fn find_quote_or_backslash(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut index = from;
+    while index + WORD <= bytes.len() {
+        let word = load(bytes, index);
+        let mask = lanes_equal(word, b'"') | lanes_equal(word, b'\\');
+        if mask != 0 {
+            return Some(index + (mask.trailing_zeros() / 8) as usize);
+        }
+        index += WORD;
+    }
+    scalar::find_quote_or_backslash(bytes, index)
+}
+
+/// Finds the closing quote of a string whose content starts at `from`,
+/// stepping over backslash escapes.
+pub fn find_string_end(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut index = from;
+    loop {
+        index = find_quote_or_backslash(bytes, index)?;
+        match bytes[index] {
+            b'"' => return Some(index),
+            _ => index += 2,
+        }
+    }
+}
+
+/// Returns the index of the first non-whitespace byte at or after `from`.
+///
+/// Most whitespace runs are a single space or a newline plus a little
+/// indentation, so the first word's worth of bytes is checked one at a time
+/// and only longer runs are scanned word by word.
+#[inline]
+pub fn skip_whitespace(bytes: &[u8], from: usize) -> usize {
+    let short = (from + WORD).min(bytes.len());
+    let mut index = from;
+    while index < short && is_whitespace(bytes[index]) {
+        index += 1;
+    }
+    if index < short {
+        return index;
+    }
+    while index + WORD <= bytes.len() {
+        let word = load(bytes, index);
+        let whitespace = lanes_equal_exact(word, b' ')
+            | lanes_equal_exact(word, b'\t')
+            | lanes_equal_exact(word, b'\n')
+            | lanes_equal_exact(word, b'\r');
+        let other = !whitespace & HI;
+        if other != 0 {
+            return index + (other.trailing_zeros() / 8) as usize;
+        }
+        index += WORD;
+    }
+    scalar::skip_whitespace(bytes, index)
+}
+
+#[inline]
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Byte-at-a-time versions of the scanners, used for short tails and as a
+/// reference for the SWAR paths.
+pub mod scalar {
+    /// Finds the first `"` or `\` at or after `from`.
+    pub fn find_quote_or_backslash(bytes: &[u8], from: usize) -> Option<usize> {
+        bytes
+            .get(from..)?
+            .iter()
+            .position(|byte| matches!(byte, b'"' | b'\\'))
+            .map(|offset| from + offset)
+    }
+
+    /// Returns the index of the first non-whitespace byte at or after `from`.
+    pub fn skip_whitespace(bytes: &[u8], from: usize) -> usize {
+        let mut index = from;
+        while bytes
+            .get(index)
+            .is_some_and(|&byte| super::is_whitespace(byte))
+        {
+            index += 1;
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests_scan {
+    use super::*;
+
+    #[test]
+    fn test_swar_agrees_with_scalar() {
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b"abc\"",
+            br#"0123456789abcdef\"0123456789""#,
+            "héllo wörld, ünïcode \\ then a quote \"".as_bytes(),
+            b"\\\\\\\\\\\\\\\\\\\\",
+        ];
+        for input in inputs {
+            for from in 0..=input.len() {
+                assert_eq!(
+                    find_quote_or_backslash(input, from),
+                    scalar::find_quote_or_backslash(input, from)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip_whitespace_agrees_with_scalar() {
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b" \t\r\n",
+            b"{\n        \"a\": 1,\n        \"b\":  \t  [ ]\n}",
+            // Bytes one off from whitespace, which a borrowing lane test
+            // would confuse with it.
+            b"  !!  \x08\x0B\x0C\x1F\x21  \x0B           \x0C",
+            "                 ü                   x".as_bytes(),
+        ];
+        for input in inputs {
+            for from in 0..=input.len() {
+                assert_eq!(
+                    skip_whitespace(input, from),
+                    scalar::skip_whitespace(input, from)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_string_end() {
+        let input = br#"Jack (\"Bee\") Nimble", "next""#;
+        assert_eq!(find_string_end(input, 0), Some(21));
+        assert_eq!(find_string_end(br#"abc\"#, 0), None);
+    }
+}
@@ -0,0 +1,1833 @@
+//! JSON Schema validation, draft 2020-12 with draft-07 compatibility.
+//!
+//! [`Schema::compile`] checks a schema document once and resolves its
+//! `$ref`s; [`Schema::validate`] then reports every way a value breaks it,
+//! each with a JSON Pointer into the value and one into the schema.
+//!
+//! ```
+//! use rust_json::parser::Parser;
+//! use rust_json::schema::Schema;
+//! use rust_json::token::parse_to_tokens;
+//!
+//! let tokens = parse_to_tokens(r##"{
+//!     "type": "object",
+//!     "properties": {"port": {"$ref": "#/$defs/port"}},
+//!     "required": ["host"],
+//!     "$defs": {"port": {"type": "integer", "maximum": 65535}}
+//! }"##).unwrap();
+//! let schema = Schema::compile(&Parser::new(&tokens).parse().unwrap()).unwrap();
+//!
+//! let tokens = parse_to_tokens(r#"{"port": 70000}"#).unwrap();
+//! let errors = schema.validate(&Parser::new(&tokens).parse().unwrap()).unwrap_err();
+//! let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+//! assert_eq!(errors, [
+//!     "/port: 70000 is greater than the maximum 65535",
+//!     "missing required property `host`",
+//! ]);
+//! ```
+//!
+//! References are resolved within the schema document only: `#`, JSON
+//! Pointer fragments such as `#/$defs/port`, anchors (`$anchor`, or a
+//! draft-07 `$id` of the form `#name`), and the URIs of embedded `$id`s.
+//! Relative `$id`s and `$ref`s are resolved against the `$id` of the
+//! resource they are in, and fragments point into that resource.
+//! `$dynamicRef` is resolved like `$ref`. References that loop back without
+//! descending into the value, such as `{"$ref": "#"}`, fail to compile.
+//! `format` is an annotation and is not checked. `pattern` and
+//! `patternProperties` need the `regex` feature, which is on by default.
+
+use crate::convert::type_name;
+use crate::escape::unescape;
+use crate::object::JsonObject;
+use crate::pointer::escape_token;
+use crate::value::JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Which rules a schema follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Draft {
+    /// `items` may be an array of schemas, followed by `additionalItems`;
+    /// `dependencies`; `$ref` ignores the keywords next to it.
+    Draft7,
+    /// `prefixItems`, `dependentRequired` and `dependentSchemas`,
+    /// `unevaluatedItems` and `unevaluatedProperties`, `minContains` and
+    /// `maxContains`, and `$anchor`.
+    Draft202012,
+}
+
+/// Why a schema could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// Where in the schema, as a JSON Pointer.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        located(f, &self.pointer, &self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A way a value breaks a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The offending part of the value, as a JSON Pointer.
+    pub instance_path: String,
+    /// The keyword it broke, as a JSON Pointer into the schema document.
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        located(f, &self.instance_path, &self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn located(f: &mut Formatter<'_>, pointer: &str, message: &str) -> std::fmt::Result {
+    if pointer.is_empty() {
+        write!(f, "{}", message)
+    } else {
+        write!(f, "{}: {}", pointer, message)
+    }
+}
+
+#[cfg(feature = "regex")]
+type Pattern = regex::Regex;
+
+/// Patterns cannot be compiled without the `regex` feature.
+#[cfg(not(feature = "regex"))]
+type Pattern = std::convert::Infallible;
+
+#[cfg(feature = "regex")]
+fn is_match(pattern: &Pattern, text: &str) -> bool {
+    pattern.is_match(text)
+}
+
+#[cfg(not(feature = "regex"))]
+fn is_match(pattern: &Pattern, _: &str) -> bool {
+    match *pattern {}
+}
+
+/// A compiled schema, ready to validate values against.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    draft: Draft,
+    /// Every subschema reached from the root, which is the first. Keywords
+    /// refer to their subschemas by index, so `$ref`s may form cycles.
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    True,
+    /// Where the `false` is in the schema document.
+    False(String),
+    Keywords(Vec<Keyword>),
+}
+
+#[derive(Debug, Clone)]
+struct Keyword {
+    /// Where the keyword is in the schema document.
+    pointer: String,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Ref(usize),
+    Type(Vec<String>),
+    Enum(Vec<JsonValue<'static>>),
+    Const(JsonValue<'static>),
+    MultipleOf(f64),
+    Maximum(f64),
+    ExclusiveMaximum(f64),
+    Minimum(f64),
+    ExclusiveMinimum(f64),
+    MaxLength(usize),
+    MinLength(usize),
+    Pattern(Pattern, String),
+    PrefixItems(Vec<usize>),
+    /// Applies to the elements after the first `skip`.
+    Items {
+        skip: usize,
+        schema: usize,
+    },
+    Contains {
+        schema: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+    MaxItems(usize),
+    MinItems(usize),
+    UniqueItems,
+    Properties(Vec<(String, usize)>),
+    PatternProperties(Vec<(Pattern, usize)>),
+    /// Applies to the members not named in `properties` nor matched by
+    /// `patterns`.
+    AdditionalProperties {
+        schema: usize,
+        properties: Vec<String>,
+        patterns: Vec<Pattern>,
+    },
+    PropertyNames(usize),
+    MaxProperties(usize),
+    MinProperties(usize),
+    Required(Vec<String>),
+    DependentRequired(Vec<(String, Vec<String>)>),
+    DependentSchemas(Vec<(String, usize)>),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    If {
+        condition: usize,
+        then: Option<usize>,
+        otherwise: Option<usize>,
+    },
+    UnevaluatedItems(usize),
+    UnevaluatedProperties(usize),
+}
+
+impl Kind {
+    /// The subschemas applied to the same instance as the keyword itself,
+    /// rather than to a part of it.
+    fn in_place(&self) -> Vec<usize> {
+        match self {
+            Kind::Ref(node) | Kind::Not(node) => vec![*node],
+            Kind::AllOf(nodes) | Kind::AnyOf(nodes) | Kind::OneOf(nodes) => nodes.clone(),
+            Kind::If {
+                condition,
+                then,
+                otherwise,
+            } => [Some(*condition), *then, *otherwise]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Kind::DependentSchemas(schemas) => schemas.iter().map(|(_, node)| *node).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Finds a keyword that leads back to a subschema it is reached from
+/// without descending into the instance, which would make checking loop
+/// forever. Returns where the keyword is.
+fn find_cycle(nodes: &[Node]) -> Option<&str> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        New,
+        Open,
+        Done,
+    }
+    let edges: Vec<Vec<(usize, &str)>> = nodes
+        .iter()
+        .map(|node| match node {
+            Node::Keywords(keywords) => keywords
+                .iter()
+                .flat_map(|keyword| {
+                    let pointer = keyword.pointer.as_str();
+                    keyword
+                        .kind
+                        .in_place()
+                        .into_iter()
+                        .map(move |to| (to, pointer))
+                })
+                .collect(),
+            _ => vec![],
+        })
+        .collect();
+    let mut visits = vec![Visit::New; nodes.len()];
+    for start in 0..nodes.len() {
+        if visits[start] != Visit::New {
+            continue;
+        }
+        visits[start] = Visit::Open;
+        // Each entry is a node and the index of its next edge to follow.
+        let mut stack = vec![(start, 0)];
+        while let Some(&(node, next)) = stack.last() {
+            let Some(&(to, pointer)) = edges[node].get(next) else {
+                visits[node] = Visit::Done;
+                stack.pop();
+                continue;
+            };
+            if let Some(top) = stack.last_mut() {
+                top.1 += 1;
+            }
+            match visits[to] {
+                Visit::Open => return Some(pointer),
+                Visit::New => {
+                    visits[to] = Visit::Open;
+                    stack.push((to, 0));
+                }
+                Visit::Done => {}
+            }
+        }
+    }
+    None
+}
+
+const TYPES: &[&str] = &[
+    "null", "boolean", "object", "array", "number", "string", "integer",
+];
+
+/// Members that hold values rather than schemas, so anchors and ids inside
+/// them are not looked for.
+const DATA: &[&str] = &["const", "enum", "default", "examples"];
+
+fn join(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, escape_token(token))
+}
+
+/// Decodes the `%XX` escapes of a URI fragment.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The length of the scheme of `uri`, including its `:`, or 0 if it has
+/// none and is a relative reference.
+fn scheme_len(uri: &str) -> usize {
+    let end = uri.find([':', '/', '?', '#']).unwrap_or(uri.len());
+    let scheme = &uri[..end];
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if valid && uri[end..].starts_with(':') {
+        end + 1
+    } else {
+        0
+    }
+}
+
+/// Removes the `.` and `..` segments of a path.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = vec![];
+    let mut split = path.split('/').peekable();
+    while let Some(segment) = split.next() {
+        let last = split.peek().is_none();
+        match segment {
+            "." | ".." => {
+                // The empty segment before a leading `/` stays.
+                if segment == ".." && segments.len() > usize::from(path.starts_with('/')) {
+                    segments.pop();
+                }
+                if last {
+                    segments.push("");
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Resolves the URI `reference` against `base` (RFC 3986, section 5.2).
+/// Neither has a fragment. A relative `base`, including the empty one of a
+/// document without an `$id`, is resolved against like an absolute one.
+fn resolve_uri(base: &str, reference: &str) -> String {
+    if reference.is_empty() {
+        return base.to_string();
+    }
+    if scheme_len(reference) > 0 {
+        return reference.to_string();
+    }
+    let scheme = &base[..scheme_len(base)];
+    let rest = &base[scheme.len()..];
+    if reference.starts_with("//") {
+        return format!("{}{}", scheme, reference);
+    }
+    let authority = match rest.strip_prefix("//") {
+        Some(tail) => &rest[..2 + tail.find(['/', '?']).unwrap_or(tail.len())],
+        None => "",
+    };
+    let rest = &rest[authority.len()..];
+    let path = &rest[..rest.find('?').unwrap_or(rest.len())];
+    if reference.starts_with('?') {
+        return format!("{}{}{}{}", scheme, authority, path, reference);
+    }
+    let (reference, query) = reference.split_at(reference.find('?').unwrap_or(reference.len()));
+    let path = if reference.starts_with('/') {
+        reference.to_string()
+    } else if !authority.is_empty() && path.is_empty() {
+        format!("/{}", reference)
+    } else {
+        let directory = path.rfind('/').map_or("", |slash| &path[..=slash]);
+        format!("{}{}", directory, reference)
+    };
+    format!(
+        "{}{}{}{}",
+        scheme,
+        authority,
+        remove_dot_segments(&path),
+        query
+    )
+}
+
+/// Where the schema resources and anchors of a document are.
+#[derive(Debug, Default)]
+struct Targets {
+    /// The pointer to each resource, by its absolute URI. The document
+    /// itself is also found by the empty URI.
+    resources: HashMap<String, String>,
+    /// The URI of each resource, by its pointer.
+    bases: HashMap<String, String>,
+    /// The pointer to each anchor, by `uri#name` where `uri` is its
+    /// resource's.
+    anchors: HashMap<String, String>,
+}
+
+impl Targets {
+    fn new(schema: &JsonValue, draft: Draft) -> Targets {
+        let mut targets = Targets::default();
+        targets.resources.insert(String::new(), String::new());
+        targets.bases.insert(String::new(), String::new());
+        targets.scan(schema, "", "", draft);
+        targets
+    }
+
+    /// Collects the `$id`s and anchors under `pointer`, whose resource has
+    /// the URI `base`.
+    fn scan(&mut self, value: &JsonValue, pointer: &str, base: &str, draft: Draft) {
+        match value {
+            JsonValue::Object(object) => {
+                let text = |key| match object.get(key) {
+                    Some(JsonValue::String(raw)) => Some(unescape(raw).into_owned()),
+                    _ => None,
+                };
+                let mut base = base.to_string();
+                // A draft-07 `$ref` hides the keywords next to it.
+                let hidden = draft == Draft::Draft7 && object.get("$ref").is_some();
+                if let Some(id) = text("$id").filter(|_| !hidden) {
+                    let (uri, fragment) = id.split_once('#').unwrap_or((&id, ""));
+                    if !uri.is_empty() {
+                        base = resolve_uri(&base, uri);
+                        self.resources.insert(base.clone(), pointer.to_string());
+                        self.bases.insert(pointer.to_string(), base.clone());
+                    }
+                    if draft == Draft::Draft7 && !fragment.is_empty() {
+                        self.anchors
+                            .insert(format!("{}#{}", base, fragment), pointer.to_string());
+                    }
+                }
+                if draft == Draft::Draft202012 {
+                    for key in ["$anchor", "$dynamicAnchor"] {
+                        if let Some(anchor) = text(key) {
+                            self.anchors
+                                .insert(format!("{}#{}", base, anchor), pointer.to_string());
+                        }
+                    }
+                }
+                for member in object.children.iter() {
+                    let key = unescape(&member.key);
+                    if !DATA.contains(&&*key) {
+                        self.scan(&member.value, &join(pointer, &key), &base, draft);
+                    }
+                }
+            }
+            JsonValue::Array(array) => {
+                for (index, element) in array.array.iter().enumerate() {
+                    self.scan(element, &join(pointer, &index.to_string()), base, draft);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The URI of the resource the subschema at `pointer` is in.
+    fn base(&self, mut pointer: &str) -> &str {
+        loop {
+            if let Some(uri) = self.bases.get(pointer) {
+                return uri;
+            }
+            pointer = &pointer[..pointer.rfind('/').unwrap_or(0)];
+        }
+    }
+}
+
+struct Compiler<'s, 'a> {
+    root: &'s JsonValue<'a>,
+    draft: Draft,
+    nodes: Vec<Node>,
+    /// The node compiled for each pointer.
+    compiled: HashMap<String, usize>,
+    targets: Targets,
+}
+
+impl<'s, 'a> Compiler<'s, 'a> {
+    fn error<T>(pointer: &str, message: impl Display) -> Result<T, SchemaError> {
+        Err(SchemaError {
+            pointer: pointer.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    /// The node for the subschema at `pointer`, compiling it the first time.
+    fn node(&mut self, pointer: String) -> Result<usize, SchemaError> {
+        if let Some(&index) = self.compiled.get(&pointer) {
+            return Ok(index);
+        }
+        let index = self.nodes.len();
+        // A placeholder, so that references back to this node find it.
+        self.nodes.push(Node::True);
+        self.compiled.insert(pointer.clone(), index);
+        let root = self.root;
+        let node = match root.try_pointer(&pointer) {
+            Ok(JsonValue::True) => Node::True,
+            Ok(JsonValue::False) => Node::False(pointer),
+            Ok(JsonValue::Object(object)) => Node::Keywords(self.keywords(object, &pointer)?),
+            Ok(_) => return Self::error(&pointer, "a schema must be an object or a boolean"),
+            Err(e) => return Self::error(&pointer, e),
+        };
+        self.nodes[index] = node;
+        Ok(index)
+    }
+
+    /// The pointer a `$ref` at `pointer` refers to. `#/...` and `#name`
+    /// are looked for in the resource the `$ref` is in.
+    fn resolve(&self, reference: &str, pointer: &str) -> Result<String, SchemaError> {
+        let (uri, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let uri = resolve_uri(self.targets.base(pointer), uri);
+        let Some(resource) = self.targets.resources.get(&uri) else {
+            return Self::error(pointer, format!("`{}` is not a local reference", reference));
+        };
+        if fragment.is_empty() {
+            return Ok(resource.clone());
+        }
+        if fragment.starts_with('/') {
+            return match percent_decode(fragment) {
+                Some(fragment) => Ok(format!("{}{}", resource, fragment)),
+                None => Self::error(pointer, format!("`{}` is not a valid reference", reference)),
+            };
+        }
+        match self.targets.anchors.get(&format!("{}#{}", uri, fragment)) {
+            Some(target) => Ok(target.clone()),
+            None => Self::error(pointer, format!("no anchor named `{}`", fragment)),
+        }
+    }
+
+    fn number(value: &JsonValue, pointer: &str) -> Result<f64, SchemaError> {
+        match value {
+            JsonValue::Number(number) => Ok(**number),
+            _ => Self::error(pointer, "expected a number"),
+        }
+    }
+
+    fn count(value: &JsonValue, pointer: &str) -> Result<usize, SchemaError> {
+        match value {
+            JsonValue::Number(number) if **number >= 0.0 && number.fract() == 0.0 => {
+                Ok(**number as usize)
+            }
+            _ => Self::error(pointer, "expected a non-negative integer"),
+        }
+    }
+
+    fn string(value: &JsonValue, pointer: &str) -> Result<String, SchemaError> {
+        match value {
+            JsonValue::String(raw) => Ok(unescape(raw).into_owned()),
+            _ => Self::error(pointer, "expected a string"),
+        }
+    }
+
+    fn strings(value: &JsonValue, pointer: &str) -> Result<Vec<String>, SchemaError> {
+        match value {
+            JsonValue::Array(array) => array
+                .array
+                .iter()
+                .enumerate()
+                .map(|(index, element)| Self::string(element, &join(pointer, &index.to_string())))
+                .collect(),
+            _ => Self::error(pointer, "expected an array of strings"),
+        }
+    }
+
+    /// The nodes of an array of schemas.
+    fn schemas(&mut self, value: &JsonValue, pointer: &str) -> Result<Vec<usize>, SchemaError> {
+        match value {
+            JsonValue::Array(array) if !array.array.is_empty() => (0..array.array.len())
+                .map(|index| self.node(join(pointer, &index.to_string())))
+                .collect(),
+            _ => Self::error(pointer, "expected a non-empty array of schemas"),
+        }
+    }
+
+    /// The names of an object's members, with the nodes of their schemas.
+    fn named_schemas(
+        &mut self,
+        value: &JsonValue,
+        pointer: &str,
+    ) -> Result<Vec<(String, usize)>, SchemaError> {
+        match value {
+            JsonValue::Object(object) => object
+                .children
+                .iter()
+                .map(|member| {
+                    let key = unescape(&member.key).into_owned();
+                    let node = self.node(join(pointer, &key))?;
+                    Ok((key, node))
+                })
+                .collect(),
+            _ => Self::error(pointer, "expected an object of schemas"),
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn pattern(text: &str, pointer: &str) -> Result<Pattern, SchemaError> {
+        regex::Regex::new(text).or_else(|e| Self::error(pointer, e))
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn pattern(_: &str, pointer: &str) -> Result<Pattern, SchemaError> {
+        Self::error(pointer, "patterns need the `regex` feature")
+    }
+
+    fn patterns(
+        &mut self,
+        value: &JsonValue,
+        pointer: &str,
+    ) -> Result<Vec<(Pattern, usize)>, SchemaError> {
+        self.named_schemas(value, pointer)?
+            .into_iter()
+            .map(|(text, node)| Ok((Self::pattern(&text, &join(pointer, &text))?, node)))
+            .collect()
+    }
+
+    fn keywords(
+        &mut self,
+        object: &JsonObject,
+        pointer: &str,
+    ) -> Result<Vec<Keyword>, SchemaError> {
+        let draft = self.draft;
+        let mut keywords = vec![];
+        // Kept for last, as they depend on what the others evaluated.
+        let mut unevaluated = vec![];
+        for entry in object.children.iter() {
+            let key = unescape(&entry.key);
+            let value = &entry.value;
+            let at = join(pointer, &key);
+            if draft == Draft::Draft7 && object.get("$ref").is_some() && key != "$ref" {
+                continue;
+            }
+            let kind = match (&*key, draft) {
+                ("$ref", _) | ("$dynamicRef", Draft::Draft202012) => {
+                    let target = self.resolve(&Self::string(value, &at)?, &at)?;
+                    Kind::Ref(self.node(target)?)
+                }
+                ("type", _) => {
+                    let types = match value {
+                        JsonValue::Array(_) => Self::strings(value, &at)?,
+                        _ => vec![Self::string(value, &at)?],
+                    };
+                    if let Some(unknown) = types.iter().find(|name| !TYPES.contains(&name.as_str()))
+                    {
+                        return Self::error(&at, format!("unknown type `{}`", unknown));
+                    }
+                    Kind::Type(types)
+                }
+                ("enum", _) => match value {
+                    JsonValue::Array(array) => {
+                        Kind::Enum(array.array.iter().map(|v| v.clone().into_owned()).collect())
+                    }
+                    _ => return Self::error(&at, "expected an array"),
+                },
+                ("const", _) => Kind::Const(value.clone().into_owned()),
+                ("multipleOf", _) => match Self::number(value, &at)? {
+                    divisor if divisor > 0.0 => Kind::MultipleOf(divisor),
+                    _ => return Self::error(&at, "expected a number greater than 0"),
+                },
+                ("maximum", _) => Kind::Maximum(Self::number(value, &at)?),
+                ("exclusiveMaximum", _) => Kind::ExclusiveMaximum(Self::number(value, &at)?),
+                ("minimum", _) => Kind::Minimum(Self::number(value, &at)?),
+                ("exclusiveMinimum", _) => Kind::ExclusiveMinimum(Self::number(value, &at)?),
+                ("maxLength", _) => Kind::MaxLength(Self::count(value, &at)?),
+                ("minLength", _) => Kind::MinLength(Self::count(value, &at)?),
+                ("pattern", _) => {
+                    let text = Self::string(value, &at)?;
+                    Kind::Pattern(Self::pattern(&text, &at)?, text)
+                }
+                ("prefixItems", Draft::Draft202012) => Kind::PrefixItems(self.schemas(value, &at)?),
+                ("items", Draft::Draft7) if matches!(value, JsonValue::Array(_)) => {
+                    Kind::PrefixItems(self.schemas(value, &at)?)
+                }
+                ("items", _) => {
+                    let skip = match (draft, object.get("prefixItems")) {
+                        (Draft::Draft202012, Some(JsonValue::Array(prefix))) => prefix.array.len(),
+                        _ => 0,
+                    };
+                    let schema = self.node(at.clone())?;
+                    Kind::Items { skip, schema }
+                }
+                ("additionalItems", Draft::Draft7) => match object.get("items") {
+                    Some(JsonValue::Array(prefix)) => {
+                        let skip = prefix.array.len();
+                        let schema = self.node(at.clone())?;
+                        Kind::Items { skip, schema }
+                    }
+                    _ => continue,
+                },
+                ("contains", _) => {
+                    let bound = |key| match (draft, object.get(key)) {
+                        (Draft::Draft202012, Some(value)) => {
+                            Self::count(value, &join(pointer, key)).map(Some)
+                        }
+                        _ => Ok(None),
+                    };
+                    Kind::Contains {
+                        schema: self.node(at.clone())?,
+                        min: bound("minContains")?.unwrap_or(1),
+                        max: bound("maxContains")?,
+                    }
+                }
+                ("maxItems", _) => Kind::MaxItems(Self::count(value, &at)?),
+                ("minItems", _) => Kind::MinItems(Self::count(value, &at)?),
+                ("uniqueItems", _) => match value {
+                    JsonValue::True => Kind::UniqueItems,
+                    JsonValue::False => continue,
+                    _ => return Self::error(&at, "expected a boolean"),
+                },
+                ("properties", _) => Kind::Properties(self.named_schemas(value, &at)?),
+                ("patternProperties", _) => Kind::PatternProperties(self.patterns(value, &at)?),
+                ("additionalProperties", _) => {
+                    let properties = match object.get("properties") {
+                        Some(JsonValue::Object(properties)) => properties
+                            .children
+                            .iter()
+                            .map(|member| unescape(&member.key).into_owned())
+                            .collect(),
+                        _ => vec![],
+                    };
+                    let patterns = match object.get("patternProperties") {
+                        Some(patterns) => {
+                            let at = join(pointer, "patternProperties");
+                            self.patterns(patterns, &at)?
+                                .into_iter()
+                                .map(|(p, _)| p)
+                                .collect()
+                        }
+                        None => vec![],
+                    };
+                    Kind::AdditionalProperties {
+                        schema: self.node(at.clone())?,
+                        properties,
+                        patterns,
+                    }
+                }
+                ("propertyNames", _) => Kind::PropertyNames(self.node(at.clone())?),
+                ("maxProperties", _) => Kind::MaxProperties(Self::count(value, &at)?),
+                ("minProperties", _) => Kind::MinProperties(Self::count(value, &at)?),
+                ("required", _) => Kind::Required(Self::strings(value, &at)?),
+                ("dependentRequired", Draft::Draft202012) => match value {
+                    JsonValue::Object(object) => Kind::DependentRequired(
+                        object
+                            .children
+                            .iter()
+                            .map(|member| {
+                                let key = unescape(&member.key).into_owned();
+                                let names = Self::strings(&member.value, &join(&at, &key))?;
+                                Ok((key, names))
+                            })
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    _ => return Self::error(&at, "expected an object"),
+                },
+                ("dependentSchemas", Draft::Draft202012) => {
+                    Kind::DependentSchemas(self.named_schemas(value, &at)?)
+                }
+                ("dependencies", Draft::Draft7) => {
+                    let JsonValue::Object(dependencies) = value else {
+                        return Self::error(&at, "expected an object");
+                    };
+                    let (mut required, mut schemas) = (vec![], vec![]);
+                    for member in dependencies.children.iter() {
+                        let key = unescape(&member.key).into_owned();
+                        let at = join(&at, &key);
+                        match &member.value {
+                            JsonValue::Array(_) => {
+                                required.push((key, Self::strings(&member.value, &at)?))
+                            }
+                            _ => schemas.push((key, self.node(at)?)),
+                        }
+                    }
+                    keywords.push(Keyword {
+                        pointer: at.clone(),
+                        kind: Kind::DependentRequired(required),
+                    });
+                    Kind::DependentSchemas(schemas)
+                }
+                ("allOf", _) => Kind::AllOf(self.schemas(value, &at)?),
+                ("anyOf", _) => Kind::AnyOf(self.schemas(value, &at)?),
+                ("oneOf", _) => Kind::OneOf(self.schemas(value, &at)?),
+                ("not", _) => Kind::Not(self.node(at.clone())?),
+                ("if", _) => {
+                    let mut branch = |key| match object.get(key) {
+                        Some(_) => self.node(join(pointer, key)).map(Some),
+                        None => Ok(None),
+                    };
+                    let then = branch("then")?;
+                    let otherwise = branch("else")?;
+                    Kind::If {
+                        condition: self.node(at.clone())?,
+                        then,
+                        otherwise,
+                    }
+                }
+                ("unevaluatedItems", Draft::Draft202012) => {
+                    unevaluated.push(Keyword {
+                        kind: Kind::UnevaluatedItems(self.node(at.clone())?),
+                        pointer: at,
+                    });
+                    continue;
+                }
+                ("unevaluatedProperties", Draft::Draft202012) => {
+                    unevaluated.push(Keyword {
+                        kind: Kind::UnevaluatedProperties(self.node(at.clone())?),
+                        pointer: at,
+                    });
+                    continue;
+                }
+                _ => continue,
+            };
+            keywords.push(Keyword { pointer: at, kind });
+        }
+        keywords.extend(unevaluated);
+        Ok(keywords)
+    }
+}
+
+/// The parts of an array or object that some keyword looked at, which
+/// `unevaluatedItems` and `unevaluatedProperties` skip.
+#[derive(Debug, Default)]
+struct Evaluated {
+    items: HashSet<usize>,
+    properties: HashSet<String>,
+}
+
+impl Evaluated {
+    fn extend(&mut self, other: Evaluated) {
+        self.items.extend(other.items);
+        self.properties.extend(other.properties);
+    }
+}
+
+/// Runs `f` with `path` extended by one reference token.
+fn with_token<R>(path: &mut String, token: &str, f: impl FnOnce(&mut String) -> R) -> R {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&escape_token(token));
+    let result = f(path);
+    path.truncate(len);
+    result
+}
+
+fn is_type(value: &JsonValue, name: &str) -> bool {
+    match (name, value) {
+        ("integer", JsonValue::Number(number)) => number.fract() == 0.0,
+        (name, value) => type_name(value) == name,
+    }
+}
+
+impl Schema {
+    /// Compiles `schema` by the draft its `$schema` names: draft-07 rules
+    /// for draft-06 and draft-07, draft 2020-12 otherwise. Schemas naming
+    /// draft-04 or earlier fail to compile, as their keywords differ.
+    pub fn compile(schema: &JsonValue) -> Result<Schema, SchemaError> {
+        let draft = match schema {
+            JsonValue::Object(object) => match object.get("$schema") {
+                Some(JsonValue::String(uri)) => {
+                    let named = |names: &[&str]| names.iter().any(|name| uri.contains(name));
+                    if named(&["draft-03", "draft-04"]) {
+                        return Compiler::error("/$schema", format!("unsupported draft `{}`", uri));
+                    }
+                    if named(&["draft-06", "draft-07"]) {
+                        Draft::Draft7
+                    } else {
+                        Draft::Draft202012
+                    }
+                }
+                _ => Draft::Draft202012,
+            },
+            _ => Draft::Draft202012,
+        };
+        Self::compile_as(schema, draft)
+    }
+
+    /// Compiles `schema` by the rules of `draft`, whatever its `$schema`.
+    pub fn compile_as(schema: &JsonValue, draft: Draft) -> Result<Schema, SchemaError> {
+        let targets = Targets::new(schema, draft);
+        let mut compiler = Compiler {
+            root: schema,
+            draft,
+            nodes: vec![],
+            compiled: HashMap::new(),
+            targets,
+        };
+        compiler.node(String::new())?;
+        if let Some(pointer) = find_cycle(&compiler.nodes) {
+            return Compiler::error(
+                pointer,
+                "this loops back to the schema it is in without descending into the value",
+            );
+        }
+        Ok(Schema {
+            draft,
+            nodes: compiler.nodes,
+        })
+    }
+
+    pub fn draft(&self) -> Draft {
+        self.draft
+    }
+
+    /// Checks `instance`, returning every error found.
+    pub fn validate(&self, instance: &JsonValue) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        self.check(0, instance, &mut String::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonValue) -> bool {
+        let mut errors = vec![];
+        self.check(0, instance, &mut String::new(), &mut errors);
+        errors.is_empty()
+    }
+
+    /// Checks `instance` against a subschema without reporting errors.
+    fn passes(&self, node: usize, instance: &JsonValue, path: &mut String) -> Option<Evaluated> {
+        let mut errors = vec![];
+        let evaluated = self.check(node, instance, path, &mut errors);
+        errors.is_empty().then_some(evaluated)
+    }
+
+    /// Checks `instance` against the subschema `node`, adding what is wrong
+    /// to `errors` and returning what was evaluated.
+    fn check(
+        &self,
+        node: usize,
+        instance: &JsonValue,
+        path: &mut String,
+        errors: &mut Vec<ValidationError>,
+    ) -> Evaluated {
+        let mut evaluated = Evaluated::default();
+        let keywords = match &self.nodes[node] {
+            Node::True => return evaluated,
+            Node::False(pointer) => {
+                errors.push(ValidationError {
+                    instance_path: path.clone(),
+                    schema_path: pointer.clone(),
+                    message: "no value is allowed here".to_string(),
+                });
+                return evaluated;
+            }
+            Node::Keywords(keywords) => keywords,
+        };
+        let number = match instance {
+            JsonValue::Number(number) => Some(**number),
+            _ => None,
+        };
+        let text = match instance {
+            JsonValue::String(raw) => Some(unescape(raw)),
+            _ => None,
+        };
+        let elements = match instance {
+            JsonValue::Array(array) => Some(&array.array),
+            _ => None,
+        };
+        let members = match instance {
+            JsonValue::Object(object) => Some(object),
+            _ => None,
+        };
+        let has = |name: &str| members.is_some_and(|object| object.get(name).is_some());
+        for keyword in keywords {
+            let fail = |errors: &mut Vec<ValidationError>, path: &str, message: String| {
+                errors.push(ValidationError {
+                    instance_path: path.to_string(),
+                    schema_path: keyword.pointer.clone(),
+                    message,
+                })
+            };
+            match &keyword.kind {
+                Kind::Ref(target) => {
+                    let before = errors.len();
+                    let sub = self.check(*target, instance, path, errors);
+                    if errors.len() == before {
+                        evaluated.extend(sub);
+                    }
+                }
+                Kind::Type(types) => {
+                    if !types.iter().any(|name| is_type(instance, name)) {
+                        let message = format!(
+                            "expected {}, found {}",
+                            types.join(" or "),
+                            type_name(instance)
+                        );
+                        fail(errors, path, message);
+                    }
+                }
+                Kind::Enum(values) => {
+                    if !values.iter().any(|value| value.equivalent(instance)) {
+                        fail(
+                            errors,
+                            path,
+                            "value is not one of the allowed values".to_string(),
+                        );
+                    }
+                }
+                Kind::Const(value) => {
+                    if !value.equivalent(instance) {
+                        fail(errors, path, format!("expected {}", value));
+                    }
+                }
+                Kind::MultipleOf(divisor) => {
+                    if let Some(number) = number {
+                        // `0.3 / 0.1` is 2.9999999999999996, so allow for
+                        // rounding relative to the size of the quotient.
+                        let quotient = number / divisor;
+                        let off = (quotient - quotient.round()).abs();
+                        if quotient.is_finite()
+                            && off > 4.0 * f64::EPSILON * quotient.abs().max(1.0)
+                        {
+                            fail(
+                                errors,
+                                path,
+                                format!("{} is not a multiple of {}", number, divisor),
+                            );
+                        }
+                    }
+                }
+                Kind::Maximum(limit) => {
+                    if let Some(number) = number.filter(|number| number > limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("{} is greater than the maximum {}", number, limit),
+                        );
+                    }
+                }
+                Kind::ExclusiveMaximum(limit) => {
+                    if let Some(number) = number.filter(|number| number >= limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("{} is not less than {}", number, limit),
+                        );
+                    }
+                }
+                Kind::Minimum(limit) => {
+                    if let Some(number) = number.filter(|number| number < limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("{} is less than the minimum {}", number, limit),
+                        );
+                    }
+                }
+                Kind::ExclusiveMinimum(limit) => {
+                    if let Some(number) = number.filter(|number| number <= limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("{} is not greater than {}", number, limit),
+                        );
+                    }
+                }
+                Kind::MaxLength(limit) => {
+                    if text
+                        .as_ref()
+                        .is_some_and(|text| text.chars().count() > *limit)
+                    {
+                        fail(
+                            errors,
+                            path,
+                            format!("string is longer than {} characters", limit),
+                        );
+                    }
+                }
+                Kind::MinLength(limit) => {
+                    if text
+                        .as_ref()
+                        .is_some_and(|text| text.chars().count() < *limit)
+                    {
+                        fail(
+                            errors,
+                            path,
+                            format!("string is shorter than {} characters", limit),
+                        );
+                    }
+                }
+                Kind::Pattern(pattern, source) => {
+                    if text.as_ref().is_some_and(|text| !is_match(pattern, text)) {
+                        fail(errors, path, format!("string does not match `{}`", source));
+                    }
+                }
+                Kind::PrefixItems(schemas) => {
+                    let Some(elements) = elements else { continue };
+                    for (index, (element, schema)) in elements.iter().zip(schemas).enumerate() {
+                        with_token(path, &index.to_string(), |path| {
+                            self.check(*schema, element, path, errors)
+                        });
+                        evaluated.items.insert(index);
+                    }
+                }
+                Kind::Items { skip, schema } => {
+                    let Some(elements) = elements else { continue };
+                    for (index, element) in elements.iter().enumerate().skip(*skip) {
+                        with_token(path, &index.to_string(), |path| {
+                            self.check(*schema, element, path, errors)
+                        });
+                        evaluated.items.insert(index);
+                    }
+                }
+                Kind::Contains { schema, min, max } => {
+                    let Some(elements) = elements else { continue };
+                    let mut count = 0;
+                    for (index, element) in elements.iter().enumerate() {
+                        let passed = with_token(path, &index.to_string(), |path| {
+                            self.passes(*schema, element, path)
+                        });
+                        if passed.is_some() {
+                            count += 1;
+                            if self.draft == Draft::Draft202012 {
+                                evaluated.items.insert(index);
+                            }
+                        }
+                    }
+                    if count < *min {
+                        fail(
+                            errors,
+                            path,
+                            format!(
+                                "{} items match `contains`, expected at least {}",
+                                count, min
+                            ),
+                        );
+                    }
+                    if let Some(max) = max.filter(|&max| count > max) {
+                        fail(
+                            errors,
+                            path,
+                            format!("{} items match `contains`, expected at most {}", count, max),
+                        );
+                    }
+                }
+                Kind::MaxItems(limit) => {
+                    if elements.is_some_and(|elements| elements.len() > *limit) {
+                        fail(errors, path, format!("array has more than {} items", limit));
+                    }
+                }
+                Kind::MinItems(limit) => {
+                    if elements.is_some_and(|elements| elements.len() < *limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("array has fewer than {} items", limit),
+                        );
+                    }
+                }
+                Kind::UniqueItems => {
+                    let Some(elements) = elements else { continue };
+                    let duplicate = (0..elements.len()).find_map(|j| {
+                        (0..j)
+                            .find(|&i| elements[i].equivalent(&elements[j]))
+                            .map(|i| (i, j))
+                    });
+                    if let Some((i, j)) = duplicate {
+                        fail(errors, path, format!("items {} and {} are equal", i, j));
+                    }
+                }
+                Kind::Properties(schemas) => {
+                    let Some(object) = members else { continue };
+                    for (name, schema) in schemas {
+                        if let Some(value) = object.get(name) {
+                            with_token(path, name, |path| self.check(*schema, value, path, errors));
+                            evaluated.properties.insert(name.clone());
+                        }
+                    }
+                }
+                Kind::PatternProperties(patterns) => {
+                    let Some(object) = members else { continue };
+                    for entry in object.children.iter() {
+                        let name = unescape(&entry.key);
+                        for (pattern, schema) in patterns {
+                            if is_match(pattern, &name) {
+                                with_token(path, &name, |path| {
+                                    self.check(*schema, &entry.value, path, errors)
+                                });
+                                evaluated.properties.insert(name.to_string());
+                            }
+                        }
+                    }
+                }
+                Kind::AdditionalProperties {
+                    schema,
+                    properties,
+                    patterns,
+                } => {
+                    let Some(object) = members else { continue };
+                    for entry in object.children.iter() {
+                        let name = unescape(&entry.key);
+                        if properties.iter().any(|property| *property == name)
+                            || patterns.iter().any(|pattern| is_match(pattern, &name))
+                        {
+                            continue;
+                        }
+                        with_token(path, &name, |path| {
+                            self.check(*schema, &entry.value, path, errors)
+                        });
+                        evaluated.properties.insert(name.into_owned());
+                    }
+                }
+                Kind::PropertyNames(schema) => {
+                    let Some(object) = members else { continue };
+                    for entry in object.children.iter() {
+                        let name = JsonValue::String(Box::new(entry.key.clone()));
+                        let key = unescape(&entry.key);
+                        with_token(path, &key, |path| self.check(*schema, &name, path, errors));
+                    }
+                }
+                Kind::MaxProperties(limit) => {
+                    if members.is_some_and(|object| object.len() > *limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("object has more than {} properties", limit),
+                        );
+                    }
+                }
+                Kind::MinProperties(limit) => {
+                    if members.is_some_and(|object| object.len() < *limit) {
+                        fail(
+                            errors,
+                            path,
+                            format!("object has fewer than {} properties", limit),
+                        );
+                    }
+                }
+                Kind::Required(names) => {
+                    if members.is_none() {
+                        continue;
+                    }
+                    for name in names.iter().filter(|name| !has(name)) {
+                        fail(
+                            errors,
+                            path,
+                            format!("missing required property `{}`", name),
+                        );
+                    }
+                }
+                Kind::DependentRequired(dependencies) => {
+                    for (name, required) in dependencies.iter().filter(|(name, _)| has(name)) {
+                        for missing in required.iter().filter(|required| !has(required)) {
+                            fail(
+                                errors,
+                                path,
+                                format!(
+                                    "property `{}` is required when `{}` is present",
+                                    missing, name
+                                ),
+                            );
+                        }
+                    }
+                }
+                Kind::DependentSchemas(dependencies) => {
+                    for (_, schema) in dependencies.iter().filter(|(name, _)| has(name)) {
+                        let before = errors.len();
+                        let sub = self.check(*schema, instance, path, errors);
+                        if errors.len() == before {
+                            evaluated.extend(sub);
+                        }
+                    }
+                }
+                Kind::AllOf(schemas) => {
+                    for schema in schemas {
+                        let before = errors.len();
+                        let sub = self.check(*schema, instance, path, errors);
+                        if errors.len() == before {
+                            evaluated.extend(sub);
+                        }
+                    }
+                }
+                Kind::AnyOf(schemas) => {
+                    let mut matched = false;
+                    for schema in schemas {
+                        if let Some(sub) = self.passes(*schema, instance, path) {
+                            matched = true;
+                            evaluated.extend(sub);
+                        }
+                    }
+                    if !matched {
+                        fail(
+                            errors,
+                            path,
+                            "value does not match any schema in `anyOf`".to_string(),
+                        );
+                    }
+                }
+                Kind::OneOf(schemas) => {
+                    let mut passed: Vec<_> = schemas
+                        .iter()
+                        .filter_map(|schema| self.passes(*schema, instance, path))
+                        .collect();
+                    match passed.len() {
+                        0 => fail(
+                            errors,
+                            path,
+                            "value does not match any schema in `oneOf`".to_string(),
+                        ),
+                        1 => evaluated.extend(passed.remove(0)),
+                        count => fail(
+                            errors,
+                            path,
+                            format!(
+                                "value matches {} schemas in `oneOf`, expected exactly one",
+                                count
+                            ),
+                        ),
+                    }
+                }
+                Kind::Not(schema) => {
+                    if self.passes(*schema, instance, path).is_some() {
+                        fail(
+                            errors,
+                            path,
+                            "value matches the schema in `not`".to_string(),
+                        );
+                    }
+                }
+                Kind::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = match self.passes(*condition, instance, path) {
+                        Some(sub) => {
+                            evaluated.extend(sub);
+                            then
+                        }
+                        None => otherwise,
+                    };
+                    if let Some(branch) = branch {
+                        let before = errors.len();
+                        let sub = self.check(*branch, instance, path, errors);
+                        if errors.len() == before {
+                            evaluated.extend(sub);
+                        }
+                    }
+                }
+                Kind::UnevaluatedItems(schema) => {
+                    let Some(elements) = elements else { continue };
+                    for (index, element) in elements.iter().enumerate() {
+                        if evaluated.items.insert(index) {
+                            with_token(path, &index.to_string(), |path| {
+                                self.check(*schema, element, path, errors)
+                            });
+                        }
+                    }
+                }
+                Kind::UnevaluatedProperties(schema) => {
+                    let Some(object) = members else { continue };
+                    for entry in object.children.iter() {
+                        let name = unescape(&entry.key);
+                        if evaluated.properties.insert(name.to_string()) {
+                            with_token(path, &name, |path| {
+                                self.check(*schema, &entry.value, path, errors)
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        evaluated
+    }
+}
+
+#[cfg(test)]
+mod tests_schema {
+    use super::*;
+    use crate::value::parse;
+
+    fn compile(schema: &str) -> Schema {
+        Schema::compile(&parse(schema)).unwrap()
+    }
+
+    /// The errors for `instance`, as `instance path|schema path|message`.
+    fn errors(schema: &Schema, instance: &str) -> Vec<String> {
+        match schema.validate(&parse(instance)) {
+            Ok(()) => vec![],
+            Err(errors) => errors
+                .iter()
+                .map(|e| format!("{}|{}|{}", e.instance_path, e.schema_path, e.message))
+                .collect(),
+        }
+    }
+
+    fn assert_valid(schema: &Schema, instances: &[&str]) {
+        for instance in instances {
+            assert_eq!(
+                errors(schema, instance),
+                Vec::<String>::new(),
+                "{}",
+                instance
+            );
+        }
+    }
+
+    fn assert_invalid(schema: &Schema, instances: &[&str]) {
+        for instance in instances {
+            assert!(!schema.is_valid(&parse(instance)), "{}", instance);
+        }
+    }
+
+    #[test]
+    fn test_scalars() {
+        let schema = compile(
+            r#"{"type": ["integer", "string"], "minimum": 1, "exclusiveMaximum": 10,
+            "multipleOf": 0.5, "minLength": 2, "maxLength": 3}"#,
+        );
+        assert_valid(
+            &schema,
+            &["1", "9", "2.0", r#""ab""#, r#""\u00e9\u00e9\u00e9""#],
+        );
+        assert_eq!(
+            errors(&schema, "true"),
+            ["|/type|expected integer or string, found boolean"]
+        );
+        assert_eq!(
+            errors(&schema, "10"),
+            ["|/exclusiveMaximum|10 is not less than 10"]
+        );
+        assert_eq!(
+            errors(&schema, "0.5"),
+            [
+                "|/type|expected integer or string, found number",
+                "|/minimum|0.5 is less than the minimum 1"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, r#""abcd""#),
+            ["|/maxLength|string is longer than 3 characters"]
+        );
+
+        let schema = compile(r#"{"multipleOf": 0.1}"#);
+        assert_valid(&schema, &["0.3", "0.7", "-2.2", "1e300"]);
+        assert_invalid(&schema, &["0.31", "0.30000001"]);
+        let schema = compile(r#"{"multipleOf": 0.01}"#);
+        assert_valid(&schema, &["19.99", "0.07", "1234567.89"]);
+        assert_invalid(&schema, &["19.995"]);
+
+        let schema = compile(r#"{"enum": [1, "a", {"b": [null]}], "not": {"const": 1.0}}"#);
+        assert_valid(&schema, &[r#""a""#, r#"{"b": [null]}"#]);
+        assert_eq!(
+            errors(&schema, "1"),
+            ["|/not|value matches the schema in `not`"]
+        );
+        assert_eq!(
+            errors(&schema, "2"),
+            ["|/enum|value is not one of the allowed values"]
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        let schema = compile(
+            r#"{"prefixItems": [{"type": "string"}], "items": {"type": "number"},
+            "contains": {"const": 0}, "maxContains": 1, "minItems": 2, "uniqueItems": true}"#,
+        );
+        assert_valid(&schema, &[r#"["a", 0]"#, r#"["a", 1, 0, 2]"#, "{}"]);
+        assert_eq!(
+            errors(&schema, r#"["a", 1, "b"]"#),
+            [
+                "/2|/items/type|expected number, found string",
+                "|/contains|0 items match `contains`, expected at least 1"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, r#"["a", 0, 0]"#),
+            [
+                "|/contains|2 items match `contains`, expected at most 1",
+                "|/uniqueItems|items 1 and 2 are equal"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, "[0]"),
+            [
+                "/0|/prefixItems/0/type|expected string, found number",
+                "|/minItems|array has fewer than 2 items"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_objects() {
+        let schema = compile(
+            r#"{
+            "properties": {"a/b": {"type": "integer"}, "c": true, "d": true},
+            "additionalProperties": false,
+            "required": ["a/b", "d"],
+            "dependentRequired": {"c": ["e"]},
+            "propertyNames": {"maxLength": 3},
+            "maxProperties": 2
+        }"#,
+        );
+        assert_eq!(
+            errors(&schema, r#"{"a/b": 1.5, "c": 1, "long": 2}"#),
+            [
+                "/a~1b|/properties/a~1b/type|expected integer, found number",
+                "/long|/additionalProperties|no value is allowed here",
+                "|/required|missing required property `d`",
+                "|/dependentRequired|property `e` is required when `c` is present",
+                "/long|/propertyNames/maxLength|string is longer than 3 characters",
+                "|/maxProperties|object has more than 2 properties"
+            ]
+        );
+        assert_valid(&schema, &["[]", r#"{"a/b": 1, "d": null}"#]);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let schema = compile(
+            r#"{
+            "anyOf": [{"type": "string"}, {"type": "number"}],
+            "oneOf": [{"type": "number", "minimum": 0}, {"maximum": 10}],
+            "if": {"type": "string"}, "then": {"minLength": 2}, "else": {"multipleOf": 2}
+        }"#,
+        );
+        assert_valid(&schema, &["-2", "12", r#""ab""#]);
+        assert_eq!(
+            errors(&schema, "4"),
+            ["|/oneOf|value matches 2 schemas in `oneOf`, expected exactly one"]
+        );
+        assert_eq!(
+            errors(&schema, "11"),
+            ["|/else/multipleOf|11 is not a multiple of 2"]
+        );
+        assert_eq!(
+            errors(&schema, r#""a""#),
+            ["|/then/minLength|string is shorter than 2 characters"]
+        );
+        assert_eq!(
+            errors(&schema, "null"),
+            ["|/anyOf|value does not match any schema in `anyOf`"]
+        );
+
+        let schema = compile(r#"{"allOf": [{"type": "object"}, {"required": ["a"]}]}"#);
+        assert_eq!(
+            errors(&schema, "{}"),
+            ["|/allOf/1/required|missing required property `a`"]
+        );
+        assert_invalid(&compile("false"), &["null"]);
+        assert_valid(&compile("true"), &["null"]);
+    }
+
+    #[test]
+    fn test_references() {
+        // A recursive tree, through a pointer, an anchor and an `$id`.
+        let schema = compile(
+            r##"{
+            "$id": "https://example.com/tree",
+            "$ref": "#/$defs/node",
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": {"$ref": "#number"},
+                        "children": {"items": {"$ref": "https://example.com/tree"}},
+                        "label": {"$ref": "label.json"},
+                        "percent": {"$ref": "#/$defs/a%25b"}
+                    }
+                },
+                "number": {"$anchor": "number", "type": "number"},
+                "label": {"$id": "label.json", "type": "string"},
+                "a%b": {"const": true}
+            }
+        }"##,
+        );
+        assert_valid(
+            &schema,
+            &[
+                r#"{"value": 1, "children": [{"value": 2, "children": []}], "label": "x", "percent": true}"#,
+            ],
+        );
+        assert_eq!(
+            errors(
+                &schema,
+                r#"{"children": [{"children": [{"value": "x"}]}], "label": 1, "percent": 0}"#
+            ),
+            [
+                "/children/0/children/0/value|/$defs/number/type|expected number, found string",
+                "/label|/$defs/label/type|expected string, found number",
+                "/percent|/$defs/a%b/const|expected true"
+            ]
+        );
+
+        let error = |schema: &str| Schema::compile(&parse(schema)).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"{"$ref": "https://example.com/other"}"#),
+            "/$ref: `https://example.com/other` is not a local reference"
+        );
+        assert_eq!(
+            error(r##"{"items": {"$ref": "#nowhere"}}"##),
+            "/items/$ref: no anchor named `nowhere`"
+        );
+        assert_eq!(
+            error(r##"{"$ref": "#/missing"}"##),
+            "/missing: no value at `/missing`"
+        );
+    }
+
+    #[test]
+    fn test_embedded_resources() {
+        // Fragments and anchors are looked for in the resource the `$ref`
+        // is in, and relative URIs are resolved against its `$id`.
+        let schema = compile(
+            r##"{
+            "$ref": "http://x/a",
+            "properties": {"b": {"$ref": "http://x/dir/b.json"}, "n": {"$ref": "#n"}},
+            "$defs": {
+                "n": {"$anchor": "n", "type": "null"},
+                "A": {
+                    "$id": "http://x/a",
+                    "$ref": "#/$defs/B",
+                    "$defs": {"B": {"type": "object"}}
+                },
+                "dir": {
+                    "$id": "http://x/dir/",
+                    "$defs": {
+                        "b": {"$id": "b.json", "$ref": "../c.json#n"},
+                        "c": {"$id": "../c.json", "$anchor": "n", "type": "number"}
+                    }
+                }
+            }
+        }"##,
+        );
+        assert_valid(&schema, &[r#"{"b": 1, "n": null}"#]);
+        assert_eq!(
+            errors(&schema, r#"{"b": "x", "n": 1}"#),
+            [
+                "/b|/$defs/dir/$defs/c/type|expected number, found string",
+                "/n|/$defs/n/type|expected null, found number"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, "[]"),
+            ["|/$defs/A/$defs/B/type|expected object, found array"]
+        );
+
+        let error = |schema: &str| Schema::compile(&parse(schema)).unwrap_err().to_string();
+        assert_eq!(
+            error(
+                r##"{"$defs": {"n": {"$anchor": "n"}, "a": {"$id": "http://x/a",
+                "$ref": "#n"}}, "$ref": "http://x/a"}"##
+            ),
+            "/$defs/a/$ref: no anchor named `n`"
+        );
+    }
+
+    #[test]
+    fn test_resolve_uri() {
+        let base = "http://a/b/c/d;p?q";
+        for (reference, resolved) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../../../g", "http://a/g"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(resolve_uri(base, reference), resolved, "{}", reference);
+        }
+        assert_eq!(resolve_uri("", "a.json"), "a.json");
+        assert_eq!(resolve_uri("http://x", "a.json"), "http://x/a.json");
+    }
+
+    #[test]
+    fn test_unevaluated() {
+        let schema = compile(
+            r#"{
+            "properties": {"a": true},
+            "allOf": [{"properties": {"b": true}}],
+            "anyOf": [{"required": ["c"], "properties": {"c": true}}, {"not": {"required": ["c"]}}],
+            "if": {"properties": {"d": {"const": 1}}}, "then": {"properties": {"e": true}},
+            "unevaluatedProperties": false
+        }"#,
+        );
+        assert_valid(
+            &schema,
+            &[r#"{"a": 1, "b": 2, "c": 3}"#, r#"{"d": 1, "e": 2}"#],
+        );
+        assert_eq!(
+            errors(&schema, r#"{"d": 2, "e": 2, "f": 3}"#),
+            [
+                "/d|/unevaluatedProperties|no value is allowed here",
+                "/e|/unevaluatedProperties|no value is allowed here",
+                "/f|/unevaluatedProperties|no value is allowed here"
+            ]
+        );
+
+        let schema = compile(
+            r#"{"prefixItems": [true], "contains": {"type": "string"},
+            "unevaluatedItems": {"type": "number"}}"#,
+        );
+        assert_valid(&schema, &[r#"[null, "a", 1, "b"]"#]);
+        assert_eq!(
+            errors(&schema, r#"[null, "a", true]"#),
+            ["/2|/unevaluatedItems/type|expected number, found boolean"]
+        );
+    }
+
+    #[test]
+    fn test_draft7() {
+        let text = r##"{
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {"positive": {"$id": "#positive", "exclusiveMinimum": 0}},
+            "items": [{"type": "string"}, {"$ref": "#positive", "type": "string"}],
+            "additionalItems": false,
+            "dependencies": {"a": ["b"], "c": {"required": ["d"]}},
+            "unevaluatedItems": false
+        }"##;
+        let schema = compile(text);
+        assert_eq!(schema.draft(), Draft::Draft7);
+        // `type` next to `$ref` is ignored, and `unevaluatedItems` is not a
+        // draft-07 keyword.
+        assert_valid(&schema, &[r#"["a", 1]"#, r#"{"a": 1, "b": 2}"#]);
+        assert_eq!(
+            errors(&schema, r#"["a", 0, 2]"#),
+            [
+                "/1|/definitions/positive/exclusiveMinimum|0 is not greater than 0",
+                "/2|/additionalItems|no value is allowed here"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, r#"{"a": 1, "c": 2}"#),
+            [
+                "|/dependencies|property `b` is required when `a` is present",
+                "|/dependencies/c/required|missing required property `d`"
+            ]
+        );
+
+        let schema = compile(r#"{"$schema": "http://json-schema.org/draft-06/schema#"}"#);
+        assert_eq!(schema.draft(), Draft::Draft7);
+        let error = Schema::compile(&parse(
+            r#"{"$schema": "http://json-schema.org/draft-04/schema#",
+            "maximum": 5, "exclusiveMaximum": true}"#,
+        ))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "/$schema: unsupported draft `http://json-schema.org/draft-04/schema#`"
+        );
+
+        let schema = Schema::compile_as(&parse(text), Draft::Draft202012).unwrap_err();
+        assert_eq!(
+            schema.to_string(),
+            "/items: a schema must be an object or a boolean"
+        );
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let error = |schema: &str| Schema::compile(&parse(schema)).unwrap_err().to_string();
+        assert_eq!(error("1"), "a schema must be an object or a boolean");
+        assert_eq!(error(r#"{"type": "int"}"#), "/type: unknown type `int`");
+        assert_eq!(
+            error(r#"{"minLength": -1}"#),
+            "/minLength: expected a non-negative integer"
+        );
+        assert_eq!(
+            error(r#"{"multipleOf": 0}"#),
+            "/multipleOf: expected a number greater than 0"
+        );
+        assert_eq!(
+            error(r#"{"anyOf": []}"#),
+            "/anyOf: expected a non-empty array of schemas"
+        );
+        assert_eq!(
+            error(r#"{"properties": {"a": {"required": "a"}}}"#),
+            "/properties/a/required: expected an array of strings"
+        );
+
+        let cycle = "this loops back to the schema it is in without descending into the value";
+        assert_eq!(
+            error(r##"{"$defs": {"a": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##),
+            format!("/$defs/a/$ref: {}", cycle)
+        );
+        assert_eq!(
+            error(
+                r##"{"$defs": {"a": {"$dynamicRef": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}},
+                "allOf": [{"$ref": "#/$defs/a"}]}"##
+            ),
+            format!("/$defs/b/$ref: {}", cycle)
+        );
+        assert_eq!(
+            error(r##"{"anyOf": [{"type": "null"}, {"$ref": "#"}]}"##),
+            format!("/anyOf/1/$ref: {}", cycle)
+        );
+        // Descending into the value ends the recursion.
+        Schema::compile(&parse(
+            r##"{"items": {"$ref": "#"}, "not": {"$ref": "#/$defs/n"},
+            "$defs": {"n": {"type": "string"}}}"##,
+        ))
+        .unwrap();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_patterns() {
+        let schema = compile(
+            r#"{"pattern": "^[a-z]+$",
+            "patternProperties": {"^x-": {"type": "string"}},
+            "additionalProperties": {"type": "number"}}"#,
+        );
+        assert_valid(&schema, &[r#""abc""#, r#"{"x-a": "b", "c": 1}"#]);
+        assert_eq!(
+            errors(&schema, r#""aB""#),
+            ["|/pattern|string does not match `^[a-z]+$`"]
+        );
+        assert_eq!(
+            errors(&schema, r#"{"x-a": 1, "c": "d"}"#),
+            [
+                "/x-a|/patternProperties/^x-/type|expected string, found number",
+                "/c|/additionalProperties/type|expected number, found string"
+            ]
+        );
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn test_patterns_need_the_feature() {
+        let error = Schema::compile(&parse(r#"{"pattern": "a"}"#)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "/pattern: patterns need the `regex` feature"
+        );
+    }
+}
@@ -0,0 +1,588 @@
+//! Serializing Rust types with serde, behind the `serde` feature.
+//!
+//! [`to_string`] writes the same compact form as [`JsonValue`]'s `Display`.
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Server<'a> {
+//!     host: &'a str,
+//!     port: u16,
+//!     tags: Vec<&'a str>,
+//! }
+//!
+//! let server = Server { host: "example.com", port: 8080, tags: vec!["a\tb"] };
+//! assert_eq!(
+//!     rust_json::ser::to_string(&server).unwrap(),
+//!     r#"{"host":"example.com","port":8080,"tags":["a\tb"]}"#
+//! );
+//! ```
+
+use crate::escape::{escape, unescape};
+use crate::value::JsonValue;
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+/// Why [`to_string`] failed: the value has no JSON form, e.g. a map with
+/// keys that are not strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    message: String,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error {
+            message: msg.to_string(),
+        }
+    }
+}
+
+fn key_must_be_a_string() -> Error {
+    ser::Error::custom("object keys must be strings")
+}
+
+/// Serializes `value` to compact JSON.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    let mut serializer = Serializer { out: String::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+struct Serializer {
+    out: String,
+}
+
+impl Serializer {
+    fn string(&mut self, text: &str) {
+        self.out.push('"');
+        self.out.push_str(&escape(text));
+        self.out.push('"');
+    }
+
+    // Writing to a `String` cannot fail.
+    fn display(&mut self, value: impl Display) {
+        let _ = write!(self.out, "{}", value);
+    }
+
+    /// NaN and the infinities are written as `null`, like `Display` does.
+    fn float(&mut self, value: f64) -> Result<(), Error> {
+        if value.is_finite() {
+            self.display(value);
+        } else {
+            self.out.push_str("null");
+        }
+        Ok(())
+    }
+
+    /// Opens the `{"Variant":` wrapper of a non-unit enum variant.
+    fn variant(&mut self, variant: &str) {
+        self.out.push('{');
+        self.string(variant);
+        self.out.push(':');
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident: $type:ty,)*) => {
+        $(fn $method(self, value: $type) -> Result<(), Error> {
+            self.display(value);
+            Ok(())
+        })*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    serialize_display! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), Error> {
+        if !value.is_finite() {
+            return self.float(value.into());
+        }
+        // Printed as an `f32` so that 0.1 stays 0.1.
+        self.display(value);
+        Ok(())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), Error> {
+        self.float(value)
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.string(value.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.string(value);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), Error> {
+        ser::Serializer::collect_seq(self, value)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.out.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.variant(variant);
+        value.serialize(&mut *self)?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.out.push('[');
+        Ok(Compound::new(self, "]"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.variant(variant);
+        self.out.push('[');
+        Ok(Compound::new(self, "]}"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.out.push('{');
+        Ok(Compound::new(self, "}"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.variant(variant);
+        self.out.push('{');
+        Ok(Compound::new(self, "}}"))
+    }
+}
+
+/// An array or object being written; `close` is what ends it.
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    close: &'static str,
+}
+
+impl<'a> Compound<'a> {
+    fn new(ser: &'a mut Serializer, close: &'static str) -> Self {
+        Compound {
+            ser,
+            first: true,
+            close,
+        }
+    }
+
+    fn comma(&mut self) {
+        if !self.first {
+            self.ser.out.push(',');
+        }
+        self.first = false;
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.comma();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        self.comma();
+        self.ser.string(key);
+        self.ser.out.push(':');
+        value.serialize(&mut *self.ser)
+    }
+
+    fn close(self) -> Result<(), Error> {
+        self.ser.out.push_str(self.close);
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.comma();
+        key.serialize(KeySerializer {
+            ser: &mut *self.ser,
+        })?;
+        self.ser.out.push(':');
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeStruct for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Compound<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+/// Writes a map key. Strings are used as they are; numbers, booleans and
+/// chars are quoted; anything else is an error.
+struct KeySerializer<'a> {
+    ser: &'a mut Serializer,
+}
+
+macro_rules! serialize_quoted {
+    ($($method:ident: $type:ty,)*) => {
+        $(fn $method(self, value: $type) -> Result<(), Error> {
+            self.ser.string(&value.to_string());
+            Ok(())
+        })*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method:ident$(<$generic:ident>)?($($arg:ident: $type:ty),*) -> $ok:ty,)*) => {
+        $(fn $method$(<$generic: ?Sized + Serialize>)?(self, $($arg: $type),*) -> Result<$ok, Error> {
+            let _ = ($($arg),*);
+            Err(key_must_be_a_string())
+        })*
+    };
+}
+
+impl<'a> ser::Serializer for KeySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    serialize_quoted! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_char: char,
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.ser.string(value);
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    reject_key! {
+        serialize_f32(value: f32) -> (),
+        serialize_f64(value: f64) -> (),
+        serialize_bytes(value: &[u8]) -> (),
+        serialize_none() -> (),
+        serialize_some<T>(value: &T) -> (),
+        serialize_unit() -> (),
+        serialize_unit_struct(name: &'static str) -> (),
+        serialize_newtype_variant<T>(name: &'static str, index: u32, variant: &'static str, value: &T) -> (),
+        serialize_seq(len: Option<usize>) -> Impossible<(), Error>,
+        serialize_tuple(len: usize) -> Impossible<(), Error>,
+        serialize_tuple_struct(name: &'static str, len: usize) -> Impossible<(), Error>,
+        serialize_tuple_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Impossible<(), Error>,
+        serialize_map(len: Option<usize>) -> Impossible<(), Error>,
+        serialize_struct(name: &'static str, len: usize) -> Impossible<(), Error>,
+        serialize_struct_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Impossible<(), Error>,
+    }
+}
+
+impl<'a> Serialize for JsonValue<'a> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            // Whole numbers go out as integers for formats that tell them
+            // apart.
+            JsonValue::Number(number)
+                if number.fract() == 0.0 && number.abs() <= (1u64 << 53) as f64 =>
+            {
+                serializer.serialize_i64(**number as i64)
+            }
+            JsonValue::Number(number) if !number.is_finite() => serializer.serialize_unit(),
+            JsonValue::Number(number) => serializer.serialize_f64(**number),
+            JsonValue::String(raw) => serializer.serialize_str(&unescape(raw)),
+            JsonValue::Object(obj) => {
+                serializer.collect_map(obj.iter().map(|child| (unescape(&child.key), &child.value)))
+            }
+            JsonValue::Array(arr) => serializer.collect_seq(arr.array.iter()),
+            JsonValue::True => serializer.serialize_bool(true),
+            JsonValue::False => serializer.serialize_bool(false),
+            JsonValue::Null | JsonValue::Empty => serializer.serialize_unit(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_ser {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::token::parse_to_tokens;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Config<'a> {
+        name: &'a str,
+        port: u16,
+        ratio: f32,
+        tags: Vec<&'a str>,
+        owner: Option<()>,
+        shapes: Vec<Shape>,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(i32, i32),
+        Rect { w: u8, h: u8 },
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        let config = Config {
+            name: "quote\" and\ttab",
+            port: 8080,
+            ratio: 0.1,
+            tags: vec![],
+            owner: None,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Line(-1, 2),
+                Shape::Rect { w: 3, h: 4 },
+            ],
+        };
+        assert_eq!(
+            to_string(&config).unwrap(),
+            r#"{"name":"quote\" and\ttab","port":8080,"ratio":0.1,"tags":[],"owner":null,"shapes":["Point",{"Circle":1.5},{"Line":[-1,2]},{"Rect":{"w":3,"h":4}}]}"#
+        );
+    }
+
+    #[test]
+    fn test_map_keys() {
+        let map: BTreeMap<i32, char> = [(1, 'a'), (-2, '"')].into();
+        assert_eq!(to_string(&map).unwrap(), r#"{"-2":"\"","1":"a"}"#);
+        let map: BTreeMap<Vec<u8>, u8> = [(vec![1], 1)].into();
+        assert_eq!(
+            to_string(&map).unwrap_err().to_string(),
+            "object keys must be strings"
+        );
+        assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+        assert_eq!(to_string(&[f32::INFINITY, 0.1]).unwrap(), "[null,0.1]");
+    }
+
+    #[test]
+    fn test_json_value() {
+        let text = r#"{"a":[1,-2.5,true,null],"b\n":"xA","c":{}}"#;
+        let tokens = parse_to_tokens(text).unwrap();
+        let value = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"{"a":[1,-2.5,true,null],"b\n":"xA","c":{}}"#
+        );
+    }
+}
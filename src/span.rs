@@ -0,0 +1,305 @@
+//! Byte ranges for tokens, keys and values, for pointing users at the exact
+//! place in their input a problem comes from.
+//!
+//! Spans are opt-in: [`SpannedValue::parse`] builds a tree in which every
+//! value and key knows where it was read from, while the ordinary
+//! [`Parser`](crate::parser::Parser) keeps its lighter [`JsonValue`]s. Spans
+//! are plain byte offsets; line and column numbers are only worked out when
+//! asked for, with [`Span::location`] or, for many lookups in the same text,
+//! a [`LineIndex`].
+//!
+//! ```
+//! use rust_json::span::{Location, SpannedValue};
+//!
+//! let text = "{\n  \"port\": \"eighty\"\n}";
+//! let root = SpannedValue::parse(text).unwrap();
+//! let port = root.get("port").unwrap();
+//! assert_eq!(&text[port.span.range()], "\"eighty\"");
+//! assert_eq!(port.span.location(text), Location { line: 2, column: 11 });
+//! ```
+
+use crate::array::JsonArray;
+use crate::error::JsonError;
+use crate::error::JsonParserError::{Unclosed, UnexpectedEndOfTokens};
+use crate::escape::unescape;
+use crate::keyvalue::KeyValue;
+use crate::object::JsonObject;
+use crate::recover::Recovered;
+use crate::token::Tokenizer;
+use crate::value::JsonValue;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// The bytes `start..end` of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// Line and column of the start of the span in `source`.
+    pub fn location(&self, source: &str) -> Location {
+        LineIndex::new(source).location(self.start)
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span::new(range.start, range.end)
+    }
+}
+
+/// A 1-based line and column. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The offsets at which each line of a text starts, for turning many byte
+/// offsets into [`Location`]s without rescanning the text.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// The location of byte `offset`. Offsets past the end of the text are
+    /// clamped to it.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.starts[line];
+        // Count the characters that begin before `offset`, so an offset in
+        // the middle of one still lands on it.
+        let column = self.source.as_bytes()[start..offset]
+            .iter()
+            .filter(|&&byte| (byte as i8) >= -0x40)
+            .count();
+        Location {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// The text of the 1-based `line`, without its line ending.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.starts.get(line.checked_sub(1)?)?;
+        let end = self.starts.get(line).map_or(self.source.len(), |&end| end);
+        let text = &self.source[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+}
+
+/// A value and the bytes it was read from. Containers span from their
+/// opening to their closing bracket.
+#[derive(Debug, PartialEq)]
+pub struct SpannedValue<'a> {
+    pub span: Span,
+    pub kind: SpannedKind<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpannedKind<'a> {
+    /// A string, number or literal.
+    Scalar(JsonValue<'a>),
+    Object(Vec<SpannedMember<'a>>),
+    Array(Vec<SpannedValue<'a>>),
+}
+
+/// An object member. `key_span` covers the key including its quotes.
+#[derive(Debug, PartialEq)]
+pub struct SpannedMember<'a> {
+    pub key: Cow<'a, str>,
+    pub key_span: Span,
+    pub value: SpannedValue<'a>,
+}
+
+impl<'a> SpannedValue<'a> {
+    /// Parses a document made of exactly one value, recording the span of
+    /// every value and key in it.
+    pub fn parse(origin: &'a str) -> Result<SpannedValue<'a>, JsonError<'a>> {
+        Self::from_tokenizer(Tokenizer::new(origin))
+    }
+
+    /// Like [`SpannedValue::parse`], reading from an already configured
+    /// tokenizer, e.g. one that accepts comments.
+    ///
+    /// Runs the [`Recovered`] parser and fails with the first problem it
+    /// finds. An unclosed bracket is noticed at the token that fails to
+    /// close it, so that token is what is reported.
+    pub fn from_tokenizer(tokens: Tokenizer<'a>) -> Result<SpannedValue<'a>, JsonError<'a>> {
+        let recovered = Recovered::from_tokenizer(tokens);
+        let mut diagnostics = recovered
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| !matches!(diagnostic.error, JsonError::Parse(Unclosed(_))));
+        match (diagnostics.next(), recovered.value) {
+            (None, Some(value)) => Ok(value),
+            (Some(diagnostic), _) => Err(diagnostic.error),
+            (None, None) => Err(UnexpectedEndOfTokens)?,
+        }
+    }
+
+    /// The member named `key`, with the span of its key. Keys are compared
+    /// as unescaped text, and the first one wins if the key repeats.
+    pub fn member(&self, key: &str) -> Option<&SpannedMember<'a>> {
+        match &self.kind {
+            SpannedKind::Object(members) => {
+                members.iter().find(|member| unescape(&member.key) == key)
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of the member named `key`.
+    pub fn get(&self, key: &str) -> Option<&SpannedValue<'a>> {
+        self.member(key).map(|member| &member.value)
+    }
+
+    /// The array element at `index`.
+    pub fn get_index(&self, index: usize) -> Option<&SpannedValue<'a>> {
+        match &self.kind {
+            SpannedKind::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+
+    /// Drops the spans, leaving the plain value.
+    pub fn into_value(self) -> JsonValue<'a> {
+        match self.kind {
+            SpannedKind::Scalar(value) => value,
+            SpannedKind::Object(members) => JsonValue::Object(Box::new(JsonObject {
+                children: members
+                    .into_iter()
+                    .map(|member| KeyValue {
+                        key: member.key,
+                        value: member.value.into_value(),
+                    })
+                    .collect(),
+            })),
+            SpannedKind::Array(elements) => JsonValue::Array(Box::new(JsonArray {
+                array: elements.into_iter().map(SpannedValue::into_value).collect(),
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_span {
+    use super::*;
+    use crate::error::JsonParserError::NestingTooDeep;
+    use crate::error::{expect_a_comma_or_right_bracket_after_value, expect_value};
+    use crate::parser::{Parser, MAX_DEPTH};
+    use crate::token::parse_to_tokens;
+    use crate::token::JsonToken::{RightBrace, RightBracket};
+
+    #[test]
+    fn test_spans_of_values_and_keys() {
+        let text = r#"{"name": "Jack", "list": [1, true, {}], "x": null}"#;
+        let root = SpannedValue::parse(text).unwrap();
+        assert_eq!(root.span, Span::new(0, text.len()));
+
+        let name = root.member("name").unwrap();
+        assert_eq!(&text[name.key_span.range()], "\"name\"");
+        assert_eq!(&text[name.value.span.range()], "\"Jack\"");
+
+        let list = root.get("list").unwrap();
+        assert_eq!(&text[list.span.range()], "[1, true, {}]");
+        assert_eq!(&text[list.get_index(1).unwrap().span.range()], "true");
+        assert_eq!(&text[list.get_index(2).unwrap().span.range()], "{}");
+        assert!(list.get_index(3).is_none());
+
+        let tokens = parse_to_tokens(text).unwrap();
+        let expected = Parser::new(&tokens).parse().unwrap();
+        assert_eq!(root.into_value(), expected);
+
+        assert_eq!(
+            SpannedValue::parse("[1,]").unwrap_err(),
+            JsonError::Parse(expect_value(&RightBracket))
+        );
+    }
+
+    #[test]
+    fn test_errors_are_the_first_problem() {
+        // The closing bracket, not the container it leaves unclosed.
+        assert_eq!(
+            SpannedValue::parse(r#"{"a": [1 }"#).unwrap_err(),
+            JsonError::Parse(expect_a_comma_or_right_bracket_after_value(&RightBrace))
+        );
+        assert_eq!(
+            SpannedValue::parse(" ").unwrap_err(),
+            JsonError::Parse(UnexpectedEndOfTokens)
+        );
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(
+            SpannedValue::parse(&deep).unwrap_err(),
+            JsonError::Parse(NestingTooDeep)
+        );
+        assert!(SpannedValue::parse("[1] 2").is_err());
+    }
+
+    #[test]
+    fn test_locations() {
+        let text = "{\r\n  \"é\": [1,\n    2]\n}";
+        let index = LineIndex::new(text);
+        let two = SpannedValue::parse(text)
+            .unwrap()
+            .get("é")
+            .unwrap()
+            .get_index(1)
+            .unwrap()
+            .span;
+        assert_eq!(index.location(two.start), Location { line: 3, column: 5 });
+        // "é" is two bytes but one column.
+        let colon = text.find(':').unwrap();
+        assert_eq!(index.location(colon), Location { line: 2, column: 6 });
+        assert_eq!(index.location(0), Location { line: 1, column: 1 });
+        assert_eq!(index.location(1000), Location { line: 4, column: 2 });
+        assert_eq!(index.line(2), Some("  \"é\": [1,"));
+        assert_eq!(index.line(4), Some("}"));
+        assert_eq!(index.line(5), None);
+    }
+
+    #[test]
+    fn test_members_are_found_by_unescaped_key() {
+        let text = r#"{"\u0061": 1, "a": 2, "b\/c": 3}"#;
+        let value = SpannedValue::parse(text).unwrap();
+        let member = value.member("a").unwrap();
+        assert_eq!(&text[member.key_span.range()], r#""\u0061""#);
+        assert_eq!(&text[member.value.span.range()], "1");
+        assert_eq!(&text[value.get("b/c").unwrap().span.range()], "3");
+        assert!(value.get("\\u0061").is_none());
+    }
+}
@@ -0,0 +1,140 @@
+//! Deserializing back-to-back documents with no delimiter between them,
+//! such as `{..}{..}[..]`.
+
+use crate::error::JsonError;
+use crate::event::EventReader;
+use crate::scan;
+use crate::token::Tokenizer;
+use crate::value::JsonValue;
+
+/// Iterates over the top-level values of a concatenated stream.
+///
+/// After each value, [`byte_offset`](Self::byte_offset) tells where it ended.
+/// A trailing document that is cut off is reported as an error, after which
+/// the iterator is exhausted.
+///
+/// ```
+/// use rust_json::stream::StreamDeserializer;
+///
+/// let mut stream = StreamDeserializer::new(r#"{"a": 1}[2] {"b": 3}"#);
+/// assert_eq!(stream.next().unwrap().unwrap().to_string(), r#"{"a":1}"#);
+/// assert_eq!(stream.byte_offset(), 8);
+/// assert_eq!(stream.next().unwrap().unwrap().to_string(), "[2]");
+/// assert_eq!(stream.byte_offset(), 11);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamDeserializer<'a> {
+    origin: &'a str,
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> StreamDeserializer<'a> {
+    pub fn new(origin: &'a str) -> Self {
+        Self {
+            origin,
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    /// Byte offset just past the last value returned, or 0 before the first.
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for StreamDeserializer<'a> {
+    type Item = Result<JsonValue<'a>, JsonError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = scan::skip_whitespace(self.origin.as_bytes(), self.offset);
+        if self.failed || start == self.origin.len() {
+            return None;
+        }
+        // The whole stream is tokenized from the value's start, so error
+        // offsets count from the start of the stream.
+        let mut tokens = Tokenizer::new(self.origin);
+        tokens.seek(start);
+        let mut reader = EventReader::from_tokenizer(tokens);
+        match reader.read_value() {
+            Ok(value) => {
+                self.offset = reader.offset();
+                Some(Ok(value))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_stream_deserializer {
+    use super::*;
+    use crate::error::JsonParserError::UnexpectedEndOfTokens;
+    use crate::token::TokenParseError::UnexpectedCharacter;
+
+    #[test]
+    fn test_offsets() {
+        let mut stream = StreamDeserializer::new("{\"a\":1}{\"b\":\"}\"}\n[3]\n");
+        let mut ends = vec![];
+        while let Some(value) = stream.next() {
+            ends.push((value.unwrap().to_string(), stream.byte_offset()));
+        }
+        assert_eq!(
+            ends,
+            vec![
+                (r#"{"a":1}"#.to_string(), 7),
+                (r#"{"b":"}"}"#.to_string(), 16),
+                ("[3]".to_string(), 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scalars() {
+        let values: Vec<_> = StreamDeserializer::new(r#"1 "a"[2]true null"#)
+            .map(|value| value.unwrap().to_string())
+            .collect();
+        assert_eq!(values, vec!["1", r#""a""#, "[2]", "true", "null"]);
+    }
+
+    #[test]
+    fn test_partial_trailing_document() {
+        let mut stream = StreamDeserializer::new(r#"[1] {"b": [2"#);
+        assert!(stream.next().unwrap().is_ok());
+        assert_eq!(
+            stream.next(),
+            Some(Err(JsonError::Parse(UnexpectedEndOfTokens)))
+        );
+        assert_eq!(stream.byte_offset(), 3);
+        assert_eq!(stream.next(), None);
+    }
+
+    #[test]
+    fn test_error_offsets_count_from_stream_start() {
+        let mut stream = StreamDeserializer::new("[1] [x]");
+        assert!(stream.next().unwrap().is_ok());
+        assert_eq!(
+            stream.next(),
+            Some(Err(JsonError::Token(UnexpectedCharacter { offset: 5 })))
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark_only_at_start() {
+        let values: Vec<_> = StreamDeserializer::new("\u{feff}[1] [2]")
+            .map(|value| value.unwrap().to_string())
+            .collect();
+        assert_eq!(values, vec!["[1]", "[2]"]);
+
+        let mut stream = StreamDeserializer::new("[1] \u{feff}[2]");
+        assert!(stream.next().unwrap().is_ok());
+        assert_eq!(
+            stream.next(),
+            Some(Err(JsonError::Token(UnexpectedCharacter { offset: 4 })))
+        );
+    }
+}
@@ -1,6 +1,14 @@
-use crate::token::TokenParseError::{KeyParseError, NumberParseError};
+use crate::json5;
+use crate::scan;
+use crate::span::Span;
+use crate::token::TokenParseError::{
+    ControlCharacterInString, InvalidEscape, InvalidUtf8, KeyParseError, NumberParseError,
+    UnexpectedCharacter, UnterminatedComment,
+};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use std::num::ParseFloatError;
-use std::str::CharIndices;
+use std::str;
 use JsonToken::*;
 
 #[derive(Debug)]
@@ -8,7 +16,7 @@ pub struct JsonTokenStream<'a> {
     pub(crate) tokens: Vec<JsonToken<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JsonToken<'a> {
     LeftBrace,
     RightBrace,
@@ -19,187 +27,514 @@ pub enum JsonToken<'a> {
     True,
     False,
     Null,
-    String(&'a str),
+    String(Cow<'a, str>),
     LeftBracket,
     RightBracket,
 }
+impl<'a> JsonToken<'a> {
+    pub fn into_owned(self) -> JsonToken<'static> {
+        match self {
+            LeftBrace => LeftBrace,
+            RightBrace => RightBrace,
+            DoubleQuote => DoubleQuote,
+            Comma => Comma,
+            Number(number) => Number(number),
+            Colon => Colon,
+            True => True,
+            False => False,
+            Null => Null,
+            String(str) => String(Cow::Owned(str.into_owned())),
+            LeftBracket => LeftBracket,
+            RightBracket => RightBracket,
+        }
+    }
+}
+
+/// Writes the token the way it appears in JSON text.
+impl<'a> Display for JsonToken<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeftBrace => write!(f, "{{"),
+            RightBrace => write!(f, "}}"),
+            DoubleQuote => write!(f, "\""),
+            Comma => write!(f, ","),
+            Number(number) => write!(f, "{}", number),
+            Colon => write!(f, ":"),
+            True => write!(f, "true"),
+            False => write!(f, "false"),
+            Null => write!(f, "null"),
+            String(str) => write!(f, "\"{}\"", str),
+            LeftBracket => write!(f, "["),
+            RightBracket => write!(f, "]"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TokenParseError {
     KeyParseError,
     NumberParseError(NumberParseErrorKind),
+    /// The input is not valid UTF-8 at this byte offset.
+    InvalidUtf8 {
+        offset: usize,
+    },
+    /// A `/*` comment in JSON5 input is never closed.
+    UnterminatedComment,
+    /// A byte that cannot start a token, e.g. a stray letter, `'` or `+`.
+    UnexpectedCharacter {
+        offset: usize,
+    },
+    /// A raw control character (below U+0020) inside a string; it must be
+    /// escaped.
+    ControlCharacterInString {
+        offset: usize,
+    },
+    /// A backslash that does not start one of the escapes JSON allows.
+    InvalidEscape {
+        offset: usize,
+    },
 }
 
+impl Display for TokenParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyParseError => write!(f, "unterminated string"),
+            NumberParseError(NumberParseErrorKind::DoubleDotInNumber) => {
+                write!(f, "invalid number: more than one decimal point")
+            }
+            NumberParseError(NumberParseErrorKind::NumberParseError(e)) => {
+                write!(f, "invalid number: {}", e)
+            }
+            NumberParseError(NumberParseErrorKind::InvalidHexNumber) => {
+                write!(f, "invalid hexadecimal number")
+            }
+            NumberParseError(NumberParseErrorKind::LeadingZero) => {
+                write!(f, "invalid number: leading zero")
+            }
+            NumberParseError(NumberParseErrorKind::MissingDigits) => {
+                write!(f, "invalid number: expected a digit")
+            }
+            InvalidUtf8 { offset } => write!(f, "invalid UTF-8 at byte {}", offset),
+            UnterminatedComment => write!(f, "unterminated block comment"),
+            UnexpectedCharacter { offset } => write!(f, "unexpected character at byte {}", offset),
+            ControlCharacterInString { offset } => {
+                write!(
+                    f,
+                    "unescaped control character in string at byte {}",
+                    offset
+                )
+            }
+            InvalidEscape { offset } => write!(f, "invalid escape in string at byte {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for TokenParseError {}
+
 #[derive(Debug, PartialEq)]
 pub enum NumberParseErrorKind {
     DoubleDotInNumber,
     NumberParseError(ParseFloatError),
+    InvalidHexNumber,
+    /// `01`: only `0` itself may start with a zero.
+    LeadingZero,
+    /// A sign, decimal point or exponent that is not followed by a digit.
+    MissingDigits,
+}
+
+/// How the tokenizer treats the bytes it is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Utf8Mode<'a> {
+    /// The input came from this `&str` and is already known to be valid.
+    Trusted(&'a str),
+    /// Invalid sequences are reported as [`TokenParseError::InvalidUtf8`].
+    Strict,
+    /// Invalid sequences inside strings are replaced with U+FFFD.
+    Lossy,
+}
+
+pub fn parse_to_tokens(origin: &str) -> Result<JsonTokenStream<'_>, TokenParseError> {
+    tokenize(Tokenizer::new(origin))
+}
+
+/// Tokenizes raw bytes, validating UTF-8 as it goes.
+///
+/// String tokens borrow from `origin`; the first invalid byte sequence is
+/// reported with its offset.
+pub fn parse_bytes_to_tokens(origin: &[u8]) -> Result<JsonTokenStream<'_>, TokenParseError> {
+    tokenize(Tokenizer::from_bytes(origin))
 }
 
-pub fn parse_to_tokens(origin: &str) -> Result<JsonTokenStream, TokenParseError> {
+/// Like [`parse_bytes_to_tokens`], but invalid byte sequences inside strings
+/// are replaced with U+FFFD instead of failing. Only the affected strings are
+/// copied.
+pub fn parse_bytes_to_tokens_lossy(origin: &[u8]) -> Result<JsonTokenStream<'_>, TokenParseError> {
+    tokenize(Tokenizer::from_bytes_lossy(origin))
+}
+
+/// Tokenizes relaxed JSON5 input, see [`Tokenizer::json5`].
+pub fn parse_json5_to_tokens(origin: &str) -> Result<JsonTokenStream<'_>, TokenParseError> {
+    tokenize(Tokenizer::json5(origin))
+}
+
+fn tokenize(mut tokenizer: Tokenizer) -> Result<JsonTokenStream, TokenParseError> {
     let mut tokens: Vec<JsonToken> = Vec::new();
-    let mut char_indices = origin.char_indices();
-
-    while let Some((index, char)) = char_indices.next() {
-        skip_whitespace(&mut char_indices);
-        match char {
-            '"' => {
-                let (str, end) = parse_key(char_indices.clone().as_str())?;
-                char_indices.nth(end);
-                tokens.push(String(str));
-            }
-            // number
-            '0'..='9' | '+' | '-' => {
-                let (number, _) = parse_number(&mut char_indices, index, origin)?;
-                tokens.push(Number(number));
-            }
-            // 匹配 true false null
-            't' | 'f' | 'n' => {
-                if parse_const_if_ok_then_skip(
-                    char_indices.clone().as_str(),
-                    "rue",
-                    &mut char_indices,
-                )
-                .is_some()
-                {
-                    tokens.push(True);
-                }
-                if parse_const_if_ok_then_skip(
-                    char_indices.clone().as_str(),
-                    "alse",
-                    &mut char_indices,
-                )
-                .is_some()
-                {
-                    tokens.push(False);
-                }
-                if parse_const_if_ok_then_skip(
-                    char_indices.clone().as_str(),
-                    "ull",
-                    &mut char_indices,
-                )
-                .is_some()
-                {
-                    tokens.push(JsonToken::Null);
-                }
-            }
-            '{' => tokens.push(LeftBrace),
-            '}' => tokens.push(RightBrace),
-            '[' => tokens.push(LeftBracket),
-            ']' => tokens.push(RightBracket),
-            ',' => tokens.push(Comma),
-            ':' => tokens.push(Colon),
-            _ => {}
-        }
+    while let Some(token) = tokenizer.next_token()? {
+        tokens.push(token);
     }
     Ok(JsonTokenStream { tokens })
 }
 
-fn parse_number(
-    char_indices: &mut CharIndices<'_>,
-    current_index: usize,
-    origin: &str,
-) -> Result<(f64, usize), TokenParseError> {
-    let peek = char_indices.clone().peekable();
-    let start = current_index;
-    let mut end = current_index;
-    let mut has_dot = false;
-
-    for (_index, char) in peek {
-        if char == '.' {
-            if has_dot {
-                return Err(NumberParseError(NumberParseErrorKind::DoubleDotInNumber));
-            } else {
-                has_dot = true;
-            }
+/// Lazily produces the tokens of its input, one at a time.
+///
+/// This is what [`parse_to_tokens`] collects; use it directly to look at a
+/// document without materializing every token. It stops after the first
+/// error.
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    bytes: &'a [u8],
+    mode: Utf8Mode<'a>,
+    index: usize,
+    json5: bool,
+    comments: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(origin: &'a str) -> Self {
+        Self {
+            bytes: origin.as_bytes(),
+            mode: Utf8Mode::Trusted(origin),
+            index: 0,
+            json5: false,
+            comments: false,
         }
+    }
 
-        if char.is_ascii_digit() || char == '.' {
-            end = _index;
-        } else {
-            break;
+    /// See [`parse_bytes_to_tokens`].
+    pub fn from_bytes(origin: &'a [u8]) -> Self {
+        Self {
+            bytes: origin,
+            mode: Utf8Mode::Strict,
+            index: 0,
+            json5: false,
+            comments: false,
         }
     }
 
-    // Sync the state to char_indices
-    if end - start > 0 {
-        char_indices.nth(end - start - 1);
+    /// See [`parse_bytes_to_tokens_lossy`].
+    pub fn from_bytes_lossy(origin: &'a [u8]) -> Self {
+        Self {
+            bytes: origin,
+            mode: Utf8Mode::Lossy,
+            index: 0,
+            json5: false,
+            comments: false,
+        }
     }
 
-    let number_str = &origin[start..end + 1];
-    match number_str.parse::<f64>() {
-        Ok(number) => Ok((number, end)),
-        Err(e) => Err(NumberParseError(NumberParseErrorKind::NumberParseError(e))),
+    /// Accepts the JSON5 extensions on top of JSON: `//` and `/* */`
+    /// comments, single-quoted strings, unquoted keys, hex numbers, leading
+    /// and trailing decimal points, explicit `+` signs, `Infinity` and `NaN`.
+    ///
+    /// Use it with [`Parser::new_json5`](crate::parser::Parser::new_json5),
+    /// which also allows trailing commas.
+    pub fn json5(origin: &'a str) -> Self {
+        Self {
+            json5: true,
+            comments: true,
+            ..Self::new(origin)
+        }
     }
-}
 
-fn parse_key(input: &str) -> Result<(&str, usize), TokenParseError> {
-    let char_indices = input.char_indices();
-    let start_index = 0;
-    let mut end_index = None;
-    let mut ignore_next = false;
-
-    for (index, char) in char_indices {
-        if ignore_next {
-            ignore_next = false;
-            end_index = Some(index);
-            continue;
+    /// Accepts `//` and `/* */` comments, as found in JSONC configuration
+    /// files, but is otherwise strict.
+    pub fn jsonc(origin: &'a str) -> Self {
+        Self {
+            comments: true,
+            ..Self::new(origin)
         }
+    }
 
-        match char {
-            '\\' => {
-                ignore_next = true;
-            }
-            '"' => {
-                // This is the closing quote of the key
-                end_index = Some(index);
-                break;
-            }
-            _ => continue,
+    /// Byte offset just past the last token produced.
+    pub fn offset(&self) -> usize {
+        self.index
+    }
+
+    /// Continues reading at byte `index` of the same input, so that offsets
+    /// stay relative to its start and a byte order mark is only allowed at 0.
+    pub(crate) fn seek(&mut self, index: usize) {
+        self.index = index;
+    }
+
+    /// Like [`Iterator::next`], but also returns the byte range the token
+    /// was read from.
+    pub fn next_spanned(&mut self) -> Option<Result<(Span, JsonToken<'a>), TokenParseError>> {
+        let mut start = self.index;
+        let token = self.read_token(&mut start);
+        if token.is_err() {
+            self.index = self.bytes.len();
         }
+        token
+            .map(|token| token.map(|token| (Span::new(start, self.index), token)))
+            .transpose()
+    }
+
+    /// Like [`Tokenizer::next_spanned`], but a bad token does not end the
+    /// stream: its error comes back with the bytes that were skipped, and
+    /// reading resumes after them.
+    pub(crate) fn next_recovering(
+        &mut self,
+    ) -> Option<Result<(Span, JsonToken<'a>), (Span, TokenParseError)>> {
+        let mut start = self.index;
+        let error = match self.read_token(&mut start) {
+            Ok(token) => return token.map(|token| Ok((Span::new(start, self.index), token))),
+            Err(error) => error,
+        };
+        let bytes = self.bytes;
+        self.index = match error {
+            // Nothing after an unclosed string or comment can be trusted.
+            KeyParseError | UnterminatedComment => bytes.len(),
+            _ if bytes[start] == b'"' => {
+                scan::find_string_end(bytes, start + 1).map_or(bytes.len(), |end| end + 1)
+            }
+            _ => bytes[start + 1..]
+                .iter()
+                .position(|byte| byte.is_ascii_whitespace() || b",:[]{}\"".contains(byte))
+                .map_or(bytes.len(), |offset| start + 1 + offset),
+        };
+        Some(Err((Span::new(start, self.index), error)))
     }
 
-    match end_index {
-        Some(end) => Ok((&input[start_index..end], end)),
-        _ => Err(KeyParseError),
+    fn next_token(&mut self) -> Result<Option<JsonToken<'a>>, TokenParseError> {
+        self.read_token(&mut 0)
+    }
+
+    /// Reads the next token, storing the offset it starts at in `start`.
+    fn read_token(&mut self, start: &mut usize) -> Result<Option<JsonToken<'a>>, TokenParseError> {
+        let bytes = self.bytes;
+        loop {
+            let index = scan::skip_whitespace(bytes, self.index);
+            *start = index;
+            let Some(&byte) = bytes.get(index) else {
+                self.index = index;
+                return Ok(None);
+            };
+            self.index = index + 1;
+            let token = match byte {
+                b'/' if self.comments => match json5::skip_comment(bytes, index)? {
+                    Some(end) => {
+                        self.index = end;
+                        continue;
+                    }
+                    None => return Err(UnexpectedCharacter { offset: index }),
+                },
+                // JSON5 also counts vertical tab and form feed as whitespace.
+                0x0B | 0x0C if self.json5 => continue,
+                b'\'' if self.json5 => {
+                    let end = json5::find_single_quoted_end(bytes, index + 1)?;
+                    self.index = end + 1;
+                    let content = decode(bytes, index + 1, end, self.mode)?;
+                    String(json5::to_json_escapes(content, index + 1)?)
+                }
+                b'0'..=b'9' | b'+' | b'-' | b'.' if self.json5 => {
+                    let (number, end) = json5::parse_number(bytes, index)?;
+                    self.index = end;
+                    Number(number)
+                }
+                b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'$' if self.json5 => {
+                    let (token, end) = json5::parse_identifier(bytes, index)?;
+                    self.index = end;
+                    token
+                }
+                b'"' => {
+                    let end = scan::find_string_end(bytes, index + 1).ok_or(KeyParseError)?;
+                    if !self.json5 {
+                        check_string(bytes, index + 1, end)?;
+                    }
+                    self.index = end + 1;
+                    let content = decode(bytes, index + 1, end, self.mode)?;
+                    if self.json5 {
+                        String(json5::to_json_escapes(content, index + 1)?)
+                    } else {
+                        String(content)
+                    }
+                }
+                // number
+                b'0'..=b'9' | b'-' => {
+                    let (number, end) = parse_number(bytes, index)?;
+                    self.index = end;
+                    Number(number)
+                }
+                // 匹配 true false null
+                b't' | b'f' | b'n' => match parse_const(&bytes[index..]) {
+                    Some((token, len)) => {
+                        self.index = index + len;
+                        token
+                    }
+                    None => return Err(UnexpectedCharacter { offset: index }),
+                },
+                b'{' => LeftBrace,
+                b'}' => RightBrace,
+                b'[' => LeftBracket,
+                b']' => RightBracket,
+                b',' => Comma,
+                b':' => Colon,
+                // A byte order mark is allowed in front of the document.
+                0xEF if index == 0 && bytes.starts_with(BOM) => {
+                    self.index = BOM.len();
+                    continue;
+                }
+                // Stray non-ASCII outside a string is reported as invalid
+                // UTF-8 first, if it is.
+                0x80.. if self.mode == Utf8Mode::Strict => {
+                    let end = (index + 4).min(bytes.len());
+                    let valid = match str::from_utf8(&bytes[index..end]) {
+                        Ok(_) => end - index,
+                        Err(e) => e.valid_up_to(),
+                    };
+                    if valid == 0 {
+                        return Err(InvalidUtf8 { offset: index });
+                    }
+                    return Err(UnexpectedCharacter { offset: index });
+                }
+                _ => return Err(UnexpectedCharacter { offset: index }),
+            };
+            return Ok(Some(token));
+        }
     }
 }
 
-fn parse_const_if_ok_then_skip(input: &str, pattern: &str, chars: &mut CharIndices) -> Option<()> {
-    match parse_const(input, pattern) {
-        Some(size) => {
-            chars.nth(size - 1);
-            Some(())
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<JsonToken<'a>, TokenParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.next_token();
+        if token.is_err() {
+            self.index = self.bytes.len();
         }
-        None => None,
+        token.transpose()
     }
 }
 
-fn parse_const(input: &str, const_str: &str) -> Option<usize> {
-    if input.starts_with(const_str) {
-        Some(const_str.len()) // Return the number of characters to skip
-    } else {
-        None
+/// Decodes the string content in `bytes[start..end]` according to `mode`.
+fn decode<'a>(
+    bytes: &'a [u8],
+    start: usize,
+    end: usize,
+    mode: Utf8Mode<'a>,
+) -> Result<Cow<'a, str>, TokenParseError> {
+    let content = &bytes[start..end];
+    match mode {
+        // `start` and `end` sit next to ASCII quotes, so they are char
+        // boundaries of the original text.
+        Utf8Mode::Trusted(text) => Ok(Cow::Borrowed(&text[start..end])),
+        Utf8Mode::Lossy => Ok(std::string::String::from_utf8_lossy(content)),
+        Utf8Mode::Strict => match str::from_utf8(content) {
+            Ok(str) => Ok(Cow::Borrowed(str)),
+            Err(e) => Err(InvalidUtf8 {
+                offset: start + e.valid_up_to(),
+            }),
+        },
     }
 }
 
-fn skip_whitespace(chars: &mut CharIndices) {
-    let mut whitespace_count = 0;
-    let chars_clone = chars.clone();
-    for (_, char) in chars_clone {
-        if char.is_ascii_whitespace() {
-            whitespace_count += 1;
-        } else {
-            break;
+/// The UTF-8 byte order mark.
+pub(crate) const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Checks the content of a string, `bytes[start..end]`, for raw control
+/// characters and escapes JSON does not know.
+pub(crate) fn check_string(bytes: &[u8], start: usize, end: usize) -> Result<(), TokenParseError> {
+    let mut index = start;
+    while let Some(offset) = bytes[index..end]
+        .iter()
+        .position(|&byte| byte < 0x20 || byte == b'\\')
+    {
+        index += offset;
+        if bytes[index] != b'\\' {
+            return Err(ControlCharacterInString { offset: index });
         }
+        index += match bytes[index + 1] {
+            b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => 2,
+            // The closing quote is not a hex digit, so four of them always
+            // end inside the string.
+            b'u' if bytes
+                .get(index + 2..index + 6)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) =>
+            {
+                6
+            }
+            _ => return Err(InvalidEscape { offset: index }),
+        };
     }
-    if whitespace_count > 0 {
-        chars.nth(whitespace_count - 1); // -1 because nth is 0-indexed
+    Ok(())
+}
+
+/// Parses the number starting at `start`, returning it together with the
+/// index just past its last byte. Follows the JSON grammar exactly: an
+/// optional `-`, no leading zeros, and digits on both sides of the decimal
+/// point and after the exponent marker.
+pub(crate) fn parse_number(bytes: &[u8], start: usize) -> Result<(f64, usize), TokenParseError> {
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let missing_digits = NumberParseError(NumberParseErrorKind::MissingDigits);
+
+    let mut end = start;
+    if bytes[end] == b'-' {
+        end += 1;
+    }
+    end = match bytes.get(end) {
+        Some(b'0') if bytes.get(end + 1).is_some_and(u8::is_ascii_digit) => {
+            return Err(NumberParseError(NumberParseErrorKind::LeadingZero))
+        }
+        Some(b'0') => end + 1,
+        Some(b'1'..=b'9') => digits(end),
+        _ => return Err(missing_digits),
+    };
+    if bytes.get(end) == Some(&b'.') {
+        let fraction = digits(end + 1);
+        if fraction == end + 1 {
+            return Err(missing_digits);
+        }
+        end = fraction;
+        if bytes.get(end) == Some(&b'.') {
+            return Err(NumberParseError(NumberParseErrorKind::DoubleDotInNumber));
+        }
     }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent = end + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        end = digits(exponent);
+        if end == exponent {
+            return Err(missing_digits);
+        }
+    }
+
+    // Only ASCII signs, digits, dots and exponent markers were consumed.
+    let number_str = str::from_utf8(&bytes[start..end]).unwrap_or_default();
+    match number_str.parse::<f64>() {
+        Ok(number) => Ok((number, end)),
+        Err(e) => Err(NumberParseError(NumberParseErrorKind::NumberParseError(e))),
+    }
+}
+
+fn parse_const(input: &[u8]) -> Option<(JsonToken<'static>, usize)> {
+    [("true", True), ("false", False), ("null", Null)]
+        .into_iter()
+        .find(|(const_str, _)| input.starts_with(const_str.as_bytes()))
+        .map(|(const_str, token)| (token, const_str.len()))
 }
 
 /// test for parse_to_tokens
+#[cfg(test)]
 mod tests_parse_to_tokens {
     use super::*;
+    use std::borrow::Cow::Borrowed;
 
     #[test]
     fn test_parse_to_tokens() {
@@ -219,30 +554,30 @@ mod tests_parse_to_tokens {
             tokens.tokens,
             vec![
                 LeftBrace,
-                String("name"),
+                String(Borrowed("name")),
                 Colon,
-                String("Jack (\\\"Bee\\\") Nimble"),
+                String(Borrowed("Jack (\\\"Bee\\\") Nimble")),
                 Comma,
-                String("format"),
+                String(Borrowed("format")),
                 Colon,
                 LeftBrace,
-                String("type"),
+                String(Borrowed("type")),
                 Colon,
-                String("rect"),
+                String(Borrowed("rect")),
                 Comma,
-                String("width"),
+                String(Borrowed("width")),
                 Colon,
                 Number(1920.0),
                 Comma,
-                String("height"),
+                String(Borrowed("height")),
                 Colon,
                 Number(1080.0),
                 Comma,
-                String("interlace"),
+                String(Borrowed("interlace")),
                 Colon,
                 False,
                 Comma,
-                String("array"),
+                String(Borrowed("array")),
                 Colon,
                 LeftBracket,
                 Number(1.0),
@@ -271,3 +606,38 @@ mod tests_parse_to_tokens {
         );
     }
 }
+
+#[cfg(test)]
+mod tests_parse_bytes_to_tokens {
+    use super::*;
+    use std::borrow::Cow::{Borrowed, Owned};
+
+    #[test]
+    fn test_invalid_utf8_is_reported_with_offset() {
+        let json = b"{\"key\": \"va\xFFlue\"}";
+        assert_eq!(
+            parse_bytes_to_tokens(json).unwrap_err(),
+            InvalidUtf8 { offset: 11 }
+        );
+        assert_eq!(
+            parse_bytes_to_tokens(b"[1, \xC3]").unwrap_err(),
+            InvalidUtf8 { offset: 4 }
+        );
+    }
+
+    #[test]
+    fn test_valid_utf8_borrows() {
+        let json = "{\"clé\": \"värde\"}";
+        let tokens = parse_bytes_to_tokens(json.as_bytes()).unwrap();
+        assert_eq!(tokens.tokens[1], String(Borrowed("clé")));
+        assert_eq!(tokens.tokens[3], String(Borrowed("värde")));
+    }
+
+    #[test]
+    fn test_lossy_replaces_invalid_sequences() {
+        let json = b"[\"ok\", \"va\xFFlue\"]";
+        let tokens = parse_bytes_to_tokens_lossy(json).unwrap();
+        assert_eq!(tokens.tokens[1], String(Borrowed("ok")));
+        assert_eq!(tokens.tokens[3], String(Owned("va\u{FFFD}lue".to_string())));
+    }
+}
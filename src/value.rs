@@ -1,11 +1,19 @@
 use crate::array::JsonArray;
+use crate::convert::{FromJson, FromJsonError, Lookup};
+use crate::error::JsonError;
+use crate::escape::unescape;
 use crate::object::JsonObject;
-use std::fmt::{Display, Formatter};
+use crate::parser::Parser;
+use crate::token::parse_to_tokens;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Write};
+use std::mem;
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JsonValue<'a> {
     Number(Box<f64>),
-    String(Box<&'a str>),
+    String(Box<Cow<'a, str>>),
     Object(Box<JsonObject<'a>>),
     Array(Box<JsonArray<'a>>),
     True,
@@ -15,17 +23,148 @@ pub enum JsonValue<'a> {
 }
 
 impl<'a> JsonValue<'a> {
-    pub fn try_as_array(&self) -> Option<&JsonArray> {
+    pub fn try_as_array(&self) -> Option<&JsonArray<'a>> {
         match self {
             JsonValue::Array(array) => Some(array),
             _ => None,
         }
     }
+
+    /// The member `key` (unescaped text) of this object, to be converted
+    /// with [`Lookup::parse`]: `value.get("port").parse::<u16>()`.
+    pub fn get(&self, key: &str) -> Lookup<'_, 'a> {
+        Lookup::new(self).get(key)
+    }
+
+    /// Converts the value with [`FromJson`].
+    pub fn parse<'v, T: FromJson<'v>>(&'v self) -> Result<T, FromJsonError> {
+        T::from_json(self)
+    }
+
+    /// Compares two values as data rather than as text: strings by what
+    /// they unescape to, numbers by value, and objects regardless of member
+    /// order. `==` instead compares the stored form.
+    pub fn equivalent(&self, other: &JsonValue) -> bool {
+        match (self, other) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => a == b,
+            (JsonValue::String(a), JsonValue::String(b)) => unescape(a) == unescape(b),
+            (JsonValue::Array(a), JsonValue::Array(b)) => {
+                a.array.len() == b.array.len()
+                    && a.array.iter().zip(&b.array).all(|(a, b)| a.equivalent(b))
+            }
+            (JsonValue::Object(a), JsonValue::Object(b)) if a.len() != b.len() => false,
+            (JsonValue::Object(a), JsonValue::Object(b)) => {
+                let same_order = a
+                    .children
+                    .iter()
+                    .zip(&b.children)
+                    .all(|(a, b)| unescape(&a.key) == unescape(&b.key));
+                if same_order {
+                    return a
+                        .children
+                        .iter()
+                        .zip(&b.children)
+                        .all(|(a, b)| a.value.equivalent(&b.value));
+                }
+                let positions = b.positions();
+                a.children.iter().all(|member| {
+                    positions
+                        .get(&unescape(&member.key))
+                        .is_some_and(|&index| member.value.equivalent(&b.children[index].value))
+                })
+            }
+            (a, b) => mem::discriminant(a) == mem::discriminant(b),
+        }
+    }
+
+    /// Copies every borrowed string, detaching the value from the input it
+    /// was parsed from.
+    pub fn into_owned(self) -> JsonValue<'static> {
+        match self {
+            JsonValue::Number(number) => JsonValue::Number(number),
+            JsonValue::String(str) => JsonValue::String(Box::new(Cow::Owned(str.into_owned()))),
+            JsonValue::Object(obj) => JsonValue::Object(Box::new(obj.into_owned())),
+            JsonValue::Array(arr) => JsonValue::Array(Box::new(arr.into_owned())),
+            JsonValue::True => JsonValue::True,
+            JsonValue::False => JsonValue::False,
+            JsonValue::Null => JsonValue::Null,
+            JsonValue::Empty => JsonValue::Empty,
+        }
+    }
+
+    /// Formats the value over several lines, one member or element per
+    /// line, indenting each level by `indent` spaces. Empty containers stay
+    /// on one line.
+    pub fn to_string_pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, &" ".repeat(indent), 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: &str, depth: usize) {
+        let newline = |out: &mut String, depth: usize| {
+            out.push('\n');
+            (0..depth).for_each(|_| out.push_str(indent));
+        };
+        match self {
+            JsonValue::Object(obj) if !obj.children.is_empty() => {
+                out.push('{');
+                for (index, child) in obj.children.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    let _ = write!(out, "\"{}\": ", child.key);
+                    child.value.write_pretty(out, indent, depth + 1);
+                }
+                newline(out, depth);
+                out.push('}');
+            }
+            JsonValue::Array(arr) if !arr.array.is_empty() => {
+                out.push('[');
+                for (index, value) in arr.array.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    newline(out, depth + 1);
+                    value.write_pretty(out, indent, depth + 1);
+                }
+                newline(out, depth);
+                out.push(']');
+            }
+            // Writing to a `String` cannot fail.
+            value => {
+                let _ = write!(out, "{}", value);
+            }
+        }
+    }
+}
+
+/// Parses a document into a value that owns its strings.
+impl FromStr for JsonValue<'static> {
+    type Err = JsonError<'static>;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let tokens = parse_to_tokens(text)?;
+        match Parser::new(&tokens).parse() {
+            Ok(value) => Ok(value.into_owned()),
+            Err(e) => Err(e.into_owned().into()),
+        }
+    }
+}
+
+/// Parses a document the tests know to be valid.
+#[cfg(test)]
+pub(crate) fn parse(text: &str) -> JsonValue<'static> {
+    text.parse().unwrap()
 }
 
 impl<'a> Display for JsonValue<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            // JSON cannot write NaN or the infinities, so they are written
+            // as `null`, as `ToJson` and serde serialization do.
+            JsonValue::Number(number) if !number.is_finite() => write!(f, "null"),
             JsonValue::Number(number) => write!(f, "{}", number),
             JsonValue::String(str) => write!(f, "\"{}\"", str),
             JsonValue::Object(obj) => write!(f, "{}", obj),
@@ -37,3 +176,124 @@ impl<'a> Display for JsonValue<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests_value {
+    use super::*;
+    use crate::array::JsonArray;
+    use crate::keyvalue::KeyValue;
+    use crate::parser::Parser;
+    use crate::read::from_reader;
+    use crate::recover::Recovered;
+    use crate::token::{parse_bytes_to_tokens, parse_json5_to_tokens};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    /// Strings are stored escaped, so generate text and escape it the way a
+    /// serializer would.
+    fn escaped() -> impl Strategy<Value = Cow<'static, str>> {
+        any::<String>().prop_map(|text| {
+            let mut raw = String::new();
+            for char in text.chars() {
+                match char {
+                    '"' => raw.push_str("\\\""),
+                    '\\' => raw.push_str("\\\\"),
+                    '\n' => raw.push_str("\\n"),
+                    '\t' => raw.push_str("\\t"),
+                    char if char < ' ' => {
+                        let _ = write!(raw, "\\u{:04x}", char as u32);
+                    }
+                    char => raw.push(char),
+                }
+            }
+            Cow::Owned(raw)
+        })
+    }
+
+    fn value() -> impl Strategy<Value = JsonValue<'static>> {
+        let leaf = prop_oneof![
+            Just(JsonValue::True),
+            Just(JsonValue::False),
+            Just(JsonValue::Null),
+            any::<f64>()
+                .prop_filter("JSON has no NaN or infinity", |n| n.is_finite())
+                .prop_map(|n| JsonValue::Number(Box::new(n))),
+            escaped().prop_map(|s| JsonValue::String(Box::new(s))),
+        ];
+        leaf.prop_recursive(6, 64, 8, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..8)
+                    .prop_map(|array| JsonValue::Array(Box::new(JsonArray { array }))),
+                vec((escaped(), inner), 0..8).prop_map(|members| {
+                    let children = members
+                        .into_iter()
+                        .map(|(key, value)| KeyValue { key, value })
+                        .collect();
+                    JsonValue::Object(Box::new(JsonObject { children }))
+                }),
+            ]
+        })
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let a = parse(r#"{"a": [1, "\u0041"], "b": {}}"#);
+        assert!(a.equivalent(&parse(r#"{"b": {}, "a": [1.0, "A"]}"#)));
+        assert!(!a.equivalent(&parse(r#"{"a": [1, "A"], "c": {}}"#)));
+        assert!(!a.equivalent(&parse(r#"{"a": [1, "A"]}"#)));
+        assert!(!parse("[1, 2]").equivalent(&parse("[2, 1]")));
+        assert!(!parse("true").equivalent(&parse("false")));
+        assert!(parse("null").equivalent(&parse("null")));
+    }
+
+    #[test]
+    fn test_non_finite_numbers_are_null() {
+        let array = [f64::NAN, f64::INFINITY, 1.5].map(|n| JsonValue::Number(Box::new(n)));
+        let value = JsonValue::Array(Box::new(JsonArray {
+            array: array.into(),
+        }));
+        assert_eq!(value.to_string(), "[null,null,1.5]");
+        assert_eq!(value.to_string_pretty(1), "[\n null,\n null,\n 1.5\n]");
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(value in value()) {
+            prop_assert_eq!(parse(&value.to_string()), value.clone());
+            prop_assert!(value.equivalent(&value));
+        }
+
+        #[test]
+        fn test_pretty_round_trip(value in value(), indent in 0..4usize) {
+            prop_assert_eq!(parse(&value.to_string_pretty(indent)), value);
+        }
+
+        /// Damaged documents may be rejected, but must never panic, and
+        /// `from_reader` must agree with the parser on them.
+        #[test]
+        fn test_damaged_input_does_not_panic(
+            value in value(),
+            cut in any::<prop::sample::Index>(),
+            edits in vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+        ) {
+            let mut bytes = value.to_string().into_bytes();
+            for (index, byte) in edits {
+                let index = index.index(bytes.len());
+                bytes[index] = byte;
+            }
+            bytes.truncate(cut.index(bytes.len() + 1));
+
+            let parsed = match parse_bytes_to_tokens(&bytes) {
+                Ok(tokens) => Parser::new(&tokens).parse().ok().map(JsonValue::into_owned),
+                Err(_) => None,
+            };
+            prop_assert_eq!(from_reader(bytes.as_slice()).ok(), parsed);
+            let _ = Recovered::from_tokenizer(crate::token::Tokenizer::from_bytes(&bytes));
+            if let Ok(text) = std::str::from_utf8(&bytes) {
+                if let Ok(tokens) = parse_json5_to_tokens(text) {
+                    let _ = Parser::new_json5(&tokens).parse();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust_json"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_fmt_and_minify() {
+    let output = run(&["fmt"], r#"{"a": [1, {}], "b": {"c": null}}"#);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": {\n    \"c\": null\n  }\n}\n"
+    );
+
+    let output = run(&["minify"], "[ 1,\n 2 ]");
+    assert_eq!(output.stdout, b"[1,2]\n");
+
+    assert_eq!(
+        run(&["minify", "--check"], "[1,2]\n").status.code(),
+        Some(0)
+    );
+    let output = run(&["fmt", "--check"], "[1,2]\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_numbers_are_kept_as_written() {
+    let input = r#"{"id": 9007199254740993, "price": 1.50, "n": 1e3, "z": -0.0}"#;
+    let output = run(&["minify"], input);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"id\":9007199254740993,\"price\":1.50,\"n\":1e3,\"z\":-0.0}\n"
+    );
+    let output = run(&["fmt"], "[9007199254740993, 1.50]");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[\n  9007199254740993,\n  1.50\n]\n"
+    );
+    assert_eq!(
+        run(&["minify", "--check"], "[9007199254740993,1.50]\n")
+            .status
+            .code(),
+        Some(0)
+    );
+
+    let path = std::env::temp_dir().join(format!("rust_json_numbers_{}.json", std::process::id()));
+    std::fs::write(&path, "[9007199254740993 , 1.50]").unwrap();
+    let status = run(&["minify", "-w", path.to_str().unwrap()], "").status;
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(written, "[9007199254740993,1.50]\n");
+}
+
+#[test]
+fn test_validate_reports_every_error() {
+    let output = run(&["validate"], "{\"a\": 1 \"b\": [2,]}");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("error:").count(), 2);
+    assert!(stderr.contains("--> <stdin>:1:9\n"));
+    assert!(stderr.contains("= help: did you forget a comma?"));
+
+    assert_eq!(
+        run(&["validate", "tests/json1.json"], "").status.code(),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_usage_and_io_errors() {
+    assert_eq!(run(&["frobnicate"], "").status.code(), Some(2));
+    assert_eq!(
+        run(&["fmt", "--check", "--write"], "").status.code(),
+        Some(2)
+    );
+    let output = run(&["validate", "tests/does-not-exist.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_bench() {
+    // `tester.py` runs the binary with just a count.
+    let output = run(&["2"], "");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("MiB/s").count(), 9);
+
+    let output = run(&["bench", "1", "tests/json2.json"], "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("tests/json2.json (260 bytes, 1 iterations)\n  tokenize"));
+}
@@ -0,0 +1,136 @@
+//! Runs the parser against the JSONTestSuite corpus in
+//! `tests/JSONTestSuite/test_parsing`: every `y_` file must parse, every `n_`
+//! file must be rejected, and every `i_` file (where RFC 8259 leaves the
+//! choice to the implementation) must behave as documented in [`IMPLEMENTATION_DEFINED`].
+//! `read::from_reader`, fed one byte at a time, must agree with the parser
+//! on every file.
+
+use rust_json::parser::Parser;
+use rust_json::read::from_reader;
+use rust_json::token::parse_bytes_to_tokens;
+use std::fs;
+use std::io::{self, Read};
+
+const CORPUS: &str = "tests/JSONTestSuite/test_parsing";
+
+/// What we do with each `i_` case, and why.
+const IMPLEMENTATION_DEFINED: &[(&str, bool)] = &[
+    // Numbers are read as `f64`: out of range values become infinite or zero
+    // and long integers lose precision, but they are all accepted.
+    ("i_number_double_huge_neg_exp.json", true),
+    ("i_number_huge_exp.json", true),
+    ("i_number_neg_int_huge_exp.json", true),
+    ("i_number_pos_double_huge_exp.json", true),
+    ("i_number_real_neg_overflow.json", true),
+    ("i_number_real_pos_overflow.json", true),
+    ("i_number_real_underflow.json", true),
+    ("i_number_too_big_neg_int.json", true),
+    ("i_number_too_big_pos_int.json", true),
+    ("i_number_very_big_negative_int.json", true),
+    // Strings are kept escaped, so a `\u` escape only has to be four hex
+    // digits; unpaired surrogates are passed through untouched.
+    ("i_object_key_lone_2nd_surrogate.json", true),
+    ("i_string_1st_surrogate_but_2nd_missing.json", true),
+    ("i_string_1st_valid_surrogate_2nd_invalid.json", true),
+    ("i_string_incomplete_surrogate_and_escape_valid.json", true),
+    ("i_string_incomplete_surrogate_pair.json", true),
+    ("i_string_incomplete_surrogates_escape_valid.json", true),
+    ("i_string_invalid_lonely_surrogate.json", true),
+    ("i_string_invalid_surrogate.json", true),
+    ("i_string_inverted_surrogates_U+1D11E.json", true),
+    ("i_string_lone_second_surrogate.json", true),
+    // The input must be UTF-8; anything else is an `InvalidUtf8` error.
+    ("i_string_UTF-16LE_with_BOM.json", false),
+    ("i_string_UTF-8_invalid_sequence.json", false),
+    ("i_string_UTF8_surrogate_U+D800.json", false),
+    ("i_string_invalid_utf-8.json", false),
+    ("i_string_iso_latin_1.json", false),
+    ("i_string_lone_utf8_continuation_byte.json", false),
+    ("i_string_not_in_unicode_range.json", false),
+    ("i_string_overlong_sequence_2_bytes.json", false),
+    ("i_string_overlong_sequence_6_bytes.json", false),
+    ("i_string_overlong_sequence_6_bytes_null.json", false),
+    ("i_string_truncated-utf-8.json", false),
+    ("i_string_utf16BE_no_BOM.json", false),
+    ("i_string_utf16LE_no_BOM.json", false),
+    // Nesting is limited to `MAX_DEPTH` (128) levels.
+    ("i_structure_500_nested_arrays.json", false),
+    // A leading byte order mark is skipped.
+    ("i_structure_UTF-8_BOM_empty_object.json", true),
+];
+
+/// Hands out its input one byte per read, so every token is split.
+struct ByteAtATime<'a>(&'a [u8]);
+
+impl<'a> Read for ByteAtATime<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.0.len()).min(1);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+fn accepts(name: &str, bytes: &[u8]) -> bool {
+    let parsed = match parse_bytes_to_tokens(bytes) {
+        Ok(tokens) => Parser::new(&tokens)
+            .parse()
+            .ok()
+            .map(|value| value.into_owned()),
+        Err(_) => None,
+    };
+    let read = from_reader(ByteAtATime(bytes)).ok();
+    assert_eq!(
+        read, parsed,
+        "from_reader disagrees with the parser on {}",
+        name
+    );
+    parsed.is_some()
+}
+
+fn corpus(prefix: &str) -> Vec<(String, Vec<u8>)> {
+    let mut files: Vec<_> = fs::read_dir(CORPUS)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with(prefix) && name.ends_with(".json"))
+        .map(|name| {
+            let bytes = fs::read(format!("{}/{}", CORPUS, name)).unwrap();
+            (name, bytes)
+        })
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no {}*.json files in {}", prefix, CORPUS);
+    files
+}
+
+#[test]
+fn test_accepts_every_y_case() {
+    let rejected: Vec<_> = corpus("y_")
+        .into_iter()
+        .filter(|(name, bytes)| !accepts(name, bytes))
+        .map(|(name, _)| name)
+        .collect();
+    assert!(rejected.is_empty(), "rejected: {:#?}", rejected);
+}
+
+#[test]
+fn test_rejects_every_n_case() {
+    let accepted: Vec<_> = corpus("n_")
+        .into_iter()
+        .filter(|(name, bytes)| accepts(name, bytes))
+        .map(|(name, _)| name)
+        .collect();
+    assert!(accepted.is_empty(), "accepted: {:#?}", accepted);
+}
+
+#[test]
+fn test_i_cases_behave_as_documented() {
+    for (name, bytes) in corpus("i_") {
+        let expected = IMPLEMENTATION_DEFINED
+            .iter()
+            .find(|(case, _)| *case == name)
+            .unwrap_or_else(|| panic!("{} is not in IMPLEMENTATION_DEFINED", name))
+            .1;
+        assert_eq!(accepts(&name, &bytes), expected, "{}", name);
+    }
+}
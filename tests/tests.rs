@@ -1,41 +1,87 @@
 #[cfg(test)]
 mod test {
     use rust_json::parser::Parser;
+    use rust_json::read::from_reader;
     use rust_json::token::parse_to_tokens;
 
     #[test]
     fn test() {
         use std::fs;
-        use std::time::Instant;
-
-        let mut token_times = vec![];
-        let mut parse_times = vec![];
-        let time = 1;
-        for _i in 0..time {
-            let json_text = fs::read_to_string(format!("tests/json{}.json", 1));
-            if let Ok(text) = json_text {
-                let start = Instant::now();
-                let tokens = parse_to_tokens(&text).unwrap();
-                let token_time = start.elapsed();
-                token_times.push(token_time);
-
-                let start = Instant::now();
-                let _object = Parser::new(&tokens).parse();
-                println!("{}", _object.unwrap());
-                let parse_time = start.elapsed();
-                parse_times.push(parse_time);
-            } else {
-                break;
-            }
+
+        // Timings live in `cargo bench` and `rust_json bench`; this only
+        // checks that every fixture parses and survives a round trip.
+        for i in 1..=3 {
+            let text = fs::read_to_string(format!("tests/json{}.json", i)).unwrap();
+            let tokens = parse_to_tokens(&text).unwrap();
+            let object = Parser::new(&tokens).parse().unwrap();
+
+            let serialized = object.to_string();
+            let tokens = parse_to_tokens(&serialized).unwrap();
+            assert_eq!(Parser::new(&tokens).parse().unwrap(), object);
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        use std::fs::{self, File};
+
+        for i in 1..=3 {
+            let path = format!("tests/json{}.json", i);
+            let text = fs::read_to_string(&path).unwrap();
+            let tokens = parse_to_tokens(&text).unwrap();
+            let expected = Parser::new(&tokens).parse().unwrap().into_owned();
+
+            let value = from_reader(File::open(&path).unwrap()).unwrap();
+            assert_eq!(value, expected);
         }
-        let total_token_time: u128 = token_times.iter().map(|d| d.as_nanos()).sum();
-        let avg_token_time = total_token_time as f64 / token_times.len() as f64;
+    }
 
-        let total_parse_time: u128 = parse_times.iter().map(|d| d.as_nanos()).sum();
-        let avg_parse_time = total_parse_time as f64 / parse_times.len() as f64;
+    #[test]
+    fn test_nesting_limit() {
+        use rust_json::cst::CstDocument;
+        use rust_json::error::JsonError;
+        use rust_json::error::JsonParserError::NestingTooDeep;
+        use rust_json::event::EventReader;
+        use rust_json::parser::MAX_DEPTH;
+        use rust_json::push::PushParser;
+        use rust_json::read::ReadError;
+        use rust_json::recover::Recovered;
+        use rust_json::span::SpannedValue;
+
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        let deepest = nested(MAX_DEPTH);
+        let tokens = parse_to_tokens(&deepest).unwrap();
+        assert!(Parser::new(&tokens).parse().is_ok());
+        assert!(from_reader(deepest.as_bytes()).is_ok());
+        assert!(EventReader::new(&deepest).read_value().is_ok());
+        assert!(CstDocument::parse(&deepest).is_ok());
+        assert!(SpannedValue::parse(&deepest).is_ok());
+        assert!(Recovered::parse(&deepest).is_ok());
 
-        println!("Average token time: {} ns", avg_token_time);
-        println!("Average parse time: {} ns", avg_parse_time);
-        println!("token + parse time:{} ns", avg_parse_time + avg_token_time);
+        let too_deep = JsonError::Parse(NestingTooDeep);
+        for text in [nested(MAX_DEPTH + 1), "[".repeat(100_000), nested(100_000)] {
+            let tokens = parse_to_tokens(&text).unwrap();
+            assert_eq!(Parser::new(&tokens).parse().unwrap_err(), NestingTooDeep);
+            assert!(matches!(
+                from_reader(text.as_bytes()),
+                Err(ReadError::Syntax(e)) if e == too_deep
+            ));
+            assert_eq!(
+                PushParser::new().feed(text.as_bytes()).unwrap_err(),
+                too_deep
+            );
+            assert_eq!(EventReader::new(&text).read_value().unwrap_err(), too_deep);
+            assert_eq!(CstDocument::parse(&text).unwrap_err(), too_deep);
+            assert_eq!(SpannedValue::parse(&text).unwrap_err(), too_deep);
+            let recovered = Recovered::parse(&text);
+            assert_eq!(recovered.diagnostics[0].error, too_deep);
+            #[cfg(feature = "serde")]
+            assert!(
+                rust_json::de::from_str::<rust_json::value::JsonValue>(&text)
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("objects and arrays nested more than 128 levels deep")
+            );
+        }
     }
 }